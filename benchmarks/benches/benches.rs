@@ -2,6 +2,7 @@
 //! - Server benches -> `rewrk` (spawned by server::run_server_bench)
 //! - Parser benches -> Criterion
 //! - Date benches   -> Criterion
+//! - Router benches -> Criterion
 //!
 //! Run from repo root (example):
 //!   cargo bench --manifest-path benchmarks/Cargo.toml -- server:minimal
@@ -10,7 +11,8 @@
 //!                  `axum`, `axum:*`, `axum:name`,
 //!                  `parser`, `parser:*`, `parser:name`,
 //!                  `httparse`, `httparse:*`, `httparse:name`,
-//!                  `date`, `date:*`, `date:name`
+//!                  `date`, `date:*`, `date:name`,
+//!                  `router`, `router:*`, `router:name`
 //!
 //! (inspired by axum's benches.rs)
 
@@ -18,12 +20,15 @@
 mod date;
 #[path = "targets/parser.rs"]
 mod parser;
+#[path = "targets/router.rs"]
+mod router;
 #[path = "targets/server.rs"]
 mod server;
 
 use criterion::Criterion;
 use date::DATE_BENCHES;
 use parser::PARSER_BENCHES;
+use router::ROUTER_BENCHES;
 use server::SERVER_BENCHES;
 use std::fmt;
 
@@ -47,6 +52,7 @@ fn main() {
 
     ran_any |= run_criterion_targets("parser", PARSER_BENCHES, &filters);
     ran_any |= run_criterion_targets("date", DATE_BENCHES, &filters);
+    ran_any |= run_criterion_targets("router", ROUTER_BENCHES, &filters);
 
     if !ran_any && !filters.is_empty() {
         print_no_match(&filters);
@@ -141,5 +147,6 @@ fn print_no_match(filters: &[String]) {
         .map(|b| b.id)
         .chain(PARSER_BENCHES.iter().map(|b| b.id))
         .chain(DATE_BENCHES.iter().map(|b| b.id))
+        .chain(ROUTER_BENCHES.iter().map(|b| b.id))
         .for_each(|id| eprintln!("{id}"));
 }
@@ -2,6 +2,7 @@ use crate::{BenchId, CriterionBench};
 use criterion::measurement::WallTime;
 use criterion::{BenchmarkGroup, BenchmarkId};
 use khttp::Headers;
+use khttp::simd::{self, Backend, Scan};
 
 pub const PARSER_BENCHES: &[CriterionBench] = &[
     CriterionBench {
@@ -20,6 +21,22 @@ pub const PARSER_BENCHES: &[CriterionBench] = &[
         id: BenchId::new("httparse", "long"),
         run: bench_httparse_long,
     },
+    CriterionBench {
+        id: BenchId::new("parser", "segmented"),
+        run: bench_parser_segmented,
+    },
+    CriterionBench {
+        id: BenchId::new("parser", "scan_uri"),
+        run: bench_scan_uri,
+    },
+    CriterionBench {
+        id: BenchId::new("parser", "scan_header_name"),
+        run: bench_scan_header_name,
+    },
+    CriterionBench {
+        id: BenchId::new("parser", "scan_header_value"),
+        run: bench_scan_header_value,
+    },
     CriterionBench {
         id: BenchId::new("parser", "response"),
         run: bench_parser_response,
@@ -102,6 +119,57 @@ fn bench_httparse_long(group: &mut BenchmarkGroup<'_, WallTime>) {
     });
 }
 
+// long request arriving in 16-byte segments, resuming after each one
+fn bench_parser_segmented(group: &mut BenchmarkGroup<'_, WallTime>) {
+    group.bench_function(BenchmarkId::new("parser:segmented", ""), |b| {
+        let raw = make_long_request();
+        let mut parser = khttp::RequestParser::new();
+        b.iter(|| {
+            let mut end = 0;
+            loop {
+                end = (end + 16).min(raw.len());
+                match parser.parse(std::hint::black_box(&raw[..end])).unwrap() {
+                    khttp::ParseStatus::Complete(_) => break,
+                    khttp::ParseStatus::Partial(_) => continue,
+                }
+            }
+        });
+    });
+}
+
+// byte-class scanners, once per backend supported by this CPU
+fn bench_scan_uri(group: &mut BenchmarkGroup<'_, WallTime>) {
+    let raw = make_long_request();
+    let uri = &raw[4..raw.iter().position(|&b| b == b'\r').unwrap()];
+    bench_scan(group, "parser:scan_uri", Scan::Uri, uri);
+}
+
+fn bench_scan_header_name(group: &mut BenchmarkGroup<'_, WallTime>) {
+    bench_scan(
+        group,
+        "parser:scan_header_name",
+        Scan::HeaderName,
+        b"X-Custom-Header-Name-That-Is-Long: value",
+    );
+}
+
+fn bench_scan_header_value(group: &mut BenchmarkGroup<'_, WallTime>) {
+    bench_scan(
+        group,
+        "parser:scan_header_value",
+        Scan::HeaderValue,
+        b"text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n",
+    );
+}
+
+fn bench_scan(group: &mut BenchmarkGroup<'_, WallTime>, id: &str, scan: Scan, input: &[u8]) {
+    for backend in Backend::ALL.into_iter().filter(|b| b.is_supported()) {
+        group.bench_function(BenchmarkId::new(id, backend.name()), |b| {
+            b.iter(|| simd::scan_with(backend, scan, std::hint::black_box(input)));
+        });
+    }
+}
+
 // ---------------------------------------------------------------------
 // utils
 // ---------------------------------------------------------------------
@@ -0,0 +1,76 @@
+use crate::{BenchId, CriterionBench};
+use criterion::{BenchmarkGroup, BenchmarkId, measurement::WallTime};
+use khttp::{Method, Router, RouterBuilder};
+
+pub const ROUTER_BENCHES: &[CriterionBench] = &[
+    CriterionBench {
+        id: BenchId::new("router", "radix"),
+        run: bench_router_radix,
+    },
+    CriterionBench {
+        id: BenchId::new("router", "linear"),
+        run: bench_router_linear,
+    },
+];
+
+// ---------------------------------------------------------------------
+// benchmark functions
+// ---------------------------------------------------------------------
+
+fn bench_router_radix(group: &mut BenchmarkGroup<'_, WallTime>) {
+    let router = make_router();
+    for (name, uri) in URIS {
+        group.bench_function(BenchmarkId::new("router:radix", name), |b| {
+            b.iter(|| {
+                let m = router.match_route(&Method::Get, std::hint::black_box(uri));
+                std::hint::black_box(m.route);
+            });
+        });
+    }
+}
+
+// the previous matcher, which scans every pattern route
+fn bench_router_linear(group: &mut BenchmarkGroup<'_, WallTime>) {
+    let router = make_router();
+    for (name, uri) in URIS {
+        group.bench_function(BenchmarkId::new("router:linear", name), |b| {
+            b.iter(|| {
+                let m = router.match_route_linear(&Method::Get, std::hint::black_box(uri));
+                std::hint::black_box(m.route);
+            });
+        });
+    }
+}
+
+// ---------------------------------------------------------------------
+// utils
+// ---------------------------------------------------------------------
+
+const URIS: [(&str, &str); 4] = [
+    ("param", "/api/v1/resource42/12345"),
+    ("nested", "/api/v2/resource7/99/items/5/comments"),
+    ("wildcard", "/static/css/app/main.css"),
+    ("miss", "/api/v3/unknown/path"),
+];
+
+/// ~400 pattern routes, like a REST API with many resources.
+fn make_router() -> Router<usize> {
+    let mut b = RouterBuilder::new(0);
+    let mut id = 1;
+    let mut add = |b: &mut RouterBuilder<usize>, path: String| {
+        b.add_route(&Method::Get, &path, id);
+        id += 1;
+    };
+    for version in ["v1", "v2"] {
+        for i in 0..50 {
+            let base = format!("/api/{version}/resource{i}");
+            add(&mut b, format!("{base}/:id"));
+            add(&mut b, format!("{base}/:id/items/:item"));
+            add(&mut b, format!("{base}/:id/items/:item/comments"));
+            add(&mut b, format!("{base}/:id<u64>/history"));
+        }
+    }
+    add(&mut b, "/static/**".to_string());
+    add(&mut b, "/api/*/health".to_string());
+    b.build()
+}
@@ -71,7 +71,7 @@ fn main() {
     });
 
     // Lifecycle hook: called after a request is parsed, right before routing
-    app.pre_routing_hook(|req, res| {
+    app.pre_routing_hook(|req, _conn, _ext, res| {
         if req.http_version == 0 {
             let _ = res.send0(&Status::of(505), Headers::close());
             return PreRoutingAction::Drop;
@@ -84,7 +84,7 @@ fn main() {
     });
 
     // Lifecycle hook: called right before the TCP connection is dropped
-    app.connection_teardown_hook(move |_stream, io_result| {
+    app.connection_teardown_hook(move |_stream, _state, io_result| {
         if let Some(e) = io_result.err() {
             eprintln!("tcp socket error: {e}");
         }
@@ -1,11 +1,10 @@
-use std::any::{Any, TypeId};
-use std::collections::HashMap;
 use std::io;
 use std::sync::Arc;
 
+use khttp::middleware::from_fn;
 use khttp::{
-    Headers, Method, PreRoutingAction, Request, RequestContext, ResponseHandle, Server,
-    ServerBuilder, Status,
+    ConnectionInfo, Extensions, Headers, Method::*, Middleware, Next, PreRoutingAction, Request,
+    RequestContext, ResponseHandle, Server, ServerBuilder, Status,
 };
 
 fn main() {
@@ -14,53 +13,49 @@ fn main() {
     // Custom configuration
     app.with_trailing_slash_redirect();
 
-    // Sample "services"
-    let db = Arc::new(Database { data: "123".into() });
+    // Sample "services", the database as typed application state
+    app.state(Database { data: "123".into() });
     let logger = Arc::new(Logger);
 
-    // Reusable middleware chain used by multiple routes
-    let base_layer = Chain::new()
-        .middleware(middlewares::panic_unwind())
-        .inject(logger.clone())
-        .middleware(middlewares::logger());
-
-    app.get("/health")
-        .with(&base_layer)
-        .handle(|_, res| res.ok(Headers::empty(), "ok"));
-
-    app.get("/api/user/:id")
-        .with(&base_layer)
-        .middleware(middlewares::auth("user-secret"))
-        .handle(|ctx, res| {
-            let log = ctx.get::<Arc<Logger>>().unwrap();
-
-            let user_id = match ctx.request.params.get("id").unwrap().parse::<u64>() {
-                Ok(id) => id,
-                Err(_) => {
-                    log.warn("Invalid user id");
-                    return res.send(&Status::BAD_REQUEST, Headers::empty(), "bad id");
-                }
-            };
-            if user_id == 0 {
-                log.err("Simulated panic for id=0");
-                panic!("boom");
+    // Global middleware, runs for every request (fallback route included)
+    app.middleware(middlewares::panic_unwind());
+    app.middleware(middlewares::logger(logger.clone()));
+
+    app.route(Get, "/health", |_, res| res.ok(Headers::empty(), "ok"));
+
+    // Middleware stack for a single route
+    let user_auth: Vec<Arc<dyn Middleware>> = vec![Arc::new(middlewares::Auth::new(
+        "user-secret",
+        logger.clone(),
+    ))];
+    let log = logger.clone();
+    app.route_with(Get, "/api/user/:id", &user_auth, move |ctx, res| {
+        let user_id = match ctx.params.get("id").unwrap().parse::<u64>() {
+            Ok(id) => id,
+            Err(_) => {
+                log.warn("Invalid user id");
+                return res.send(&Status::BAD_REQUEST, Headers::empty(), "bad id");
             }
+        };
+        if user_id == 0 {
+            log.err("Simulated panic for id=0");
+            panic!("boom");
+        }
 
-            res.ok(Headers::empty(), format!("user: {}\n", user_id).as_bytes())
-        });
-
-    app.post("/api/db/call")
-        .with(&base_layer)
-        .middleware(middlewares::auth("db-secret"))
-        .inject(db.clone())
-        .handle(|ctx, res| {
-            let db = ctx.get::<Arc<Database>>().unwrap();
-            let log = ctx.get::<Arc<Logger>>().unwrap();
+        res.ok(Headers::empty(), format!("user: {}\n", user_id).as_bytes())
+    });
 
+    // Middleware for a group of routes
+    app.scope("/api/db", |api| {
+        api.middleware(middlewares::Auth::new("db-secret", logger.clone()));
+        let log = logger.clone();
+        api.route(Post, "/call", move |ctx, res| {
             log.info("querying database...");
+            let db = ctx.state::<Database>().unwrap();
             let result = format!("data = {}\n", db.data);
             res.ok(Headers::empty(), result.as_bytes())
         });
+    });
 
     app.serve().unwrap();
 }
@@ -70,27 +65,11 @@ fn main() {
 // -------------------------------------------------------------------------
 
 pub trait ServerBuilderExt {
-    fn route(&mut self, method: Method, path: &'static str) -> RouteBuilder<'_>;
-    fn get(&mut self, path: &'static str) -> RouteBuilder<'_> {
-        self.route(Method::Get, path)
-    }
-    fn post(&mut self, path: &'static str) -> RouteBuilder<'_> {
-        self.route(Method::Post, path)
-    }
     fn with_trailing_slash_redirect(&mut self) -> &mut Self;
     fn serve(self) -> io::Result<()>;
 }
 
 impl ServerBuilderExt for ServerBuilder {
-    fn route(&mut self, method: Method, path: &'static str) -> RouteBuilder<'_> {
-        RouteBuilder {
-            app: self,
-            method,
-            path,
-            middleware: Vec::new(),
-        }
-    }
-
     /// Redirects "/foo/" -> "/foo"
     fn with_trailing_slash_redirect(&mut self) -> &mut Self {
         self.pre_routing_hook(trailing_slash_redirect());
@@ -107,169 +86,59 @@ impl ServerBuilderExt for ServerBuilder {
     }
 }
 
-pub struct HandlerContext<'r> {
-    pub request: RequestContext<'r>,
-    pub extensions: HashMap<TypeId, Box<dyn Any>>,
-}
-
-impl<'r> HandlerContext<'r> {
-    pub fn get<T: 'static>(&self) -> Option<&T> {
-        self.extensions
-            .get(&TypeId::of::<T>())
-            .and_then(|b| b.downcast_ref::<T>())
-    }
-
-    pub fn insert<T: 'static>(&mut self, val: T) {
-        self.extensions.insert(TypeId::of::<T>(), Box::new(val));
-    }
-}
-
-pub type Handler = dyn Fn(HandlerContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync;
-pub type MiddlewareFn = dyn Fn(Box<Handler>) -> Box<Handler>;
-
-#[derive(Default)]
-pub struct Chain {
-    mws: Vec<Arc<MiddlewareFn>>,
-}
-
-impl Chain {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    pub fn middleware<F>(mut self, mw: F) -> Self
-    where
-        F: Fn(Box<Handler>) -> Box<Handler> + 'static,
-    {
-        self.mws.push(Arc::new(mw));
-        self
-    }
-
-    pub fn inject<T>(self, val: T) -> Self
-    where
-        T: 'static + Send + Sync + Clone,
-    {
-        self.middleware(inject(val))
-    }
-
-    fn extend_into(&self, v: &mut Vec<Arc<MiddlewareFn>>) {
-        v.extend(self.mws.iter().cloned());
-    }
-}
-
-pub struct RouteBuilder<'a> {
-    app: &'a mut ServerBuilder,
-    method: Method,
-    path: &'static str,
-    middleware: Vec<Arc<MiddlewareFn>>,
-}
-
-impl<'a> RouteBuilder<'a> {
-    pub fn middleware<F>(mut self, mw: F) -> Self
-    where
-        F: Fn(Box<Handler>) -> Box<Handler> + Send + Sync + 'static,
-    {
-        self.middleware.push(Arc::new(mw));
-        self
-    }
-
-    pub fn inject<T>(self, val: T) -> Self
-    where
-        T: 'static + Send + Sync + Clone,
-    {
-        self.middleware(inject(val))
-    }
+mod middlewares {
+    use super::*;
 
-    pub fn with(mut self, chain: &Chain) -> Self {
-        chain.extend_into(&mut self.middleware);
-        self
+    pub struct Auth {
+        secret: &'static str,
+        log: Arc<Logger>,
     }
 
-    pub fn handle<F>(self, handler: F)
-    where
-        F: Fn(HandlerContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
-    {
-        let mut next: Box<Handler> = Box::new(handler);
-        for mw in self.middleware.into_iter().rev() {
-            next = (mw)(next);
+    impl Auth {
+        pub fn new(secret: &'static str, log: Arc<Logger>) -> Self {
+            Auth { secret, log }
         }
-
-        let route = move |ctx: RequestContext, res: &mut ResponseHandle| {
-            let ctx = HandlerContext {
-                request: ctx,
-                extensions: HashMap::new(),
-            };
-            next(ctx, res)
-        };
-
-        self.app.route(self.method, self.path, route);
     }
-}
 
-// Helper middleware: inject a value into the HandlerContext
-fn inject<T>(val: T) -> impl Fn(Box<Handler>) -> Box<Handler>
-where
-    T: 'static + Send + Sync + Clone,
-{
-    move |next| {
-        Box::new({
-            let val = val.clone();
-            move |mut ctx, res| {
-                ctx.insert(val.clone());
-                next(ctx, res)
+    impl Middleware for Auth {
+        fn handle(
+            &self,
+            ctx: RequestContext,
+            res: &mut ResponseHandle,
+            next: Next,
+        ) -> io::Result<()> {
+            if ctx.headers.get("authorization") == Some(self.secret.as_bytes()) {
+                next.run(ctx, res)
+            } else {
+                self.log.warn("blocked unauthorized request");
+                res.send(&Status::of(401), Headers::empty(), b"unauthorized")
             }
-        })
-    }
-}
-
-mod middlewares {
-    use super::*;
-
-    pub fn auth(secret: &'static str) -> impl Fn(Box<Handler>) -> Box<Handler> {
-        move |next| {
-            Box::new(move |ctx, res| {
-                if ctx.request.headers.get("authorization") == Some(secret.as_bytes()) {
-                    next(ctx, res)
-                } else {
-                    if let Some(log) = ctx.get::<Arc<Logger>>() {
-                        log.warn("blocked unauthorized request");
-                    }
-                    res.send(&Status::of(401), Headers::empty(), b"unauthorized")
-                }
-            })
         }
     }
 
-    pub fn logger() -> impl Fn(Box<Handler>) -> Box<Handler> {
-        |next| {
-            Box::new(move |ctx, res| {
-                let ip = ctx
-                    .request
-                    .get_stream()
-                    .peer_addr()
-                    .map(|x| x.ip().to_string())
-                    .unwrap_or_else(|_| "<unknown>".into());
-
-                if let Some(log) = ctx.get::<Arc<Logger>>() {
-                    log.info(&format!(
-                        "[ip: {}] {} {}",
-                        ip,
-                        ctx.request.method,
-                        ctx.request.uri.as_str()
-                    ));
-                }
-                next(ctx, res)
-            })
-        }
+    pub fn logger(log: Arc<Logger>) -> impl Middleware {
+        from_fn(move |ctx, res, next| {
+            let ip = ctx.connection().peer_addr().ip();
+            let route = ctx.route_pattern().unwrap_or("<fallback>");
+            log.info(&format!(
+                "[ip: {}] {} {} (route: {})",
+                ip,
+                ctx.method,
+                ctx.uri.as_str(),
+                route
+            ));
+            next.run(ctx, res)
+        })
     }
 
-    pub fn panic_unwind() -> impl Fn(Box<Handler>) -> Box<Handler> {
-        |next| {
-            Box::new(move |ctx, res| {
-                let result =
-                    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| next(ctx, res)));
+    pub fn panic_unwind() -> impl Middleware {
+        from_fn(|ctx, res, next| {
+            let result =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| next.run(ctx, res)));
 
-                if let Err(panic_info) = result {
+            match result {
+                Ok(result) => result,
+                Err(panic_info) => {
                     let msg = if let Some(s) = panic_info.downcast_ref::<&str>() {
                         *s
                     } else if let Some(s) = panic_info.downcast_ref::<String>() {
@@ -280,16 +149,16 @@ mod middlewares {
 
                     eprintln!("[panic] handler panicked: {msg}");
                     res.send(&Status::of(500), Headers::empty(), b"internal error")
-                } else {
-                    Ok(())
                 }
-            })
-        }
+            }
+        })
     }
 }
 
-fn trailing_slash_redirect() -> impl Fn(&mut Request<'_>, &mut ResponseHandle) -> PreRoutingAction {
-    move |request, response| {
+fn trailing_slash_redirect(
+) -> impl Fn(&mut Request<'_>, &ConnectionInfo, &mut Extensions, &mut ResponseHandle) -> PreRoutingAction
+{
+    move |request, _, _, response| {
         let path = request.uri.path();
         if path != "/" && path.ends_with('/') {
             let trimmed = path.trim_end_matches('/');
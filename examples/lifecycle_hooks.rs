@@ -10,7 +10,8 @@ use std::{
     time::Instant,
 };
 
-use khttp::{ConnectionSetupAction, Headers, Method::*, PreRoutingAction, Server, Status};
+use khttp::middleware::from_fn;
+use khttp::{ConnectionSetupAction, Extensions, Headers, Method::*, Server, Status};
 
 fn main() {
     let mut app = Server::builder("0.0.0.0:8080").unwrap();
@@ -49,52 +50,45 @@ fn main() {
             peer.active_connections += 1;
         }
 
-        // update connection table
+        // update connection table, and keep the stats with the connection itself
         let fd = stream.as_raw_fd();
+        let conn = Arc::new(ConnectionStats::new(peer_addr));
         {
-            let conn = ConnectionInfo::new(peer_addr);
             let mut lock = conn_table.write().unwrap();
-            lock.connections.insert(fd, conn);
+            lock.connections.insert(fd, conn.clone());
         }
+        let mut state = Extensions::new();
+        state.insert(conn);
 
-        ConnectionSetupAction::Proceed(stream)
+        ConnectionSetupAction::ProceedWith(stream, state)
     });
 
-    let conn_table = conn_table_arc.clone();
-    app.pre_routing_hook(move |_req, res| {
-        let fd = res.get_stream().as_raw_fd();
-
-        // update connection table: increment request counter
-        {
-            let lock = conn_table.read().unwrap();
-            lock.connections
-                .get(&fd)
-                .map(|conn| conn.request_count.fetch_add(1, Ordering::Relaxed));
+    // count requests per connection
+    app.middleware(from_fn(|mut ctx, res, next| {
+        if let Some(conn) = ctx.connection_state().get::<Arc<ConnectionStats>>() {
+            conn.request_count.fetch_add(1, Ordering::Relaxed);
         }
-
-        PreRoutingAction::Proceed
-    });
+        next.run(ctx, res)
+    }));
 
     let peer_table = peer_table_arc.clone();
     let conn_table = conn_table_arc.clone();
-    app.connection_teardown_hook(move |stream, io_result| {
+    app.connection_teardown_hook(move |stream, state, io_result| {
         if let Err(e) = io_result {
             eprintln!("socket err: {e}");
         };
 
-        let fd = stream.as_raw_fd();
-
         // update connection table: remove the connection
-        let conn_info = {
+        {
             let mut lock = conn_table.write().unwrap();
-            lock.connections.remove(&fd)
-        };
+            lock.connections.remove(&stream.as_raw_fd());
+        }
 
         // update peer table: decrement active connection counter
-        if let Some(conn_info) = conn_info {
+        if let Some(conn) = state.get::<Arc<ConnectionStats>>() {
             let mut lock = peer_table.write().unwrap();
             lock.peers
-                .entry(conn_info.peer_addr.ip())
+                .entry(conn.peer_addr.ip())
                 .and_modify(|x| x.active_connections = x.active_connections.saturating_sub(1));
         }
     });
@@ -196,7 +190,7 @@ struct PeerInfo {
 
 #[derive(Default)]
 struct ConnectionTable {
-    connections: HashMap<i32, ConnectionInfo>,
+    connections: HashMap<i32, Arc<ConnectionStats>>,
 }
 
 impl ConnectionTable {
@@ -212,15 +206,15 @@ impl ConnectionTable {
     }
 }
 
-struct ConnectionInfo {
+struct ConnectionStats {
     peer_addr: SocketAddr,
     request_count: AtomicU64,
     conn_start: Instant,
 }
 
-impl ConnectionInfo {
+impl ConnectionStats {
     fn new(peer_addr: SocketAddr) -> Self {
-        ConnectionInfo {
+        ConnectionStats {
             peer_addr,
             request_count: AtomicU64::new(0),
             conn_start: Instant::now(),
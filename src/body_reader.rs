@@ -1,3 +1,4 @@
+use crate::parser::check_framing;
 use crate::Headers;
 use std::cmp::min;
 use std::io::{self, BufRead, BufReader, ErrorKind, Read};
@@ -14,30 +15,45 @@ enum BodyEncoding<'a, R> {
 }
 
 impl<'a, R: Read> BodyReader<'a, R> {
+    /// Chunked transfer-encoding takes precedence over content-length (RFC 9112 6.3),
+    /// [`Request::parse`](crate::Request::parse) rejects requests with both.
     pub fn from_request(leftover: &'a [u8], stream: R, headers: &Headers) -> Self {
-        if let Some(content_len) = headers.get_content_length() {
+        if headers.is_transfer_encoding_chunked() {
+            Self::new_chunked(leftover, stream)
+        } else if let Some(content_len) = headers.get_content_length() {
             if content_len > 0 {
                 Self::new_fixed(leftover, stream, content_len as usize)
             } else {
                 Self::new_empty(stream)
             }
-        } else if headers.is_transfer_encoding_chunked() {
-            Self::new_chunked(leftover, stream)
         } else {
             Self::new_empty(stream)
         }
     }
 
+    /// Like [`BodyReader::from_request`], but rejects ambiguous framing the way strict
+    /// [`Request::parse`](crate::Request::parse) does: `content-length` together with
+    /// `transfer-encoding`, or a `transfer-encoding` whose final coding isn't `chunked`.
+    /// Fails with [`ErrorKind::InvalidData`].
+    pub fn from_request_strict(
+        leftover: &'a [u8],
+        stream: R,
+        headers: &Headers,
+    ) -> io::Result<Self> {
+        check_framing(headers).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(Self::from_request(leftover, stream, headers))
+    }
+
     #[cfg(feature = "client")]
     pub fn from_response(leftover: &'a [u8], stream: R, headers: &Headers) -> Self {
-        if let Some(content_len) = headers.get_content_length() {
+        if headers.is_transfer_encoding_chunked() {
+            Self::new_chunked(leftover, stream)
+        } else if let Some(content_len) = headers.get_content_length() {
             if content_len > 0 {
                 Self::new_fixed(leftover, stream, content_len as usize)
             } else {
                 Self::new_empty(stream)
             }
-        } else if headers.is_transfer_encoding_chunked() {
-            Self::new_chunked(leftover, stream)
         } else {
             Self::new_eof(leftover, stream)
         }
@@ -261,11 +277,25 @@ impl<'a, R: Read> ChunkedReader<'a, R> {
         if self.inner.read_line(&mut line)? == 0 {
             return Err(io::Error::new(ErrorKind::UnexpectedEof, "chunk size eof"));
         }
+        let line = match line.strip_suffix("\r\n") {
+            Some(line) => line,
+            None if line.ends_with('\n') => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "bare LF after chunk size",
+                ))
+            }
+            None => return Err(io::Error::new(ErrorKind::UnexpectedEof, "chunk size eof")),
+        };
+        // chunk-size [ BWS ";" chunk-ext ], from_str_radix alone would also accept a sign
         let hex = line
             .split(';')
             .next()
             .unwrap_or("")
-            .trim_end_matches(['\r', '\n']);
+            .trim_end_matches([' ', '\t']);
+        if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(io::Error::new(ErrorKind::InvalidData, "invalid chunk size"));
+        }
         self.remaining_in_chunk = usize::from_str_radix(hex, 16)
             .map_err(|_| io::Error::new(ErrorKind::InvalidData, "invalid chunk size"))?;
         self.state = if self.remaining_in_chunk == 0 {
@@ -53,6 +53,15 @@ impl<'r> ClientResponseHandle<'r> {
         &mut self.body
     }
 
+    /// Response body with `content-encoding` (gzip / deflate) undone.
+    #[cfg(feature = "compression")]
+    pub fn decoded_body(
+        &mut self,
+        max_size: u64,
+    ) -> io::Result<crate::BodyDecoder<&mut BodyReader<'r, TcpStream>>> {
+        crate::BodyDecoder::new(&mut self.body, &mut self.headers, max_size)
+    }
+
     pub fn stream(&self) -> &TcpStream {
         self.body.inner()
     }
@@ -0,0 +1,388 @@
+use crate::header::AcceptEncoding;
+use crate::printer::probe_body;
+use crate::{Headers, HttpPrinter, Status};
+use flate2::bufread::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::read::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{self, BufRead, Cursor, ErrorKind, Read, Write};
+
+const DEFAULT_MIN_SIZE: usize = 1024;
+const DEFAULT_LEVEL: u32 = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentCoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CompressionConfig {
+    min_size: usize,
+    level: u32,
+    gzip: bool,
+    deflate: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+            level: DEFAULT_LEVEL,
+            gzip: true,
+            deflate: true,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bodies smaller than this (in bytes) are sent uncompressed.
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Compression level, 0 (none) to 9 (best).
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    pub fn deflate(mut self, enabled: bool) -> Self {
+        self.deflate = enabled;
+        self
+    }
+}
+
+/// Per-request compression state, kept on the `ResponseHandle`.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ResponseCompression {
+    config: Option<CompressionConfig>,
+    coding: Option<ContentCoding>,
+}
+
+impl ResponseCompression {
+    pub(crate) fn negotiate(config: Option<CompressionConfig>, request: &Headers) -> Self {
+        let coding = config.and_then(|c| negotiate_coding(&c, request));
+        Self { config, coding }
+    }
+
+    pub(crate) fn disable(&mut self) {
+        self.config = None;
+        self.coding = None;
+    }
+
+    #[inline]
+    pub(crate) fn is_active(&self) -> bool {
+        self.config.is_some()
+    }
+
+    fn level(&self) -> Compression {
+        Compression::new(self.config.map_or(DEFAULT_LEVEL, |c| c.level))
+    }
+
+    /// Returns `None` if the response is not eligible for compression. Otherwise returns the
+    /// response headers to send (`vary` added, framing adjusted) and the negotiated coding, if any.
+    fn prepare<'h>(
+        &self,
+        status: &Status,
+        headers: &Headers<'h>,
+        body_len: u64,
+    ) -> Option<(Headers<'h>, Option<ContentCoding>)> {
+        let config = self.config?;
+        if body_len < config.min_size as u64
+            || !status_allows_compression(status)
+            || headers.get(Headers::CONTENT_ENCODING).is_some()
+            || !headers
+                .get(Headers::CONTENT_TYPE)
+                .is_some_and(is_compressible_content_type)
+        {
+            return None;
+        }
+
+        let mut headers = headers.clone();
+        if !varies_on_accept_encoding(&headers) {
+            headers.add(Headers::VARY, &b"accept-encoding"[..]);
+        }
+        if let Some(coding) = self.coding {
+            headers.add(Headers::CONTENT_ENCODING, coding.as_str().as_bytes());
+            headers.set_content_length(None);
+        }
+        Some((headers, self.coding))
+    }
+}
+
+pub(crate) fn write_response_bytes<W: Write>(
+    writer: W,
+    state: &ResponseCompression,
+    status: &Status,
+    headers: &Headers,
+    body: &[u8],
+) -> io::Result<()> {
+    match state.prepare(status, headers, body.len() as u64) {
+        Some((headers, Some(coding))) => {
+            let mut compressed = Vec::with_capacity(body.len() / 2);
+            encoder(coding, state.level(), body).read_to_end(&mut compressed)?;
+            HttpPrinter::write_response_bytes(writer, status, &headers, &compressed)
+        }
+        Some((headers, None)) => HttpPrinter::write_response_bytes(writer, status, &headers, body),
+        None => HttpPrinter::write_response_bytes(writer, status, headers, body),
+    }
+}
+
+pub(crate) fn write_response<W: Write, R: Read>(
+    writer: W,
+    state: &ResponseCompression,
+    status: &Status,
+    headers: &Headers,
+    mut body: R,
+) -> io::Result<()> {
+    let min_size = state.config.map_or(0, |c| c.min_size);
+
+    // without content-length, probe the body to find out if it reaches the size threshold
+    let (prefix, body_len) = match headers.get_content_length() {
+        Some(cl) => (Vec::new(), cl),
+        None if headers.is_transfer_encoding_chunked() => (Vec::new(), u64::MAX),
+        None => {
+            let (prefix, complete) = probe_body(&mut body, min_size)?;
+            let len = if complete {
+                prefix.len() as u64
+            } else {
+                u64::MAX
+            };
+            (prefix, len)
+        }
+    };
+    let body = Cursor::new(prefix).chain(body);
+
+    match state.prepare(status, headers, body_len) {
+        Some((headers, Some(coding))) => {
+            let encoded = encoder(coding, state.level(), body);
+            HttpPrinter::write_response(writer, status, &headers, encoded)
+        }
+        Some((headers, None)) => HttpPrinter::write_response(writer, status, &headers, body),
+        None => HttpPrinter::write_response(writer, status, headers, body),
+    }
+}
+
+enum Encoder<R: Read> {
+    Gzip(GzEncoder<R>),
+    Deflate(ZlibEncoder<R>),
+}
+
+fn encoder<R: Read>(coding: ContentCoding, level: Compression, reader: R) -> Encoder<R> {
+    match coding {
+        ContentCoding::Gzip => Encoder::Gzip(GzEncoder::new(reader, level)),
+        ContentCoding::Deflate => Encoder::Deflate(ZlibEncoder::new(reader, level)),
+    }
+}
+
+impl<R: Read> Read for Encoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(e) => e.read(buf),
+            Encoder::Deflate(e) => e.read(buf),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------
+// BODY DECODING
+// -------------------------------------------------------------------------
+
+/// Decodes a `content-encoding: gzip / deflate` body, enforcing a limit on the decoded size.
+pub struct BodyDecoder<R: BufRead> {
+    inner: Decoder<R>,
+    max_size: u64,
+    total: u64,
+    limit_exceeded: bool,
+}
+
+enum Decoder<R: BufRead> {
+    Identity(R),
+    Gzip(GzDecoder<R>),
+    Zlib(ZlibDecoder<R>),
+    Deflate(DeflateDecoder<R>),
+}
+
+impl<R: BufRead> BodyDecoder<R> {
+    /// Picks the decoder from `content-encoding` and removes the `content-encoding` and
+    /// `content-length` headers, as they no longer describe the decoded body.
+    ///
+    /// Fails with `ErrorKind::Unsupported` for unknown or stacked codings.
+    pub fn new(mut body: R, headers: &mut Headers, max_size: u64) -> io::Result<Self> {
+        let mut coding: Option<ContentCoding> = None;
+        for (_, value) in headers.get_all(Headers::CONTENT_ENCODING) {
+            for token in value.split(|&b| b == b',').map(|t| t.trim_ascii()) {
+                let next = if token.is_empty() || token.eq_ignore_ascii_case(b"identity") {
+                    continue;
+                } else if token.eq_ignore_ascii_case(b"gzip")
+                    || token.eq_ignore_ascii_case(b"x-gzip")
+                {
+                    ContentCoding::Gzip
+                } else if token.eq_ignore_ascii_case(b"deflate") {
+                    ContentCoding::Deflate
+                } else {
+                    return Err(io::Error::new(
+                        ErrorKind::Unsupported,
+                        "unsupported content-encoding",
+                    ));
+                };
+                if coding.replace(next).is_some() {
+                    return Err(io::Error::new(
+                        ErrorKind::Unsupported,
+                        "stacked content-encodings",
+                    ));
+                }
+            }
+        }
+
+        let inner = match coding {
+            None => Decoder::Identity(body),
+            Some(ContentCoding::Gzip) => Decoder::Gzip(GzDecoder::new(body)),
+            // "deflate" is zlib-wrapped per RFC 9110, but some clients send raw deflate
+            Some(ContentCoding::Deflate) => match body.fill_buf()? {
+                [cmf, flg, ..] if is_zlib_header(*cmf, *flg) => {
+                    Decoder::Zlib(ZlibDecoder::new(body))
+                }
+                _ => Decoder::Deflate(DeflateDecoder::new(body)),
+            },
+        };
+        if coding.is_some() {
+            headers.remove(Headers::CONTENT_ENCODING);
+            headers.remove(Headers::CONTENT_LENGTH);
+        }
+
+        Ok(Self {
+            inner,
+            max_size,
+            total: 0,
+            limit_exceeded: false,
+        })
+    }
+
+    /// Whether reading failed because the decoded body was larger than `max_size`.
+    pub fn limit_exceeded(&self) -> bool {
+        self.limit_exceeded
+    }
+
+    pub fn string(&mut self) -> io::Result<String> {
+        let mut buf = String::new();
+        self.read_to_string(&mut buf).map(|_| buf)
+    }
+
+    pub fn vec(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.read_to_end(&mut buf).map(|_| buf)
+    }
+}
+
+impl<R: BufRead> Read for BodyDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = match &mut self.inner {
+            Decoder::Identity(r) => r.read(buf)?,
+            Decoder::Gzip(r) => r.read(buf)?,
+            Decoder::Zlib(r) => r.read(buf)?,
+            Decoder::Deflate(r) => r.read(buf)?,
+        };
+        self.total += n as u64;
+        if self.total > self.max_size {
+            self.limit_exceeded = true;
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "decoded body exceeds size limit",
+            ));
+        }
+        Ok(n)
+    }
+}
+
+fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    cmf & 0x0F == 8 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
+
+// -------------------------------------------------------------------------
+// NEGOTIATION
+// -------------------------------------------------------------------------
+
+/// Picks the best supported coding from `accept-encoding`, preferring gzip on ties.
+fn negotiate_coding(config: &CompressionConfig, request: &Headers) -> Option<ContentCoding> {
+    let accept = request.typed::<AcceptEncoding>()?;
+    let q = |coding: &str| {
+        accept
+            .0
+            .iter()
+            .find(|i| i.item == coding)
+            .map(|i| i.quality)
+    };
+    let wildcard_q = q("*");
+
+    let gzip_q = if config.gzip {
+        q("gzip").or_else(|| q("x-gzip")).or(wildcard_q)
+    } else {
+        None
+    };
+    let deflate_q = if config.deflate {
+        q("deflate").or(wildcard_q)
+    } else {
+        None
+    };
+
+    match (gzip_q.unwrap_or(0), deflate_q.unwrap_or(0)) {
+        (0, 0) => None,
+        (g, d) if g >= d => Some(ContentCoding::Gzip),
+        _ => Some(ContentCoding::Deflate),
+    }
+}
+
+fn varies_on_accept_encoding(headers: &Headers) -> bool {
+    headers.get_all(Headers::VARY).any(|(_, v)| {
+        v.split(|&b| b == b',')
+            .map(|t| t.trim_ascii())
+            .any(|t| t == b"*" || t.eq_ignore_ascii_case(Headers::ACCEPT_ENCODING.as_bytes()))
+    })
+}
+
+fn status_allows_compression(status: &Status) -> bool {
+    !matches!(status.code, 100..=199 | 204 | 206 | 304)
+}
+
+fn is_compressible_content_type(value: &[u8]) -> bool {
+    let mime = value
+        .split(|&b| b == b';')
+        .next()
+        .unwrap_or_default()
+        .trim_ascii();
+    let mime = mime.to_ascii_lowercase();
+
+    if mime.starts_with(b"text/") || mime.ends_with(b"+json") || mime.ends_with(b"+xml") {
+        return true;
+    }
+    matches!(
+        &mime[..],
+        b"application/json"
+            | b"application/javascript"
+            | b"application/xml"
+            | b"application/wasm"
+            | b"application/x-www-form-urlencoded"
+            | b"image/svg+xml"
+            | b"image/x-icon"
+    )
+}
@@ -0,0 +1,231 @@
+//! Request cookies (RFC 6265 `cookie` header) and a `set-cookie` builder.
+
+use crate::{date, Headers};
+use std::fmt;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+/// Read-only view of the cookies sent with a request, across all `cookie` headers.
+#[derive(Debug, Clone)]
+pub struct Cookies<'h> {
+    values: Vec<&'h str>,
+}
+
+impl<'h> Cookies<'h> {
+    pub fn new(headers: &'h Headers<'_>) -> Self {
+        let values = headers
+            .get_all(Headers::COOKIE)
+            .filter_map(|(_, v)| std::str::from_utf8(v).ok())
+            .collect();
+        Cookies { values }
+    }
+
+    /// Value of the first cookie called `name` (surrounding quotes removed).
+    pub fn get(&self, name: &str) -> Option<&'h str> {
+        self.iter().find_map(|(k, v)| (k == name).then_some(v))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'h str, &'h str)> + '_ {
+        self.values
+            .iter()
+            .flat_map(|v| v.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let name = name.trim();
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                (!name.is_empty()).then_some((name, value))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Browsers require `Secure` with `SameSite=None`, so it is added automatically.
+    None,
+}
+
+impl SameSite {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// Builder for a `set-cookie` header value:
+///
+/// ```
+/// # use khttp::cookie::{SameSite, SetCookie};
+/// # use khttp::Headers;
+/// let mut headers = Headers::new();
+/// SetCookie::new("session", "abc123")
+///     .unwrap()
+///     .path("/")
+///     .http_only(true)
+///     .same_site(SameSite::Lax)
+///     .append_to(&mut headers);
+/// assert_eq!(
+///     headers.get("set-cookie"),
+///     Some(&b"session=abc123; Path=/; HttpOnly; SameSite=Lax"[..])
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<i64>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl SetCookie {
+    /// `InvalidInput` error if `name` is not a token or `value` contains characters
+    /// not allowed in a cookie value (whitespace, `"`, `,`, `;`, `\` or controls).
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, value: V) -> io::Result<Self> {
+        let name = name.into();
+        let value = value.into();
+        if name.is_empty() || !name.bytes().all(is_token_char) {
+            return Err(invalid_input("invalid cookie name"));
+        }
+        if !value.bytes().all(is_cookie_octet) {
+            return Err(invalid_input("invalid cookie value"));
+        }
+        Ok(SetCookie {
+            name,
+            value,
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        })
+    }
+
+    /// A cookie that tells the client to delete `name` (empty value, expired).
+    /// Path and domain must match the ones the cookie was set with.
+    pub fn removal<N: Into<String>>(name: N) -> io::Result<Self> {
+        Ok(Self::new(name, "")?
+            .max_age(Duration::ZERO)
+            .expires(SystemTime::UNIX_EPOCH))
+    }
+
+    /// `;` and control characters are dropped, so the value can't inject attributes.
+    pub fn path<S: Into<String>>(mut self, path: S) -> Self {
+        self.path = Some(attribute_value(path.into()));
+        self
+    }
+
+    /// `;` and control characters are dropped, so the value can't inject attributes.
+    pub fn domain<S: Into<String>>(mut self, domain: S) -> Self {
+        self.domain = Some(attribute_value(domain.into()));
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age.as_secs().min(i64::MAX as u64) as i64);
+        self
+    }
+
+    pub fn expires(mut self, time: SystemTime) -> Self {
+        self.expires = Some(time);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Adds a separate `set-cookie` header (they must never be combined into one).
+    pub fn append_to(&self, headers: &mut Headers) {
+        headers.add(Headers::SET_COOKIE, self.to_string().into_bytes());
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={max_age}")?;
+        }
+        if let Some(expires) = self.expires {
+            let value = date::system_time_to_http_date(expires);
+            // date values are always ASCII
+            write!(
+                f,
+                "; Expires={}",
+                std::str::from_utf8(&value).unwrap_or_default()
+            )?;
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// RFC 6265 cookie-octet
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+fn attribute_value(mut value: String) -> String {
+    value.retain(|c| c != ';' && !c.is_control());
+    value
+}
+
+fn invalid_input(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
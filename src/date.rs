@@ -23,6 +23,8 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 const HEADER_TEMPLATE: [u8; 37] = *b"date: Mon, 00 Jan 0000 00:00:00 GMT\r\n";
 const DATE_LEN: usize = HEADER_TEMPLATE.len();
 
@@ -106,68 +108,14 @@ fn format_http_date(buf: &mut [u8; DATE_LEN], secs_since_epoch: i64) {
     const SECS_PER_HOUR: i64 = 3600;
     const SECS_PER_DAY: i64 = 86400;
 
-    const LEAPOCH: i64 = 11017;
-    const DAYS_PER_400Y: i64 = 365 * 400 + 97;
-    const DAYS_PER_100Y: i64 = 365 * 100 + 24;
-    const DAYS_PER_4Y: i64 = 365 * 4 + 1;
-
-    let (days_total, secs_of_day) = {
-        let (d, r) = divmod_i64(secs_since_epoch, SECS_PER_DAY);
-        (d - LEAPOCH, r)
-    };
-
-    let mut wday = (3 + days_total).rem_euclid(7);
-    if wday <= 0 {
-        wday += 7;
-    }
-    let woff = ((wday as usize) - 1) * 3;
-
-    let qc_cycles = days_total.div_euclid(DAYS_PER_400Y);
-    let mut remdays = days_total.rem_euclid(DAYS_PER_400Y);
-
-    let mut c_cycles = remdays / DAYS_PER_100Y;
-    if c_cycles == 4 {
-        c_cycles -= 1;
-    }
-    remdays -= c_cycles * DAYS_PER_100Y;
-
-    let mut q_cycles = remdays / DAYS_PER_4Y;
-    if q_cycles == 25 {
-        q_cycles -= 1;
-    }
-    remdays -= q_cycles * DAYS_PER_4Y;
-
-    let mut remyears = remdays / 365;
-    if remyears == 4 {
-        remyears -= 1;
-    }
-    remdays -= remyears * 365;
-
-    let mut year = 2000 + remyears + 4 * q_cycles + 100 * c_cycles + 400 * qc_cycles;
-
     const WDAY_STRS: &[u8; 21] = b"MonTueWedThuFriSatSun";
     const MON_STRS: &[u8; 36] = b"JanFebMarAprMayJunJulAugSepOctNovDec";
-    const MONTHS: [i64; 12] = [31, 30, 31, 30, 31, 31, 30, 31, 30, 31, 31, 29];
-
-    let mut mon_idx = 0;
-    let mut rd = remdays;
-    while mon_idx < 12 {
-        let ml = MONTHS[mon_idx];
-        if rd < ml {
-            break;
-        }
-        rd -= ml;
-        mon_idx += 1;
-    }
 
-    let mday = (rd + 1) as u8;
+    let (days, secs_of_day) = divmod_i64(secs_since_epoch, SECS_PER_DAY);
+    let (year, mon, mday) = civil_from_days(days);
 
-    let mut mon = mon_idx + 3;
-    if mon > 12 {
-        year += 1;
-        mon -= 12;
-    }
-    let mon = mon as u8;
+    // 1970-01-01 was a Thursday
+    let woff = (days + 3).rem_euclid(7) as usize * 3;
 
     let (hour, rem) = divmod_i64(secs_of_day, SECS_PER_HOUR);
     let (min, sec) = divmod_i64(rem, SECS_PER_MIN);
@@ -177,7 +125,7 @@ fn format_http_date(buf: &mut [u8; DATE_LEN], secs_since_epoch: i64) {
 
     buf[6..9].copy_from_slice(&WDAY_STRS[woff..woff + 3]);
 
-    write_2d(&mut buf[11..13], mday);
+    write_2d(&mut buf[11..13], mday as u8);
 
     let moff = ((mon as usize) - 1) * 3;
     buf[14..17].copy_from_slice(&MON_STRS[moff..moff + 3]);
@@ -211,3 +159,201 @@ fn write_4d(buf: &mut [u8], v: u16) {
     buf[2] = b'0' + ((v / 10 % 10) as u8);
     buf[3] = b'0' + ((v % 10) as u8);
 }
+
+// -------------------------------------------------------------------------
+// VALUES & PARSING
+// -------------------------------------------------------------------------
+
+const VALUE_LEN: usize = 29;
+
+/// Formats `seconds` as an IMF-fixdate header value, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn get_date_value_from_secs(seconds: i64) -> [u8; VALUE_LEN] {
+    let header = get_date_from_secs(seconds);
+    let mut buf = [0u8; VALUE_LEN];
+    buf.copy_from_slice(&header[6..6 + VALUE_LEN]);
+    buf
+}
+
+/// Formats `time` as an IMF-fixdate header value, sub-second precision is truncated.
+pub fn system_time_to_http_date(time: SystemTime) -> [u8; VALUE_LEN] {
+    get_date_value_from_secs(system_time_to_secs(time))
+}
+
+/// Parses an HTTP-date (RFC 9110 5.6.7) into seconds since the unix epoch.
+/// Accepts IMF-fixdate as well as the obsolete RFC 850 and asctime formats.
+pub fn parse_http_date(value: &[u8]) -> Option<i64> {
+    let value = value.trim_ascii();
+    match value.iter().position(|&b| b == b',') {
+        Some(3) => parse_imf_fixdate(value),
+        Some(_) => parse_rfc850(value),
+        None => parse_asctime(value),
+    }
+}
+
+/// Like [`parse_http_date`], but returns a [`SystemTime`].
+pub fn parse_http_date_to_system_time(value: &[u8]) -> Option<SystemTime> {
+    parse_http_date(value).map(secs_to_system_time)
+}
+
+/// Whole seconds since the unix epoch (negative before 1970).
+pub fn system_time_to_secs(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => {
+            let d = e.duration();
+            -(d.as_secs() as i64) - i64::from(d.subsec_nanos() > 0)
+        }
+    }
+}
+
+/// Inverse of [`system_time_to_secs`].
+pub fn secs_to_system_time(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+/// `Sun, 06 Nov 1994 08:49:37 GMT`
+fn parse_imf_fixdate(s: &[u8]) -> Option<i64> {
+    if s.len() != 29 || &s[3..5] != b", " || s[7] != b' ' || s[11] != b' ' || s[16] != b' ' {
+        return None;
+    }
+    weekday(&s[..3])?;
+    let day = digits(&s[5..7])?;
+    let month = month(&s[8..11])?;
+    let year = digits(&s[12..16])?;
+    let (h, m, sec) = time_of_day(&s[17..25])?;
+    (&s[25..] == b" GMT").then_some(())?;
+    to_unix_secs(year as i64, month, day, h, m, sec)
+}
+
+/// `Sunday, 06-Nov-94 08:49:37 GMT`
+fn parse_rfc850(s: &[u8]) -> Option<i64> {
+    let comma = s.iter().position(|&b| b == b',')?;
+    let day_name = &s[..comma];
+    if !WEEKDAYS_LONG.iter().any(|d| day_name == d.as_bytes()) {
+        return None;
+    }
+    let s = s[comma..].strip_prefix(b", ")?;
+    if s.len() != 22 || s[2] != b'-' || s[6] != b'-' || s[9] != b' ' {
+        return None;
+    }
+    let day = digits(&s[..2])?;
+    let month = month(&s[3..6])?;
+    let yy = digits(&s[7..9])? as i64;
+    let (h, m, sec) = time_of_day(&s[10..18])?;
+    (&s[18..] == b" GMT").then_some(())?;
+
+    // RFC 9110: a two-digit year more than 50 years in the future is in the past century
+    let current_year = {
+        let days = now_unix_sec().div_euclid(86400);
+        civil_from_days(days).0
+    };
+    let mut year = current_year - current_year.rem_euclid(100) + yy;
+    if year > current_year + 50 {
+        year -= 100;
+    }
+    to_unix_secs(year, month, day, h, m, sec)
+}
+
+/// `Sun Nov  6 08:49:37 1994`
+fn parse_asctime(s: &[u8]) -> Option<i64> {
+    if s.len() != 24 || s[3] != b' ' || s[7] != b' ' || s[10] != b' ' || s[19] != b' ' {
+        return None;
+    }
+    weekday(&s[..3])?;
+    let month = month(&s[4..7])?;
+    let day = match s[8] {
+        b' ' => digits(&s[9..10])?,
+        _ => digits(&s[8..10])?,
+    };
+    let (h, m, sec) = time_of_day(&s[11..19])?;
+    let year = digits(&s[20..24])?;
+    to_unix_secs(year as i64, month, day, h, m, sec)
+}
+
+const WEEKDAYS_LONG: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+fn weekday(s: &[u8]) -> Option<()> {
+    WEEKDAYS_LONG
+        .iter()
+        .any(|d| &d.as_bytes()[..3] == s)
+        .then_some(())
+}
+
+fn month(s: &[u8]) -> Option<u32> {
+    const MONTHS: [&[u8; 3]; 12] = [
+        b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
+        b"Dec",
+    ];
+    MONTHS
+        .iter()
+        .position(|m| &m[..] == s)
+        .map(|i| i as u32 + 1)
+}
+
+fn digits(s: &[u8]) -> Option<u32> {
+    s.iter().try_fold(0u32, |acc, &b| {
+        b.is_ascii_digit().then(|| acc * 10 + (b - b'0') as u32)
+    })
+}
+
+/// `08:49:37`
+fn time_of_day(s: &[u8]) -> Option<(u32, u32, u32)> {
+    if s[2] != b':' || s[5] != b':' {
+        return None;
+    }
+    let (h, m, sec) = (digits(&s[..2])?, digits(&s[3..5])?, digits(&s[6..8])?);
+    // allow for leap seconds
+    (h < 24 && m < 60 && sec <= 60).then_some((h, m, sec.min(59)))
+}
+
+fn to_unix_secs(year: i64, month: u32, day: u32, h: u32, m: u32, s: u32) -> Option<i64> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_len = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if day == 0 || day > month_len {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + (h * 3600 + m * 60 + s) as i64)
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days, used for both formatting and parsing
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// `(year, month, day)` of the day `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
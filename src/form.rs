@@ -0,0 +1,479 @@
+//! `multipart/form-data` (RFC 7578) and `application/x-www-form-urlencoded` request bodies.
+
+use crate::{percent, Headers, QueryPairs};
+use memchr::memmem;
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+const READ_CHUNK: usize = 8 * 1024;
+
+/// Limits applied while parsing form bodies; exceeding one is an `InvalidData` error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormLimits {
+    max_parts: usize,
+    max_part_header_size: usize,
+    max_field_size: u64,
+    max_file_size: u64,
+    max_urlencoded_size: u64,
+}
+
+impl Default for FormLimits {
+    fn default() -> Self {
+        Self {
+            max_parts: 128,
+            max_part_header_size: 8 * 1024,
+            max_field_size: 64 * 1024,
+            max_file_size: u64::MAX,
+            max_urlencoded_size: 64 * 1024,
+        }
+    }
+}
+
+impl FormLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max number of multipart parts / urlencoded fields (default: 128).
+    pub fn max_parts(mut self, n: usize) -> Self {
+        self.max_parts = n;
+        self
+    }
+
+    /// Max size of the header block of a single part (default: 8 KiB).
+    pub fn max_part_header_size(mut self, n: usize) -> Self {
+        self.max_part_header_size = n;
+        self
+    }
+
+    /// Max body size of a part without a filename (default: 64 KiB).
+    pub fn max_field_size(mut self, n: u64) -> Self {
+        self.max_field_size = n;
+        self
+    }
+
+    /// Max body size of a file part, i.e. one with a filename (default: unlimited).
+    pub fn max_file_size(mut self, n: u64) -> Self {
+        self.max_file_size = n;
+        self
+    }
+
+    /// Max size of a whole urlencoded body (default: 64 KiB).
+    pub fn max_urlencoded_size(mut self, n: u64) -> Self {
+        self.max_urlencoded_size = n;
+        self
+    }
+}
+
+// -------------------------------------------------------------------------
+// MULTIPART
+// -------------------------------------------------------------------------
+
+/// Boundary parameter of a `multipart/form-data` content-type.
+pub fn multipart_boundary(headers: &Headers) -> Option<String> {
+    let content_type = std::str::from_utf8(headers.get(Headers::CONTENT_TYPE)?).ok()?;
+    let (mime, params) = split_params(content_type);
+    if !mime.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    let boundary = params
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("boundary"))?
+        .1;
+    // RFC 2046: 1 to 70 characters
+    (!boundary.is_empty() && boundary.len() <= 70).then_some(boundary)
+}
+
+/// Streaming `multipart/form-data` parser. Parts are read one at a time:
+///
+/// ```no_run
+/// # use khttp::form::{FormLimits, Multipart};
+/// # fn handle(mut ctx: khttp::RequestContext) -> std::io::Result<()> {
+/// let mut form = ctx.multipart(FormLimits::default())?;
+/// while let Some(mut part) = form.next_part()? {
+///     if part.filename().is_some() {
+///         std::io::copy(&mut part, &mut std::io::sink())?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Multipart<R: Read> {
+    body: R,
+    delimiter: Vec<u8>, // "\r\n--boundary"
+    buf: Vec<u8>,
+    pos: usize,
+    limits: FormLimits,
+    part_count: usize,
+    in_part: bool,
+    finished: bool,
+}
+
+impl<R: Read> Multipart<R> {
+    pub fn new(body: R, boundary: &str, limits: FormLimits) -> Self {
+        let delimiter = [b"\r\n--", boundary.as_bytes()].concat();
+        Multipart {
+            body,
+            delimiter,
+            // the first delimiter has no leading CRLF, pretend it has one
+            buf: b"\r\n".to_vec(),
+            pos: 0,
+            limits,
+            part_count: 0,
+            in_part: true, // anything before the first delimiter is preamble
+            finished: false,
+        }
+    }
+
+    /// Reads the boundary from `content-type`, `InvalidInput` error if not multipart.
+    pub fn from_headers(body: R, headers: &Headers, limits: FormLimits) -> io::Result<Self> {
+        match multipart_boundary(headers) {
+            Some(boundary) => Ok(Self::new(body, &boundary, limits)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a multipart/form-data body",
+            )),
+        }
+    }
+
+    /// Next part, skipping whatever is left of the previous one. `None` after the closing delimiter.
+    pub fn next_part(&mut self) -> io::Result<Option<Part<'_, R>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        if self.in_part {
+            let mut scratch = [0u8; 1024];
+            while self.read_part_data(&mut scratch)? != 0 {}
+        }
+
+        // after a delimiter: "--" closes the body, otherwise optional whitespace and CRLF
+        self.fill_at_least(2)?;
+        if self.available().starts_with(b"--") {
+            self.finished = true;
+            return Ok(None);
+        }
+        loop {
+            self.fill_at_least(2)?;
+            match self.available() {
+                [b' ' | b'\t', ..] => self.pos += 1,
+                [b'\r', b'\n', ..] => {
+                    self.pos += 2;
+                    break;
+                }
+                _ => return Err(invalid_data("malformed multipart delimiter")),
+            }
+        }
+
+        self.part_count += 1;
+        if self.part_count > self.limits.max_parts {
+            return Err(invalid_data("too many multipart parts"));
+        }
+
+        let headers = self.read_part_headers()?;
+        let disposition = headers
+            .get("content-disposition")
+            .and_then(|v| std::str::from_utf8(v).ok())
+            .map(ContentDisposition::parse)
+            .unwrap_or_default();
+        let max_size = match disposition.filename {
+            Some(_) => self.limits.max_file_size,
+            None => self.limits.max_field_size,
+        };
+
+        self.in_part = true;
+        Ok(Some(Part {
+            multipart: self,
+            headers,
+            name: disposition.name,
+            filename: disposition.filename,
+            read: 0,
+            max_size,
+        }))
+    }
+
+    fn read_part_headers(&mut self) -> io::Result<Headers<'static>> {
+        let max = self.limits.max_part_header_size;
+        let end = loop {
+            let available = self.available();
+            if available.starts_with(b"\r\n") {
+                break 0; // no headers
+            }
+            if let Some(i) = memmem::find(available, b"\r\n\r\n") {
+                break i + 2;
+            }
+            if available.len() > max {
+                return Err(invalid_data("multipart part headers too large"));
+            }
+            self.fill_more()?;
+        };
+        if end > max {
+            return Err(invalid_data("multipart part headers too large"));
+        }
+
+        let mut headers = Headers::new_nodate();
+        let block = &self.buf[self.pos..self.pos + end];
+        for line in block.split(|&b| b == b'\n').filter(|l| !l.is_empty()) {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let colon = memchr::memchr(b':', line)
+                .ok_or_else(|| invalid_data("malformed multipart part header"))?;
+            let name = std::str::from_utf8(line[..colon].trim_ascii())
+                .map_err(|_| invalid_data("malformed multipart part header"))?;
+            if name.is_empty() {
+                return Err(invalid_data("malformed multipart part header"));
+            }
+            headers.add(
+                name.to_ascii_lowercase(),
+                line[colon + 1..].trim_ascii().to_vec(),
+            );
+        }
+        self.pos += end + 2;
+        Ok(headers)
+    }
+
+    /// Reads the current part's data, returns 0 (and consumes the delimiter) at its end.
+    fn read_part_data(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if !self.in_part || out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let available = self.available();
+            match memmem::find(available, &self.delimiter) {
+                Some(0) => {
+                    self.pos += self.delimiter.len();
+                    self.in_part = false;
+                    return Ok(0);
+                }
+                Some(i) => return Ok(self.copy_out(i, out)),
+                None => {
+                    // the tail could be the start of a delimiter, keep it
+                    let safe = available.len().saturating_sub(self.delimiter.len() - 1);
+                    if safe > 0 {
+                        return Ok(self.copy_out(safe, out));
+                    }
+                }
+            }
+            self.fill_more()?;
+        }
+    }
+
+    fn copy_out(&mut self, max: usize, out: &mut [u8]) -> usize {
+        let n = max.min(out.len());
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+
+    fn available(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn fill_at_least(&mut self, n: usize) -> io::Result<()> {
+        while self.available().len() < n {
+            self.fill_more()?;
+        }
+        Ok(())
+    }
+
+    fn fill_more(&mut self) -> io::Result<()> {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        let len = self.buf.len();
+        self.buf.resize(len + READ_CHUNK, 0);
+        let n = loop {
+            match self.body.read(&mut self.buf[len..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let n = n.inspect_err(|_| self.buf.truncate(len))?;
+        self.buf.truncate(len + n);
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "multipart body ended before the closing delimiter",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A single part, read its body with [`Read`].
+pub struct Part<'m, R: Read> {
+    multipart: &'m mut Multipart<R>,
+    headers: Headers<'static>,
+    name: Option<String>,
+    filename: Option<String>,
+    read: u64,
+    max_size: u64,
+}
+
+impl<R: Read> Part<'_, R> {
+    pub fn headers(&self) -> &Headers<'static> {
+        &self.headers
+    }
+
+    /// Form field name from `content-disposition`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// File name from `content-disposition` (`filename*` preferred), as sent by the client.
+    /// Never use it as a path without sanitizing.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        std::str::from_utf8(self.headers.get(Headers::CONTENT_TYPE)?).ok()
+    }
+
+    pub fn vec(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn string(&mut self) -> io::Result<String> {
+        let mut buf = String::new();
+        self.read_to_string(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl<R: Read> Read for Part<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.multipart.read_part_data(buf)?;
+        self.read += n as u64;
+        if self.read > self.max_size {
+            return Err(invalid_data("multipart part exceeds size limit"));
+        }
+        Ok(n)
+    }
+}
+
+#[derive(Default)]
+struct ContentDisposition {
+    name: Option<String>,
+    filename: Option<String>,
+}
+
+impl ContentDisposition {
+    fn parse(value: &str) -> Self {
+        let (_, params) = split_params(value);
+        let mut result = ContentDisposition::default();
+        let mut filename_ext = None;
+        for (key, value) in params {
+            match key.to_ascii_lowercase().as_str() {
+                "name" => result.name = Some(value),
+                "filename" => result.filename = Some(value),
+                // RFC 8187: filename*=UTF-8''na%C3%AFve.txt
+                "filename*" => {
+                    filename_ext = value
+                        .split_once("''")
+                        .filter(|(charset, _)| charset.eq_ignore_ascii_case("utf-8"))
+                        .and_then(|(_, v)| percent::decode(v).map(Cow::into_owned));
+                }
+                _ => {}
+            }
+        }
+        if filename_ext.is_some() {
+            result.filename = filename_ext;
+        }
+        result
+    }
+}
+
+/// Splits `value; key=val; key2="quoted \"val\""` into the value and its params.
+fn split_params(s: &str) -> (&str, Vec<(&str, String)>) {
+    let (value, mut rest) = s.split_once(';').unwrap_or((s, ""));
+    let mut params = Vec::new();
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+        let key = key.trim();
+        let after = after.trim_start();
+        if let Some(quoted) = after.strip_prefix('"') {
+            let mut val = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => val.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => val.push(c),
+                }
+            }
+            params.push((key, val));
+            rest = &quoted[end..];
+        } else {
+            let (val, after) = after.split_once(';').unwrap_or((after, ""));
+            params.push((key, val.trim().to_string()));
+            rest = after;
+        }
+    }
+    (value.trim(), params)
+}
+
+// -------------------------------------------------------------------------
+// URLENCODED
+// -------------------------------------------------------------------------
+
+/// A buffered `application/x-www-form-urlencoded` body.
+pub struct UrlEncodedForm {
+    raw: String,
+}
+
+impl UrlEncodedForm {
+    /// Reads the whole body, enforcing `max_urlencoded_size` and `max_parts`.
+    pub fn read<R: Read>(body: R, limits: FormLimits) -> io::Result<Self> {
+        let mut raw = Vec::new();
+        let max = limits.max_urlencoded_size;
+        body.take(max.saturating_add(1)).read_to_end(&mut raw)?;
+        if raw.len() as u64 > max {
+            return Err(invalid_data("urlencoded body exceeds size limit"));
+        }
+        let raw =
+            String::from_utf8(raw).map_err(|_| invalid_data("urlencoded body is not UTF-8"))?;
+
+        let form = UrlEncodedForm { raw };
+        if form.pairs().count() > limits.max_parts {
+            return Err(invalid_data("too many urlencoded fields"));
+        }
+        Ok(form)
+    }
+
+    pub fn pairs(&self) -> QueryPairs<'_> {
+        QueryPairs::new(&self.raw)
+    }
+
+    pub fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.pairs().get(key)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+/// Whether `content-type` is `application/x-www-form-urlencoded`.
+pub fn is_urlencoded(headers: &Headers) -> bool {
+    headers
+        .get(Headers::CONTENT_TYPE)
+        .and_then(|v| std::str::from_utf8(v).ok())
+        .is_some_and(|v| {
+            split_params(v)
+                .0
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        })
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
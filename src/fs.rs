@@ -0,0 +1,513 @@
+use crate::header::{AcceptEncoding, ETag, EntityTag, Header, IfNoneMatch, Range as RangeHeader};
+use crate::{date, percent, Headers, Method, RequestContext, ResponseHandle, Status};
+use std::fmt::Write as _;
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const MAX_RANGES: usize = 16;
+const MULTIPART_BOUNDARY: &str = "khttp-byteranges-boundary";
+
+/// Serves files from a directory, meant to be mounted on a `/**` route (GET and HEAD):
+///
+/// ```no_run
+/// # use khttp::{fs::StaticFiles, Method::*, Server};
+/// # use std::sync::Arc;
+/// let files = Arc::new(StaticFiles::new("./public").unwrap().prefix("/static"));
+/// let mut app = Server::builder("127.0.0.1:8080").unwrap();
+/// for method in [Get, Head] {
+///     let files = files.clone();
+///     app.route(method, "/static/**", move |ctx, res| files.serve(ctx, res));
+/// }
+/// ```
+pub struct StaticFiles {
+    root: PathBuf,
+    prefix: String,
+    index_files: Vec<String>,
+    directory_listing: bool,
+    precompressed_gzip: bool,
+}
+
+impl StaticFiles {
+    pub fn new<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        let root = root.as_ref().canonicalize()?;
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "static files root is not a directory",
+            ));
+        }
+        Ok(Self {
+            root,
+            prefix: String::new(),
+            index_files: vec!["index.html".to_string()],
+            directory_listing: false,
+            precompressed_gzip: true,
+        })
+    }
+
+    /// Route prefix stripped from the request path, e.g. "/static".
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// File names tried (in order) when a directory is requested, default: "index.html".
+    pub fn index_files<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.index_files = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Render an HTML listing for directories without an index file (default: off).
+    pub fn directory_listing(mut self, enabled: bool) -> Self {
+        self.directory_listing = enabled;
+        self
+    }
+
+    /// Serve "file.gz" instead of "file" to clients accepting gzip (default: on).
+    pub fn precompressed_gzip(mut self, enabled: bool) -> Self {
+        self.precompressed_gzip = enabled;
+        self
+    }
+
+    pub fn serve(&self, ctx: RequestContext, res: &mut ResponseHandle) -> io::Result<()> {
+        let head_only = match ctx.method {
+            Method::Get => false,
+            Method::Head => true,
+            _ => {
+                let mut headers = Headers::new();
+                headers.add("allow", &b"GET, HEAD"[..]);
+                return res.send0(&Status::of(405), &headers);
+            }
+        };
+
+        // the normalized path the route was matched on, see `RequestContext::path`
+        let request_path = ctx.path();
+        let rel_path = match request_path.strip_prefix(self.prefix.as_str()) {
+            Some(p) if p.is_empty() || p.starts_with('/') => p,
+            _ => return not_found(res),
+        };
+        let path = match self.resolve(rel_path) {
+            Some(p) => p,
+            None => return not_found(res),
+        };
+
+        if path.is_dir() {
+            if !request_path.ends_with('/') {
+                // redirect so that relative links inside the directory resolve correctly
+                let mut location = format!("{request_path}/");
+                if let Some(query) = ctx.uri.query() {
+                    location.push('?');
+                    location.push_str(query);
+                }
+                let mut headers = Headers::new();
+                headers.add("location", location.into_bytes());
+                return res.send0(&Status::MOVED_PERMANENTLY, &headers);
+            }
+            for index in &self.index_files {
+                let index_path = path.join(index);
+                if index_path.is_file() {
+                    return self.serve_file(&ctx, res, &index_path, head_only);
+                }
+            }
+            if self.directory_listing {
+                return serve_directory_listing(request_path, &path, res, head_only);
+            }
+            return not_found(res);
+        }
+
+        self.serve_file(&ctx, res, &path, head_only)
+    }
+
+    /// Maps a request path onto the root directory; `None` if it escapes the root or doesn't exist.
+    fn resolve(&self, rel_path: &str) -> Option<PathBuf> {
+        let decoded = percent::decode(rel_path)?;
+        let mut path = self.root.clone();
+        for segment in decoded.split('/') {
+            if segment.is_empty() || segment == "." {
+                continue;
+            }
+            if segment == ".." || segment.contains(['\\', '\0']) {
+                return None;
+            }
+            // reject anything that isn't a plain file name (e.g. "C:" prefixes on windows)
+            let mut components = Path::new(segment).components();
+            if !matches!(components.next(), Some(Component::Normal(_)))
+                || components.next().is_some()
+            {
+                return None;
+            }
+            path.push(segment);
+        }
+
+        // canonicalize resolves symlinks, which could point outside of root
+        let canonical = path.canonicalize().ok()?;
+        canonical.starts_with(&self.root).then_some(canonical)
+    }
+
+    fn serve_file(
+        &self,
+        ctx: &RequestContext,
+        res: &mut ResponseHandle,
+        path: &Path,
+        head_only: bool,
+    ) -> io::Result<()> {
+        let mut headers = Headers::new();
+        headers.add(Headers::CONTENT_TYPE, mime_type(path).as_bytes());
+        headers.add("accept-ranges", &b"bytes"[..]);
+
+        let mut file_path = path.to_path_buf();
+        if self.precompressed_gzip {
+            let mut gz_path = path.as_os_str().to_owned();
+            gz_path.push(".gz");
+            let gz_path = PathBuf::from(gz_path);
+            if gz_path.is_file() {
+                headers.add(Headers::VARY, &b"accept-encoding"[..]);
+                if accepts_gzip(&ctx.headers) {
+                    headers.add(Headers::CONTENT_ENCODING, &b"gzip"[..]);
+                    file_path = gz_path;
+                }
+            }
+        }
+
+        let file = match File::open(&file_path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                return res.send0(&Status::FORBIDDEN, Headers::empty());
+            }
+            Err(_) => return not_found(res),
+        };
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return not_found(res);
+        }
+        let len = metadata.len();
+
+        let etag = etag(&metadata);
+        headers.set_typed(&ETag(etag.clone()));
+        let last_modified = last_modified(&metadata);
+        if let Some(secs) = last_modified {
+            headers.add(
+                "last-modified",
+                date::get_date_value_from_secs(secs).to_vec(),
+            );
+        }
+
+        if is_not_modified(&ctx.headers, &etag, last_modified) {
+            return res.send_head(&Status::NOT_MODIFIED, &headers, len);
+        }
+
+        let ranges = match ctx.headers.typed::<RangeHeader>() {
+            Some(range) => range_request(&range, len),
+            None => RangeRequest::Full,
+        };
+
+        match ranges {
+            RangeRequest::Full if head_only => res.send_head(&Status::OK, &headers, len),
+            RangeRequest::Full => res.send_file(&Status::OK, &headers, &file, None),
+            RangeRequest::Unsatisfiable => {
+                headers.add("content-range", format!("bytes */{len}").into_bytes());
+                res.send0(&Status::of(416), &headers)
+            }
+            RangeRequest::Partial(ranges) if ranges.len() == 1 => {
+                let range = ranges[0].clone();
+                if head_only {
+                    let content_range = format!("bytes {}-{}/{len}", range.start, range.end - 1);
+                    headers.add("content-range", content_range.into_bytes());
+                    return res.send_head(&Status::of(206), &headers, range.end - range.start);
+                }
+                res.send_file(&Status::of(206), &headers, &file, Some(range))
+            }
+            RangeRequest::Partial(ranges) => {
+                let content_type = mime_type(path);
+                let body = MultipartRanges::new(&file, &ranges, content_type, len);
+                headers.replace(
+                    Headers::CONTENT_TYPE,
+                    format!("multipart/byteranges; boundary={MULTIPART_BOUNDARY}").into_bytes(),
+                );
+                headers.set_content_length(Some(body.len()));
+                if head_only {
+                    return res.send_head(&Status::of(206), &headers, body.len());
+                }
+                res.sendr(&Status::of(206), &headers, body)
+            }
+        }
+    }
+}
+
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = match path.extension().and_then(|e| e.to_str()) {
+        Some(e) => e.to_ascii_lowercase(),
+        None => return "application/octet-stream",
+    };
+
+    match extension.as_str() {
+        "htm" | "html" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+// -------------------------------------------------------------------------
+// CONDITIONAL REQUESTS
+// -------------------------------------------------------------------------
+
+fn etag(metadata: &Metadata) -> EntityTag {
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    EntityTag::strong(&format!("{:x}-{:x}", mtime, metadata.len()))
+}
+
+fn last_modified(metadata: &Metadata) -> Option<i64> {
+    metadata.modified().ok().map(date::system_time_to_secs)
+}
+
+fn is_not_modified(request: &Headers, etag: &EntityTag, last_modified: Option<i64>) -> bool {
+    // if-none-match takes precedence over if-modified-since (RFC 9110 13.2.2)
+    if request.get(IfNoneMatch::NAME).is_some() {
+        return request
+            .typed::<IfNoneMatch>()
+            .is_some_and(|condition| condition.matches(etag));
+    }
+    match (request.get("if-modified-since"), last_modified) {
+        (Some(since), Some(lm)) => date::parse_http_date(since).is_some_and(|since| lm <= since),
+        _ => false,
+    }
+}
+
+fn accepts_gzip(request: &Headers) -> bool {
+    request
+        .typed::<AcceptEncoding>()
+        .and_then(|accept| accept.quality("gzip"))
+        .is_some_and(|q| q > 0)
+}
+
+// -------------------------------------------------------------------------
+// RANGE REQUESTS
+// -------------------------------------------------------------------------
+
+enum RangeRequest {
+    Full,
+    Partial(Vec<Range<u64>>),
+    Unsatisfiable,
+}
+
+/// Applies `range` to a representation of `len` bytes. Malformed or unsupported range
+/// headers don't decode and are ignored (full response), per RFC 9110.
+fn range_request(range: &RangeHeader, len: u64) -> RangeRequest {
+    let ranges: Vec<_> = range
+        .0
+        .iter()
+        .filter_map(|spec| spec.to_range(len))
+        .collect();
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+    if ranges.len() > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+    RangeRequest::Partial(ranges)
+}
+
+/// `multipart/byteranges` body, streamed from the file.
+struct MultipartRanges<'f> {
+    file: &'f File,
+    segments: Vec<Segment>,
+    current: usize,
+    offset: u64,
+}
+
+enum Segment {
+    Bytes(Vec<u8>),
+    File(Range<u64>),
+}
+
+impl<'f> MultipartRanges<'f> {
+    fn new(file: &'f File, ranges: &[Range<u64>], content_type: &str, len: u64) -> Self {
+        let mut segments = Vec::with_capacity(ranges.len() * 2 + 1);
+        for (i, range) in ranges.iter().enumerate() {
+            let mut part_head = String::new();
+            if i > 0 {
+                part_head.push_str("\r\n");
+            }
+            let _ = write!(
+                part_head,
+                "--{MULTIPART_BOUNDARY}\r\ncontent-type: {content_type}\r\ncontent-range: bytes {}-{}/{len}\r\n\r\n",
+                range.start,
+                range.end - 1
+            );
+            segments.push(Segment::Bytes(part_head.into_bytes()));
+            segments.push(Segment::File(range.clone()));
+        }
+        segments.push(Segment::Bytes(
+            format!("\r\n--{MULTIPART_BOUNDARY}--\r\n").into_bytes(),
+        ));
+        Self {
+            file,
+            segments,
+            current: 0,
+            offset: 0,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Bytes(b) => b.len() as u64,
+                Segment::File(r) => r.end - r.start,
+            })
+            .sum()
+    }
+}
+
+impl Read for MultipartRanges<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(segment) = self.segments.get(self.current) {
+            let n = match segment {
+                Segment::Bytes(bytes) => {
+                    let rest = &bytes[self.offset as usize..];
+                    let n = rest.len().min(buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    n
+                }
+                Segment::File(range) => {
+                    let remaining = range.end - range.start - self.offset;
+                    if remaining == 0 {
+                        0
+                    } else {
+                        let mut file = self.file;
+                        file.seek(SeekFrom::Start(range.start + self.offset))?;
+                        let to_read = remaining.min(buf.len() as u64) as usize;
+                        match file.read(&mut buf[..to_read])? {
+                            0 => {
+                                return Err(io::Error::new(
+                                    io::ErrorKind::UnexpectedEof,
+                                    "file truncated",
+                                ))
+                            }
+                            n => n,
+                        }
+                    }
+                }
+            };
+            if n == 0 {
+                self.current += 1;
+                self.offset = 0;
+                continue;
+            }
+            self.offset += n as u64;
+            return Ok(n);
+        }
+        Ok(0)
+    }
+}
+
+// -------------------------------------------------------------------------
+// DIRECTORY LISTING
+// -------------------------------------------------------------------------
+
+fn serve_directory_listing(
+    request_path: &str,
+    dir_path: &Path,
+    res: &mut ResponseHandle,
+    head_only: bool,
+) -> io::Result<()> {
+    let mut entries: Vec<(String, bool)> = dir_path
+        .read_dir()?
+        .filter_map(Result::ok)
+        .map(|e| {
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+            (e.file_name().to_string_lossy().into_owned(), is_dir)
+        })
+        .collect();
+    entries.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let title = escape_html(request_path);
+    let mut html = String::with_capacity(1024);
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\
+         <body><h2>Index of {title}</h2><ul>"
+    );
+    if request_path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>");
+    }
+    for (name, is_dir) in entries {
+        let slash = if is_dir { "/" } else { "" };
+        let _ = write!(
+            html,
+            "<li><a href=\"{}{slash}\">{}{slash}</a></li>",
+            escape_html(&percent::encode_path_segment(&name)),
+            escape_html(&name)
+        );
+    }
+    html.push_str("</ul></body></html>");
+
+    let mut headers = Headers::new();
+    headers.add(Headers::CONTENT_TYPE, &b"text/html; charset=utf-8"[..]);
+    if head_only {
+        return res.send_head(&Status::OK, &headers, html.len() as u64);
+    }
+    res.ok(&headers, html)
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// -------------------------------------------------------------------------
+// UTILS
+// -------------------------------------------------------------------------
+
+fn not_found(res: &mut ResponseHandle) -> io::Result<()> {
+    res.send0(&Status::NOT_FOUND, Headers::empty())
+}
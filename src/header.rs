@@ -0,0 +1,917 @@
+//! Typed headers: the [`Header`] trait and parsers for common RFC 9110 headers.
+//!
+//! ```
+//! # use khttp::header::{ContentType, MediaType};
+//! # use khttp::Headers;
+//! let mut headers = Headers::new();
+//! headers.set_typed(&ContentType(MediaType::new("text", "html").with_param("charset", "utf-8")));
+//! assert_eq!(headers.get("content-type"), Some(&b"text/html; charset=utf-8"[..]));
+//!
+//! let ct: ContentType = headers.typed().unwrap();
+//! assert_eq!(ct.0.charset(), Some("utf-8"));
+//! ```
+
+use crate::Method;
+use std::borrow::Cow;
+use std::fmt::{self, Write as _};
+use std::ops::Range as StdRange;
+
+/// A header that can be decoded from / encoded to its raw value.
+pub trait Header: Sized {
+    /// Lowercase header name.
+    const NAME: &'static str;
+
+    /// Decodes from every value of the header, in order (list headers may be split over
+    /// several lines). Returns `None` if there are no values or they don't parse.
+    fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self>;
+
+    fn encode(&self) -> Vec<u8>;
+}
+
+/// Implements `Header` for a single-valued header via `FromStr`-like `parse` and `Display`.
+macro_rules! single_value_header {
+    ($ty:ty, $name:expr) => {
+        impl Header for $ty {
+            const NAME: &'static str = $name;
+
+            fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+                // like `Headers::get`, the last value wins
+                let value = std::str::from_utf8(values.last()?).ok()?;
+                Self::parse(value.trim())
+            }
+
+            fn encode(&self) -> Vec<u8> {
+                self.to_string().into_bytes()
+            }
+        }
+    };
+}
+
+// -------------------------------------------------------------------------
+// MEDIA TYPES
+// -------------------------------------------------------------------------
+
+/// `type/subtype; param=value`, type, subtype and param names are lowercased.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaType {
+    pub type_: String,
+    pub subtype: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn new(type_: &str, subtype: &str) -> Self {
+        MediaType {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params: Vec::new(),
+        }
+    }
+
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params
+            .push((name.to_ascii_lowercase(), value.to_string()));
+        self
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = split_unquoted(s, ';').into_iter();
+        let (type_, subtype) = parts.next()?.trim().split_once('/')?;
+        if !is_token(type_) || !is_token(subtype) {
+            return None;
+        }
+        let mut media_type = MediaType::new(type_, subtype);
+        for part in parts.map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = parse_param(part)?;
+            media_type.params.push((name, value));
+        }
+        Some(media_type)
+    }
+
+    /// `type/subtype` without params.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    /// Param value, names are matched case-insensitively.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    /// Whether this (possibly wildcard) media range matches `other`, e.g. `text/*` matches `text/html`.
+    pub fn matches(&self, other: &MediaType) -> bool {
+        (self.type_ == "*" || self.type_ == other.type_)
+            && (self.subtype == "*" || self.subtype == other.subtype)
+            && self
+                .params
+                .iter()
+                .all(|(k, v)| other.param(k).is_some_and(|o| o.eq_ignore_ascii_case(v)))
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.params {
+            write!(f, "; {name}={}", quote_if_needed(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// `content-type`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType(pub MediaType);
+
+impl ContentType {
+    fn parse(s: &str) -> Option<Self> {
+        MediaType::parse(s).map(ContentType)
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+single_value_header!(ContentType, "content-type");
+
+// -------------------------------------------------------------------------
+// QUALITY LISTS
+// -------------------------------------------------------------------------
+
+/// A list item with its `q` weight, in thousandths (1000 = `q=1`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityItem<T> {
+    pub item: T,
+    pub quality: u16,
+}
+
+impl<T: fmt::Display> fmt::Display for QualityItem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.item.fmt(f)?;
+        match self.quality {
+            1000.. => {}
+            0 => f.write_str(";q=0")?,
+            q => write!(f, ";q=0.{}", format!("{q:03}").trim_end_matches('0'))?,
+        }
+        Ok(())
+    }
+}
+
+/// `accept`: media ranges with weights, in the order sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accept(pub Vec<QualityItem<MediaType>>);
+
+impl Header for Accept {
+    const NAME: &'static str = "accept";
+
+    fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+        let mut items = Vec::new();
+        for item in list_items(values)? {
+            let mut parts = split_unquoted(item, ';').into_iter();
+            let (type_, subtype) = parts.next()?.trim().split_once('/')?;
+            if !is_token(type_) || !is_token(subtype) {
+                return None;
+            }
+            let mut media_range = MediaType::new(type_, subtype);
+            let mut quality = 1000;
+            for part in parts.map(str::trim).filter(|p| !p.is_empty()) {
+                let (name, value) = parse_param(part)?;
+                if name == "q" {
+                    quality = parse_qvalue(value.as_bytes())?;
+                    break; // anything after q is an accept-ext
+                }
+                media_range.params.push((name, value));
+            }
+            items.push(QualityItem {
+                item: media_range,
+                quality,
+            });
+        }
+        Some(Accept(items))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        join(&self.0).into_bytes()
+    }
+}
+
+/// Implements `Header` for a weighted list of tokens (`token;q=0.5, ...`), lowercased.
+macro_rules! quality_list_header {
+    ($(#[$doc:meta])* $ty:ident, $name:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $ty(pub Vec<QualityItem<String>>);
+
+        impl $ty {
+            /// Weight of `value`, falling back to `*`. `None` if neither is listed.
+            pub fn quality(&self, value: &str) -> Option<u16> {
+                let find = |name: &str| self.0.iter().find(|i| i.item == name).map(|i| i.quality);
+                find(&value.to_ascii_lowercase()).or_else(|| find("*"))
+            }
+        }
+
+        impl Header for $ty {
+            const NAME: &'static str = $name;
+
+            fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+                let mut items = Vec::new();
+                for item in list_items(values)? {
+                    let mut parts = item.split(';');
+                    let value = parts.next()?.trim();
+                    if !is_token(value) {
+                        return None;
+                    }
+                    let quality = match parts.find_map(|p| p.trim().strip_prefix("q=")) {
+                        Some(q) => parse_qvalue(q.as_bytes())?,
+                        None => 1000,
+                    };
+                    items.push(QualityItem {
+                        item: value.to_ascii_lowercase(),
+                        quality,
+                    });
+                }
+                Some($ty(items))
+            }
+
+            fn encode(&self) -> Vec<u8> {
+                join(&self.0).into_bytes()
+            }
+        }
+    };
+}
+
+quality_list_header!(
+    /// `accept-encoding`: content codings with weights.
+    AcceptEncoding,
+    "accept-encoding"
+);
+quality_list_header!(
+    /// `accept-charset`: charsets with weights.
+    AcceptCharset,
+    "accept-charset"
+);
+quality_list_header!(
+    /// `accept-language`: language ranges with weights.
+    AcceptLanguage,
+    "accept-language"
+);
+
+// -------------------------------------------------------------------------
+// CACHE-CONTROL
+// -------------------------------------------------------------------------
+
+/// `cache-control` directives (names lowercased), in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheControl(pub Vec<(String, Option<String>)>);
+
+impl CacheControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directive, e.g. `.directive("max-age", Some("60"))` or `.directive("no-store", None)`.
+    pub fn directive(mut self, name: &str, value: Option<&str>) -> Self {
+        self.0
+            .push((name.to_ascii_lowercase(), value.map(str::to_string)));
+        self
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.0.iter().any(|(k, _)| k.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    pub fn max_age(&self) -> Option<u64> {
+        self.get("max-age")?.parse().ok()
+    }
+
+    pub fn s_maxage(&self) -> Option<u64> {
+        self.get("s-maxage")?.parse().ok()
+    }
+
+    pub fn no_cache(&self) -> bool {
+        self.has("no-cache")
+    }
+
+    pub fn no_store(&self) -> bool {
+        self.has("no-store")
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.has("public")
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.has("private")
+    }
+}
+
+impl Header for CacheControl {
+    const NAME: &'static str = "cache-control";
+
+    fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+        let mut directives = Vec::new();
+        for item in list_items(values)? {
+            let (name, value) = match item.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(unquote(value.trim())?.into_owned())),
+                None => (item, None),
+            };
+            if !is_token(name) {
+                return None;
+            }
+            directives.push((name.to_ascii_lowercase(), value));
+        }
+        Some(CacheControl(directives))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = String::new();
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            out.push_str(name);
+            if let Some(value) = value {
+                let _ = write!(out, "={}", quote_if_needed(value));
+            }
+        }
+        out.into_bytes()
+    }
+}
+
+// -------------------------------------------------------------------------
+// AUTHORIZATION
+// -------------------------------------------------------------------------
+
+/// `authorization`: `<scheme> <credentials>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+    pub scheme: String,
+    pub credentials: String,
+}
+
+impl Authorization {
+    pub fn bearer(token: &str) -> Self {
+        Authorization {
+            scheme: "Bearer".to_string(),
+            credentials: token.to_string(),
+        }
+    }
+
+    pub fn basic(user: &str, password: &str) -> Self {
+        Authorization {
+            scheme: "Basic".to_string(),
+            credentials: base64_encode(format!("{user}:{password}").as_bytes()),
+        }
+    }
+
+    /// Token of a `Bearer` authorization.
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.scheme
+            .eq_ignore_ascii_case("bearer")
+            .then_some(self.credentials.as_str())
+    }
+
+    /// Decoded `(user, password)` of a `Basic` authorization.
+    pub fn basic_credentials(&self) -> Option<(String, String)> {
+        if !self.scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(base64_decode(&self.credentials)?).ok()?;
+        let (user, password) = decoded.split_once(':')?;
+        Some((user.to_string(), password.to_string()))
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let (scheme, credentials) = s.split_once(' ').unwrap_or((s, ""));
+        is_token(scheme).then(|| Authorization {
+            scheme: scheme.to_string(),
+            credentials: credentials.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Authorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.credentials.is_empty() {
+            true => f.write_str(&self.scheme),
+            false => write!(f, "{} {}", self.scheme, self.credentials),
+        }
+    }
+}
+
+single_value_header!(Authorization, "authorization");
+
+// -------------------------------------------------------------------------
+// RANGE
+// -------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRangeSpec {
+    /// `first-last`, inclusive
+    FromTo(u64, u64),
+    /// `first-`
+    From(u64),
+    /// `-suffix_len`
+    Suffix(u64),
+}
+
+impl ByteRangeSpec {
+    /// The byte range within a representation of `len` bytes, `None` if unsatisfiable.
+    pub fn to_range(&self, len: u64) -> Option<StdRange<u64>> {
+        let range = match *self {
+            ByteRangeSpec::FromTo(first, last) => first..last.saturating_add(1).min(len),
+            ByteRangeSpec::From(first) => first..len,
+            ByteRangeSpec::Suffix(n) => len.saturating_sub(n)..len,
+        };
+        (range.start < len && !range.is_empty()).then_some(range)
+    }
+}
+
+impl fmt::Display for ByteRangeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ByteRangeSpec::FromTo(first, last) => write!(f, "{first}-{last}"),
+            ByteRangeSpec::From(first) => write!(f, "{first}-"),
+            ByteRangeSpec::Suffix(n) => write!(f, "-{n}"),
+        }
+    }
+}
+
+/// `range: bytes=...`, other range units don't decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range(pub Vec<ByteRangeSpec>);
+
+impl Range {
+    fn parse(s: &str) -> Option<Self> {
+        let (unit, specs) = s.split_once('=')?;
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+        let mut ranges = Vec::new();
+        for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (first, last) = spec.split_once('-')?;
+            let spec = match (first.trim(), last.trim()) {
+                ("", suffix) => ByteRangeSpec::Suffix(parse_u64(suffix)?),
+                (first, "") => ByteRangeSpec::From(parse_u64(first)?),
+                (first, last) => {
+                    let (first, last) = (parse_u64(first)?, parse_u64(last)?);
+                    if last < first {
+                        return None;
+                    }
+                    ByteRangeSpec::FromTo(first, last)
+                }
+            };
+            ranges.push(spec);
+        }
+        (!ranges.is_empty()).then_some(Range(ranges))
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes={}", join(&self.0))
+    }
+}
+
+single_value_header!(Range, "range");
+
+// -------------------------------------------------------------------------
+// ENTITY TAGS
+// -------------------------------------------------------------------------
+
+/// `"tag"` or `W/"tag"`, `tag` is stored without quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    pub tag: String,
+}
+
+impl EntityTag {
+    pub fn strong(tag: &str) -> Self {
+        EntityTag {
+            weak: false,
+            tag: tag.to_string(),
+        }
+    }
+
+    pub fn weak(tag: &str) -> Self {
+        EntityTag {
+            weak: true,
+            tag: tag.to_string(),
+        }
+    }
+
+    /// Strong comparison (RFC 9110 8.8.3.2): both strong and equal.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison: equal tags, ignoring weakness.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let (weak, quoted) = match s.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        // etagc = %x21 / %x23-7E / obs-text
+        if tag.bytes().any(|b| b == b'"' || b < 0x21 || b == 0x7f) {
+            return None;
+        }
+        Some(EntityTag {
+            weak,
+            tag: tag.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// `etag`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(pub EntityTag);
+
+impl ETag {
+    fn parse(s: &str) -> Option<Self> {
+        EntityTag::parse(s).map(ETag)
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+single_value_header!(ETag, "etag");
+
+/// `if-none-match`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl IfNoneMatch {
+    /// Whether the condition is false for a representation with `etag`, i.e. whether
+    /// a GET / HEAD should be answered with 304 (weak comparison).
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(tags) => tags.iter().any(|t| t.weak_eq(etag)),
+        }
+    }
+}
+
+impl Header for IfNoneMatch {
+    const NAME: &'static str = "if-none-match";
+
+    fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+        let items = list_items(values)?;
+        if items == ["*"] {
+            return Some(IfNoneMatch::Any);
+        }
+        let tags = items
+            .into_iter()
+            .map(EntityTag::parse)
+            .collect::<Option<_>>()?;
+        Some(IfNoneMatch::Tags(tags))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            IfNoneMatch::Any => b"*".to_vec(),
+            IfNoneMatch::Tags(tags) => join(tags).into_bytes(),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------
+// HOST
+// -------------------------------------------------------------------------
+
+/// `host`: hostname (lowercased, IPv6 literals keep their brackets) and optional port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    pub hostname: String,
+    pub port: Option<u16>,
+}
+
+impl Host {
+    fn parse(s: &str) -> Option<Self> {
+        let (hostname, port) = match s.rfind(':') {
+            // "[::1]" has colons but no port
+            Some(i) if !s[i..].contains(']') => (&s[..i], Some(s[i + 1..].parse().ok()?)),
+            _ => (s, None),
+        };
+        let valid = match hostname.strip_prefix('[') {
+            Some(v6) => v6
+                .strip_suffix(']')
+                .is_some_and(|a| a.parse::<std::net::Ipv6Addr>().is_ok()),
+            None => {
+                !hostname.is_empty()
+                    && hostname
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=%".contains(&b))
+            }
+        };
+        valid.then(|| Host {
+            hostname: hostname.to_ascii_lowercase(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.hostname)?;
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
+single_value_header!(Host, "host");
+
+// -------------------------------------------------------------------------
+// LIST HEADERS
+// -------------------------------------------------------------------------
+
+/// Implements `Header` for a comma-separated list of tokens.
+macro_rules! token_list_header {
+    ($(#[$doc:meta])* $ty:ident, $name:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, Default)]
+        pub struct $ty(pub Vec<String>);
+
+        impl $ty {
+            /// Case-insensitive membership test.
+            pub fn contains(&self, token: &str) -> bool {
+                self.0.iter().any(|t| t.eq_ignore_ascii_case(token))
+            }
+        }
+
+        impl Header for $ty {
+            const NAME: &'static str = $name;
+
+            fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+                let items = list_items(values)?;
+                items
+                    .into_iter()
+                    .map(|t| is_token(t).then(|| t.to_string()))
+                    .collect::<Option<_>>()
+                    .map($ty)
+            }
+
+            fn encode(&self) -> Vec<u8> {
+                self.0.join(", ").into_bytes()
+            }
+        }
+    };
+}
+
+token_list_header!(
+    /// `vary`
+    Vary,
+    "vary"
+);
+token_list_header!(
+    /// `connection` options
+    Connection,
+    "connection"
+);
+token_list_header!(
+    /// `content-encoding`, in the order the codings were applied
+    ContentEncoding,
+    "content-encoding"
+);
+
+/// `allow`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allow(pub Vec<Method>);
+
+impl Header for Allow {
+    const NAME: &'static str = "allow";
+
+    fn decode<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Self> {
+        let methods = values
+            .filter_map(|v| std::str::from_utf8(v).ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(|m| is_token(m).then(|| Method::from(m)))
+            .collect::<Option<_>>()?;
+        Some(Allow(methods))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let methods: Vec<&str> = self.0.iter().map(Method::as_str).collect();
+        methods.join(", ").into_bytes()
+    }
+}
+
+/// Splits comma-separated list values (across all lines) into trimmed, non-empty items,
+/// ignoring commas inside quoted strings. `None` if there are no values or one isn't UTF-8.
+pub fn list_items<'v, I: Iterator<Item = &'v [u8]>>(values: I) -> Option<Vec<&'v str>> {
+    let mut items = Vec::new();
+    let mut seen = false;
+    for value in values {
+        seen = true;
+        let value = std::str::from_utf8(value).ok()?;
+        items.extend(
+            split_unquoted(value, ',')
+                .into_iter()
+                .map(str::trim)
+                .filter(|i| !i.is_empty()),
+        );
+    }
+    seen.then_some(items)
+}
+
+// -------------------------------------------------------------------------
+// GRAMMAR UTILS
+// -------------------------------------------------------------------------
+
+/// RFC 9110 token
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Splits on `sep` outside of quoted strings.
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == sep && !in_quotes => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// `name=value` or `name="quoted value"`, name lowercased.
+fn parse_param(s: &str) -> Option<(String, String)> {
+    let (name, value) = s.split_once('=')?;
+    let name = name.trim();
+    if !is_token(name) {
+        return None;
+    }
+    let value = unquote(value.trim())?;
+    Some((name.to_ascii_lowercase(), value.into_owned()))
+}
+
+/// Removes quotes and escapes from a quoted-string; tokens are returned as is.
+fn unquote(s: &str) -> Option<Cow<'_, str>> {
+    let Some(inner) = s.strip_prefix('"') else {
+        return is_token(s).then_some(Cow::Borrowed(s));
+    };
+    let inner = inner.strip_suffix('"')?;
+    if !inner.contains('\\') {
+        return Some(Cow::Borrowed(inner));
+    }
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push(chars.next()?),
+            c => out.push(c),
+        }
+    }
+    Some(Cow::Owned(out))
+}
+
+fn quote_if_needed(s: &str) -> Cow<'_, str> {
+    if is_token(s) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    Cow::Owned(out)
+}
+
+/// `q` weight in thousandths, e.g. "0.5" -> 500.
+fn parse_qvalue(s: &[u8]) -> Option<u16> {
+    let s = s.trim_ascii();
+    let (int, frac) = match s.iter().position(|&b| b == b'.') {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, &b""[..]),
+    };
+    if frac.len() > 3 || !frac.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let mut q: u16 = match int {
+        b"0" => 0,
+        b"1" => 1000,
+        _ => return None,
+    };
+    for (i, &d) in frac.iter().enumerate() {
+        q += (d - b'0') as u16 * [100, 10, 1][i];
+    }
+    (q <= 1000).then_some(q)
+}
+
+fn parse_u64(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    let mut out = String::new();
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let _ = write!(out, "{item}");
+    }
+    out
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(input: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &c in input {
+        let v = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+        acc = acc << 6 | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,75 @@
+use std::any::{Any, TypeId};
+use std::fmt;
+
+/// A map holding one value per type, e.g. application state (see
+/// [`ServerBuilder::state`](crate::ServerBuilder::state)) or data attached to a request
+/// by hooks and middleware for the route handler (an authenticated user, a request id).
+#[derive(Default)]
+pub struct Extensions {
+    // few entries, a linear scan beats hashing
+    map: Vec<(TypeId, Box<dyn Any + Send + Sync>)>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value`, returning the previous value of its type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        let id = TypeId::of::<T>();
+        match self.map.iter_mut().find(|(k, _)| *k == id) {
+            Some((_, old)) => {
+                let old = std::mem::replace(old, Box::new(value));
+                old.downcast().ok().map(|old| *old)
+            }
+            None => {
+                self.map.push((id, Box::new(value)));
+                None
+            }
+        }
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        let id = TypeId::of::<T>();
+        let (_, value) = self.map.iter().find(|(k, _)| *k == id)?;
+        value.downcast_ref()
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        let id = TypeId::of::<T>();
+        let (_, value) = self.map.iter_mut().find(|(k, _)| *k == id)?;
+        value.downcast_mut()
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        let id = TypeId::of::<T>();
+        let i = self.map.iter().position(|(k, _)| *k == id)?;
+        let (_, value) = self.map.swap_remove(i);
+        value.downcast().ok().map(|value| *value)
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
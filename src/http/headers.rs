@@ -1,3 +1,4 @@
+use crate::header::Header;
 use std::borrow::Cow;
 use std::fmt;
 use std::sync::LazyLock;
@@ -142,10 +143,25 @@ impl<'a> Headers<'a> {
         self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
     }
 
+    /// Decodes a typed header from all values of `H::NAME`.
+    pub fn typed<H: Header>(&self) -> Option<H> {
+        H::decode(self.get_all(H::NAME).map(|(_, v)| v.as_ref()))
+    }
+
+    /// Replaces `H::NAME` with the encoded header.
+    pub fn set_typed<H: Header>(&mut self, header: &H) {
+        self.replace(H::NAME, header.encode());
+    }
+
     pub const CONTENT_LENGTH: &'static str = "content-length";
     pub const CONTENT_TYPE: &'static str = "content-type";
     pub const TRANSFER_ENCODING: &'static str = "transfer-encoding";
     pub const CONNECTION: &'static str = "connection";
+    pub const CONTENT_ENCODING: &'static str = "content-encoding";
+    pub const ACCEPT_ENCODING: &'static str = "accept-encoding";
+    pub const VARY: &'static str = "vary";
+    pub const COOKIE: &'static str = "cookie";
+    pub const SET_COOKIE: &'static str = "set-cookie";
 
     pub fn get_content_length(&self) -> Option<u64> {
         self.content_length
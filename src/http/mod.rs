@@ -1,9 +1,13 @@
+mod extensions;
 mod headers;
 mod method;
+mod path;
 mod request_uri;
 mod status;
 
+pub use extensions::Extensions;
 pub use headers::Headers;
 pub use method::Method;
-pub use request_uri::RequestUri;
+pub use path::{EncodedSlash, PathNormalization};
+pub use request_uri::{QueryPairs, RequestUri};
 pub use status::Status;
@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// How `%2F` (an encoded `/`) in the request path is handled before routing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodedSlash {
+    /// Respond with 400 Bad Request.
+    Reject,
+    /// Keep it encoded: it doesn't split segments, but params decode it to `/`.
+    Preserve,
+    /// Decode it into a regular path separator.
+    Decode,
+}
+
+/// Request path normalization applied before routing (RFC 3986 6.2.2).
+///
+/// By default: unreserved characters are percent-decoded, duplicate slashes are merged,
+/// `.` / `..` segments are removed and encoded slashes are preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathNormalization {
+    decode_unreserved: bool,
+    merge_slashes: bool,
+    remove_dot_segments: bool,
+    encoded_slash: EncodedSlash,
+}
+
+impl Default for PathNormalization {
+    fn default() -> Self {
+        Self {
+            decode_unreserved: true,
+            merge_slashes: true,
+            remove_dot_segments: true,
+            encoded_slash: EncodedSlash::Preserve,
+        }
+    }
+}
+
+impl PathNormalization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode `%XX` escapes of unreserved characters, e.g. `/user/%31` -> `/user/1`.
+    pub fn decode_unreserved(mut self, enabled: bool) -> Self {
+        self.decode_unreserved = enabled;
+        self
+    }
+
+    /// Merge repeated slashes, e.g. `//user///1` -> `/user/1`.
+    pub fn merge_slashes(mut self, enabled: bool) -> Self {
+        self.merge_slashes = enabled;
+        self
+    }
+
+    /// Resolve `.` and `..` segments, e.g. `/a/../admin` -> `/admin`.
+    pub fn remove_dot_segments(mut self, enabled: bool) -> Self {
+        self.remove_dot_segments = enabled;
+        self
+    }
+
+    pub fn encoded_slash(mut self, policy: EncodedSlash) -> Self {
+        self.encoded_slash = policy;
+        self
+    }
+
+    /// Normalizes an origin-form path. Returns `None` if the path should be rejected:
+    /// a malformed `%` escape, or `%2F` with [`EncodedSlash::Reject`].
+    pub fn normalize<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+        if !path.starts_with('/') {
+            return Some(Cow::Borrowed(path)); // e.g. "*" or an empty absolute-form path
+        }
+
+        let mut path = Cow::Borrowed(path);
+        if path.contains('%') {
+            path = Cow::Owned(self.normalize_escapes(&path)?);
+        }
+        if self.merge_slashes && path.contains("//") {
+            path = Cow::Owned(merge_slashes(&path));
+        }
+        if self.remove_dot_segments && has_dot_segment(&path) {
+            path = Cow::Owned(remove_dot_segments(&path));
+        }
+        Some(path)
+    }
+
+    fn normalize_escapes(&self, path: &str) -> Option<String> {
+        let bytes = path.as_bytes();
+        let mut out = String::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'%' {
+                let next = memchr::memchr(b'%', &bytes[i..]).map_or(bytes.len(), |n| i + n);
+                out.push_str(&path[i..next]);
+                i = next;
+                continue;
+            }
+
+            let hex = bytes.get(i + 1..i + 3)?;
+            let b = (hex_digit(hex[0])? << 4) | hex_digit(hex[1])?;
+            match b {
+                b'/' => match self.encoded_slash {
+                    EncodedSlash::Reject => return None,
+                    EncodedSlash::Preserve => out.push_str("%2F"),
+                    EncodedSlash::Decode => out.push('/'),
+                },
+                b if self.decode_unreserved && is_unreserved(b) => out.push(b as char),
+                b => {
+                    // uppercase hex digits, so equivalent escapes compare equal
+                    let _ = write!(out, "%{b:02X}");
+                }
+            }
+            i += 3;
+        }
+        Some(out)
+    }
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+}
+
+fn merge_slashes(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut prev_slash = false;
+    for c in path.chars() {
+        if c == '/' && prev_slash {
+            continue;
+        }
+        prev_slash = c == '/';
+        out.push(c);
+    }
+    out
+}
+
+fn has_dot_segment(path: &str) -> bool {
+    path.split('/').any(|s| s == "." || s == "..")
+}
+
+/// RFC 3986 5.2.4, for paths starting with '/'. `..` never goes above the root.
+fn remove_dot_segments(path: &str) -> String {
+    let parts: Vec<&str> = path[1..].split('/').collect();
+    let last = parts.len() - 1;
+    let mut out: Vec<&str> = Vec::with_capacity(parts.len());
+    for (i, seg) in parts.into_iter().enumerate() {
+        match seg {
+            "." | ".." => {
+                if seg == ".." {
+                    out.pop();
+                }
+                if i == last {
+                    out.push(""); // keep the trailing slash: "/a/b/.." -> "/a/"
+                }
+            }
+            s => out.push(s),
+        }
+    }
+    format!("/{}", out.join("/"))
+}
@@ -1,3 +1,7 @@
+use crate::percent;
+use std::borrow::Cow;
+use std::str::FromStr;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RequestUri<'a> {
     full: &'a str,
@@ -64,11 +68,70 @@ impl<'a> RequestUri<'a> {
         }
     }
 
-    pub fn query(&self) -> Option<&str> {
+    pub fn query(&self) -> Option<&'a str> {
         let path_start = &self.full[self.path_i_end..];
         let qmark_i = path_start.find('?')?;
         Some(&path_start[qmark_i + 1..])
     }
+
+    /// Form-decoded `key=value` pairs of the query string, in order.
+    pub fn query_pairs(&self) -> QueryPairs<'a> {
+        QueryPairs::new(self.query().unwrap_or(""))
+    }
+}
+
+/// Iterator over decoded query pairs; only allocates for pairs that contain escapes or `+`.
+#[derive(Debug, Clone)]
+pub struct QueryPairs<'a> {
+    rest: &'a str,
+}
+
+impl<'a> QueryPairs<'a> {
+    /// Parses a raw `application/x-www-form-urlencoded` string (e.g. a query or form body).
+    pub fn new(query: &'a str) -> Self {
+        QueryPairs { rest: query }
+    }
+
+    /// First value for `key`.
+    pub fn get(&self, key: &str) -> Option<Cow<'a, str>> {
+        self.get_all(key).next()
+    }
+
+    /// All values for `key`, e.g. `?tag=a&tag=b`.
+    pub fn get_all<'k>(&self, key: &'k str) -> impl Iterator<Item = Cow<'a, str>> + 'k
+    where
+        'a: 'k,
+    {
+        self.clone().filter(move |(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// First value for `key`, parsed with `FromStr`.
+    pub fn parse<T: FromStr>(&self, key: &str) -> Option<Result<T, T::Err>> {
+        self.get(key).map(|v| v.parse())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<'a> Iterator for QueryPairs<'a> {
+    type Item = (Cow<'a, str>, Cow<'a, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let (pair, rest) = self.rest.split_once('&').unwrap_or((self.rest, ""));
+            self.rest = rest;
+            if pair.is_empty() {
+                continue;
+            }
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            return Some((percent::decode_form(key), percent::decode_form(value)));
+        }
+    }
 }
 
 impl std::fmt::Display for RequestUri<'_> {
@@ -83,6 +83,7 @@ define_statuses! {
         401 => UNAUTHORIZED, "UNAUTHORIZED";
         403 => FORBIDDEN, "FORBIDDEN";
         404 => NOT_FOUND, "NOT FOUND";
+        406 => NOT_ACCEPTABLE, "NOT ACCEPTABLE";
         429 => TOO_MANY_REQUESTS, "TOO MANY REQUESTS";
         500 => INTERNAL_SERVER_ERROR, "INTERNAL SERVER ERROR";
         503 => SERVICE_UNAVAILABLE, "SERVICE UNAVAILABLE";
@@ -106,7 +107,6 @@ define_statuses! {
         308 => PERMANENT_REDIRECT, "PERMANENT REDIRECT";
         402 => PAYMENT_REQUIRED, "PAYMENT REQUIRED";
         405 => METHOD_NOT_ALLOWED, "METHOD NOT ALLOWED";
-        406 => NOT_ACCEPTABLE, "NOT ACCEPTABLE";
         407 => PROXY_AUTHENTICATION_REQUIRED, "PROXY AUTHENTICATION REQUIRED";
         408 => REQUEST_TIMEOUT, "REQUEST TIMEOUT";
         409 => CONFLICT, "CONFLICT";
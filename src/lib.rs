@@ -1,20 +1,36 @@
 mod body_reader;
+pub mod cookie;
 pub mod date;
+pub mod form;
+pub mod fs;
+pub mod header;
 mod http;
+pub mod middleware;
+pub mod negotiate;
 mod parser;
+pub mod percent;
 mod printer;
 mod router;
+mod sendfile;
 mod server;
 mod threadpool;
 
 pub use body_reader::BodyReader;
-pub use http::{Headers, Method, RequestUri, Status};
-pub use parser::{HttpParsingError, Request};
+pub use http::{
+    EncodedSlash, Extensions, Headers, Method, PathNormalization, QueryPairs, RequestUri, Status,
+};
+pub use middleware::{Middleware, Next};
+#[doc(hidden)]
+pub use parser::simd;
+pub use parser::{HttpParsingError, ParseStatus, Request, RequestLimits, RequestParser};
 pub use printer::HttpPrinter;
-pub use router::{RouteParams, Router, RouterBuilder};
+pub use router::{
+    Match, ParamError, RouteConflict, RouteInfo, RouteParams, Router, RouterBuilder, UnknownHost,
+    UrlError,
+};
 pub use server::{
-    ConnectionSetupAction, PreRoutingAction, RequestContext, ResponseHandle, RouteFn, Server,
-    ServerBuilder,
+    ConnectionInfo, ConnectionSetupAction, PreRoutingAction, RequestContext, ResponseHandle,
+    RouteFn, Scope, Server, ServerBuilder,
 };
 
 #[cfg(feature = "client")]
@@ -23,3 +39,8 @@ mod client;
 pub use client::{Client, ClientError, ClientResponseHandle};
 #[cfg(feature = "client")]
 pub use parser::Response;
+
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "compression")]
+pub use compression::{BodyDecoder, CompressionConfig, ContentCoding};
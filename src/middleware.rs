@@ -0,0 +1,77 @@
+use crate::{RequestContext, ResponseHandle, RouteFn};
+use std::io;
+use std::sync::Arc;
+
+/// Code around route handlers, registered for all routes
+/// ([`ServerBuilder::middleware`]), a [`Scope`] ([`Scope::middleware`]) or a single route
+/// ([`ServerBuilder::route_with`]). Global middleware runs first, then that of the
+/// enclosing scopes (outermost first), then the route's own.
+///
+/// A middleware sees the request before calling [`Next::run`] and the response handle
+/// (and the result of the rest of the chain) after it. Not calling `next` skips the
+/// remaining middleware and the route, after sending a response or not.
+///
+/// ```no_run
+/// # use khttp::{middleware::from_fn, Headers, Method::*, Server, Status};
+/// let mut app = Server::builder("127.0.0.1:8080").unwrap();
+/// app.middleware(from_fn(|ctx, res, next| {
+///     if ctx.headers.get("authorization").is_none() {
+///         return res.send0(&Status::of(401), Headers::empty());
+///     }
+///     next.run(ctx, res)
+/// }));
+/// app.route(Get, "/", |_, res| res.ok(Headers::empty(), "hi"));
+/// ```
+///
+/// [`ServerBuilder::middleware`]: crate::ServerBuilder::middleware
+/// [`ServerBuilder::route_with`]: crate::ServerBuilder::route_with
+/// [`Scope`]: crate::Scope
+/// [`Scope::middleware`]: crate::Scope::middleware
+pub trait Middleware: Send + Sync {
+    fn handle(&self, ctx: RequestContext, res: &mut ResponseHandle, next: Next) -> io::Result<()>;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(RequestContext, &mut ResponseHandle, Next) -> io::Result<()> + Send + Sync,
+{
+    fn handle(&self, ctx: RequestContext, res: &mut ResponseHandle, next: Next) -> io::Result<()> {
+        self(ctx, res, next)
+    }
+}
+
+/// Gives a closure the signature of a [`Middleware`] (closures passed where any
+/// `Middleware` is accepted need their argument types spelled out otherwise).
+pub fn from_fn<F>(f: F) -> F
+where
+    F: Fn(RequestContext, &mut ResponseHandle, Next) -> io::Result<()> + Send + Sync,
+{
+    f
+}
+
+/// The rest of a middleware chain, ending with the route.
+pub struct Next<'m> {
+    stack: &'m [Arc<dyn Middleware>],
+    route: &'m RouteFn,
+}
+
+impl<'m> Next<'m> {
+    pub(crate) fn new(stack: &'m [Arc<dyn Middleware>], route: &'m RouteFn) -> Self {
+        Next { stack, route }
+    }
+
+    pub fn run(self, ctx: RequestContext, res: &mut ResponseHandle) -> io::Result<()> {
+        match self.stack.split_first() {
+            Some((middleware, stack)) => middleware.handle(ctx, res, Next::new(stack, self.route)),
+            None => (self.route)(ctx, res),
+        }
+    }
+}
+
+/// `route` behind `stack`.
+pub(crate) fn layer(stack: Vec<Arc<dyn Middleware>>, route: Box<RouteFn>) -> Box<RouteFn> {
+    if stack.is_empty() {
+        return route;
+    }
+    Box::new(move |ctx, res| Next::new(&stack, &*route).run(ctx, res))
+}
@@ -0,0 +1,149 @@
+//! Server-driven content negotiation (RFC 9110 12.5) on `accept`, `accept-language`
+//! and `accept-charset`.
+//!
+//! ```
+//! # use khttp::negotiate::Negotiator;
+//! # use khttp::Headers;
+//! let mut request = Headers::new_nodate();
+//! request.add("accept", b"text/html;q=0.8, application/json".to_vec());
+//!
+//! let mut negotiator = Negotiator::new(&request);
+//! let offered = ["text/html", "application/json", "text/csv"];
+//! assert_eq!(negotiator.media_type(&offered), Some("application/json"));
+//!
+//! let mut response = Headers::new();
+//! negotiator.append_vary(&mut response);
+//! assert_eq!(response.get("vary"), Some(&b"accept"[..]));
+//! ```
+
+use crate::header::{Accept, AcceptCharset, AcceptLanguage, MediaType};
+use crate::Headers;
+
+/// Picks the best of the offered representations for a request and tracks which
+/// request headers the choice depended on (for `vary`).
+///
+/// Each method returns `None` when none of the offers is acceptable, which should be
+/// answered with `406 Not Acceptable`. A missing (or unparsable) request header accepts
+/// anything, so the first offer is returned. Ties are broken by offer order.
+pub struct Negotiator<'h> {
+    headers: &'h Headers<'h>,
+    vary: Vec<&'static str>,
+}
+
+impl<'h> Negotiator<'h> {
+    pub fn new(headers: &'h Headers<'h>) -> Self {
+        Negotiator {
+            headers,
+            vary: Vec::new(),
+        }
+    }
+
+    /// Best offered media type (`type/subtype[; params]`) per `accept`. The weight of an
+    /// offer comes from the most specific matching media range.
+    pub fn media_type<'o>(&mut self, offered: &[&'o str]) -> Option<&'o str> {
+        self.record("accept");
+        let Some(accept) = self.headers.typed::<Accept>() else {
+            return offered.first().copied();
+        };
+        best(offered, |offer| {
+            let offer = MediaType::parse(offer)?;
+            accept
+                .0
+                .iter()
+                .filter(|range| range.item.matches(&offer))
+                .max_by_key(|range| {
+                    let r = &range.item;
+                    (r.type_ != "*", r.subtype != "*", r.params.len())
+                })
+                .map(|range| range.quality)
+        })
+    }
+
+    /// Best offered language tag per `accept-language`, using RFC 4647 basic filtering
+    /// (`en` matches `en-US`), the longest matching range wins.
+    pub fn language<'o>(&mut self, offered: &[&'o str]) -> Option<&'o str> {
+        self.record("accept-language");
+        let Some(accept) = self.headers.typed::<AcceptLanguage>() else {
+            return offered.first().copied();
+        };
+        best(offered, |offer| {
+            accept
+                .0
+                .iter()
+                .filter(|range| language_matches(&range.item, offer))
+                .max_by_key(|range| {
+                    if range.item == "*" {
+                        0
+                    } else {
+                        range.item.len()
+                    }
+                })
+                .map(|range| range.quality)
+        })
+    }
+
+    /// Best offered charset per `accept-charset`.
+    pub fn charset<'o>(&mut self, offered: &[&'o str]) -> Option<&'o str> {
+        self.record("accept-charset");
+        let Some(accept) = self.headers.typed::<AcceptCharset>() else {
+            return offered.first().copied();
+        };
+        best(offered, |offer| accept.quality(offer))
+    }
+
+    /// Request header names consulted so far.
+    pub fn vary(&self) -> &[&'static str] {
+        &self.vary
+    }
+
+    /// Adds the consulted header names to the response `vary`, skipping names already
+    /// listed (or everything, if it's `*`).
+    pub fn append_vary(&self, headers: &mut Headers) {
+        let existing: Vec<Vec<u8>> = headers
+            .get_all(Headers::VARY)
+            .flat_map(|(_, v)| v.split(|&b| b == b','))
+            .map(|t| t.trim_ascii().to_ascii_lowercase())
+            .collect();
+        if existing.iter().any(|t| t == b"*") {
+            return;
+        }
+        let missing: Vec<&str> = self
+            .vary
+            .iter()
+            .filter(|name| !existing.iter().any(|t| t == name.as_bytes()))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            headers.add(Headers::VARY, missing.join(", ").into_bytes());
+        }
+    }
+
+    fn record(&mut self, name: &'static str) {
+        if !self.vary.contains(&name) {
+            self.vary.push(name);
+        }
+    }
+}
+
+/// The offer with the highest non-zero weight, earliest offer on ties.
+fn best<'o, F>(offered: &[&'o str], mut quality: F) -> Option<&'o str>
+where
+    F: FnMut(&str) -> Option<u16>,
+{
+    offered
+        .iter()
+        .filter_map(|&offer| quality(offer).filter(|&q| q > 0).map(|q| (offer, q)))
+        .fold(None, |best: Option<(&str, u16)>, (offer, q)| match best {
+            Some((_, best_q)) if q <= best_q => best,
+            _ => Some((offer, q)),
+        })
+        .map(|(offer, _)| offer)
+}
+
+fn language_matches(range: &str, tag: &str) -> bool {
+    range == "*"
+        || tag.eq_ignore_ascii_case(range)
+        || (tag.len() > range.len()
+            && tag.as_bytes()[range.len()] == b'-'
+            && tag[..range.len()].eq_ignore_ascii_case(range))
+}
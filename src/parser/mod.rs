@@ -1,54 +1,39 @@
-use crate::Headers;
 use memchr::memchr;
 use std::{error::Error, fmt::Display, io};
 use HttpParsingError::*;
 
 mod request;
 pub mod simd;
-pub use request::Request;
+pub(crate) use request::check_framing;
+pub use request::{ParseStatus, Request, RequestLimits, RequestParser};
 
 #[cfg(feature = "client")]
 mod response;
 #[cfg(feature = "client")]
 pub use response::Response;
 
-#[inline]
-fn parse_headers(buf: &[u8]) -> Result<(Headers<'_>, &[u8]), HttpParsingError> {
-    let mut headers = Headers::new();
-    let mut buf = buf;
-
-    loop {
-        if let Some(rest) = buf.strip_prefix(b"\r\n") {
-            return Ok((headers, rest));
+/// `1*DIGIT`, or a comma-separated list of identical values (RFC 9110 8.6).
+fn parse_content_length(value: &[u8]) -> Result<u64, HttpParsingError> {
+    let mut len = None;
+    for v in value.split(|&b| b == b',').map(|v| v.trim_ascii()) {
+        if v.is_empty() || !v.iter().all(u8::is_ascii_digit) {
+            return Err(InvalidContentLength);
         }
-
-        // find '\n'
-        let nl = match memchr(b'\n', buf) {
-            Some(p) => p,
-            None => return Err(UnexpectedEof),
-        };
-
-        // require CRLF
-        if nl == 0 || buf[nl - 1] != b'\r' {
-            buf = &buf[nl + 1..];
-            continue;
+        // SAFETY: all ASCII digits
+        let v = unsafe { std::str::from_utf8_unchecked(v) };
+        let v: u64 = v.parse().map_err(|_| InvalidContentLength)?;
+        if len.is_some_and(|len| len != v) {
+            return Err(InvalidContentLength);
         }
-
-        let line = &buf[..nl - 1];
-        let (name, value) = parse_header_line(line)?;
-        headers.add(name, value);
-
-        buf = &buf[nl + 1..];
+        len = Some(v);
     }
+    len.ok_or(InvalidContentLength)
 }
+
 #[inline(always)]
 fn parse_header_line(line: &[u8]) -> Result<(&str, &[u8]), HttpParsingError> {
     let colon = memchr(b':', line).ok_or(MalformedHeader)?;
-    if !line[..colon]
-        .iter()
-        .copied()
-        .all(is_valid_header_field_byte)
-    {
+    if simd::match_header_name_vectored(&line[..colon]) != colon {
         return Err(MalformedHeader);
     }
 
@@ -74,7 +59,7 @@ fn parse_version(buf: &[u8]) -> Result<(u8, &[u8]), HttpParsingError> {
     Err(UnsupportedHttpVersion)
 }
 
-const fn make_header_field_byte_mask() -> [bool; 256] {
+pub(super) const fn make_header_field_byte_mask() -> [bool; 256] {
     let mut mask = [false; 256];
     let valid = b"!#$%&'*+-.^_`|~ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
     let mut i = 0;
@@ -99,6 +84,23 @@ pub enum HttpParsingError {
     MalformedStatusLine,
     MalformedHeader,
     UnexpectedEof,
+    /// Line terminated by LF without the preceding CR (strict mode).
+    BareLineFeed,
+    /// Invalid `content-length`, or several that disagree (strict mode).
+    InvalidContentLength,
+    /// Both `content-length` and `transfer-encoding` present (strict mode).
+    ContentLengthWithTransferEncoding,
+    /// `transfer-encoding` whose final coding isn't `chunked`, or sent with HTTP/1.0
+    /// (strict mode).
+    InvalidTransferEncoding,
+    /// Header value with bytes outside of VCHAR, obs-text, SP and HTAB (e.g. NUL or CR).
+    InvalidHeaderValue,
+    /// More header fields than [`RequestLimits::max_headers`].
+    TooManyHeaders,
+    /// A header line longer than [`RequestLimits::max_header_size`].
+    HeaderTooLarge,
+    /// A request-target longer than [`RequestLimits::max_uri_length`].
+    UriTooLong,
     IOError(io::Error),
 }
 
@@ -121,6 +123,16 @@ impl Display for HttpParsingError {
             UnsupportedHttpVersion => write!(f, "invalid http version"),
             MalformedHeader => write!(f, "malformed header"),
             UnexpectedEof => write!(f, "unexpected eof"),
+            BareLineFeed => write!(f, "line not terminated by CRLF"),
+            InvalidContentLength => write!(f, "invalid content-length"),
+            ContentLengthWithTransferEncoding => {
+                write!(f, "both content-length and transfer-encoding present")
+            }
+            InvalidTransferEncoding => write!(f, "invalid transfer-encoding"),
+            InvalidHeaderValue => write!(f, "invalid header value"),
+            TooManyHeaders => write!(f, "too many headers"),
+            HeaderTooLarge => write!(f, "header too large"),
+            UriTooLong => write!(f, "uri too long"),
             IOError(e) => write!(f, "io error: {}", e),
         }
     }
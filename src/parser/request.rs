@@ -1,9 +1,10 @@
 use super::{
-    parse_headers, parse_version,
-    simd::{match_path_vectored, match_uri_vectored},
+    parse_content_length, parse_header_line, parse_version,
+    simd::{match_header_value_vectored, match_path_vectored, match_uri_vectored},
     HttpParsingError::{self, *},
 };
 use crate::{Headers, Method, RequestUri};
+use memchr::memchr;
 
 #[derive(Debug)]
 pub struct Request<'b> {
@@ -15,24 +16,377 @@ pub struct Request<'b> {
 }
 
 impl<'b> Request<'b> {
+    /// Parses a complete request head in strict mode: besides the request grammar,
+    /// anything that could make the message length ambiguous (request smuggling) is
+    /// rejected, i.e. bare-LF line endings, invalid or conflicting `content-length`,
+    /// `content-length` together with `transfer-encoding`, and `transfer-encoding` not
+    /// ending in `chunked`. An incomplete head is `UnexpectedEof`, see [`RequestParser`]
+    /// for parsing a head that arrives in pieces.
+    ///
+    /// In both modes header values must be valid field-value bytes and the default
+    /// [`RequestLimits`] apply.
     pub fn parse(buf: &'b [u8]) -> Result<Request<'b>, HttpParsingError> {
-        let start = buf.len();
-        let (method, rest) = parse_method(buf)?;
-        let (uri, rest) = parse_uri(rest)?;
-        let (http_version, rest) = parse_version(rest)?;
-        let rest = rest.get(2..).ok_or(UnexpectedEof)?; // skip "\r\n"
-        let (headers, rest) = parse_headers(rest)?;
+        RequestParser::new().parse_complete(buf)
+    }
+
+    /// Parses a request head without the strict-mode checks of [`Request::parse`].
+    pub fn parse_lenient(buf: &'b [u8]) -> Result<Request<'b>, HttpParsingError> {
+        RequestParser::new().strict(false).parse_complete(buf)
+    }
+}
+
+// ---------------------------------------------------------------------
+// LIMITS
+// ---------------------------------------------------------------------
+
+/// Limits on the parts of a request head, each reported by its own [`HttpParsingError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    max_headers: usize,
+    max_header_size: usize,
+    max_uri_length: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_headers: 100,
+            max_header_size: 8 * 1024,
+            max_uri_length: 8 * 1024,
+        }
+    }
+}
+
+impl RequestLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max number of header fields (default: 100), see [`HttpParsingError::TooManyHeaders`].
+    pub fn max_headers(mut self, n: usize) -> Self {
+        self.max_headers = n;
+        self
+    }
+
+    /// Max size of a single header line without its CRLF (default: 8 KiB), see
+    /// [`HttpParsingError::HeaderTooLarge`].
+    pub fn max_header_size(mut self, n: usize) -> Self {
+        self.max_header_size = n;
+        self
+    }
+
+    /// Max length of the request-target (default: 8 KiB), see
+    /// [`HttpParsingError::UriTooLong`].
+    pub fn max_uri_length(mut self, n: usize) -> Self {
+        self.max_uri_length = n;
+        self
+    }
+}
+
+// ---------------------------------------------------------------------
+// INCREMENTAL PARSER
+// ---------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum ParseStatus<'b> {
+    /// The head is incomplete, at least this many more bytes are needed.
+    Partial(usize),
+    Complete(Request<'b>),
+}
+
+/// Resumable request head parser for heads that arrive in several reads.
+///
+/// Each call to [`RequestParser::parse`] gets the whole buffer read so far (the previous
+/// buffer plus new bytes). Already scanned lines are not parsed again: the parser keeps
+/// its position and the offsets of the headers it has found. After a `Complete` result
+/// or an error the parser is reset and can be reused for the next request.
+///
+/// ```
+/// # use khttp::{ParseStatus, RequestParser};
+/// let mut parser = RequestParser::new();
+/// let mut buf = b"GET /hello HTTP/1.1\r\nhost: exa".to_vec();
+/// assert!(matches!(parser.parse(&buf), Ok(ParseStatus::Partial(_))));
+///
+/// buf.extend_from_slice(b"mple.com\r\n\r\n");
+/// let Ok(ParseStatus::Complete(request)) = parser.parse(&buf) else { panic!() };
+/// assert_eq!(request.uri.path(), "/hello");
+/// assert_eq!(request.headers.get("host"), Some(&b"example.com"[..]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestParser {
+    strict: bool,
+    limits: RequestLimits,
+    /// End of the request line (after its LF), 0 while it is incomplete.
+    headers_start: usize,
+    /// Start of the first line not parsed yet.
+    line_start: usize,
+    /// Bytes before this offset are known not to contain the LF ending the current line.
+    scanned: usize,
+    headers: Vec<HeaderSpan>,
+    content_length: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct HeaderSpan {
+    name: (usize, usize),
+    value: (usize, usize),
+}
+
+impl Default for RequestParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestParser {
+    /// A parser in strict mode, see [`Request::parse`].
+    pub fn new() -> Self {
+        RequestParser {
+            strict: true,
+            limits: RequestLimits::default(),
+            headers_start: 0,
+            line_start: 0,
+            scanned: 0,
+            headers: Vec::new(),
+            content_length: None,
+        }
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn limits(mut self, limits: RequestLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Forgets any partially parsed head.
+    pub fn reset(&mut self) {
+        self.headers_start = 0;
+        self.line_start = 0;
+        self.scanned = 0;
+        self.headers.clear();
+        self.content_length = None;
+    }
+
+    pub fn parse<'b>(&mut self, buf: &'b [u8]) -> Result<ParseStatus<'b>, HttpParsingError> {
+        if buf.len() < self.scanned {
+            self.reset(); // not a continuation of the previous buffer
+        }
+        let result = self.parse_inner(buf);
+        if !matches!(result, Ok(ParseStatus::Partial(_))) {
+            self.reset();
+        }
+        result
+    }
+
+    /// Whether the request line has been parsed, i.e. the parser is in the headers.
+    pub(crate) fn in_headers(&self) -> bool {
+        self.headers_start != 0
+    }
+
+    fn parse_complete<'b>(&mut self, buf: &'b [u8]) -> Result<Request<'b>, HttpParsingError> {
+        match self.parse_inner(buf)? {
+            ParseStatus::Complete(request) => Ok(request),
+            ParseStatus::Partial(_) => Err(UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn parse_inner<'b>(&mut self, buf: &'b [u8]) -> Result<ParseStatus<'b>, HttpParsingError> {
+        if self.headers_start == 0 {
+            let Some(nl) = self.find_line_end(buf) else {
+                self.check_partial_uri(buf)?;
+                return Ok(self.partial(buf));
+            };
+            let (_, uri, _) = parse_request_line(&buf[..nl + 1], self.strict)?;
+            if uri.as_str().len() > self.limits.max_uri_length {
+                return Err(UriTooLong);
+            }
+            self.headers_start = nl + 1;
+            self.line_start = nl + 1;
+            self.scanned = nl + 1;
+        }
+
+        loop {
+            let line = &buf[self.line_start..];
+            if let Some(rest) = line.strip_prefix(b"\r\n") {
+                let buf_offset = buf.len() - rest.len();
+                return self.finish(buf, buf_offset).map(ParseStatus::Complete);
+            }
+            let Some(nl) = self.find_line_end(buf) else {
+                // (+1 for a CR that may already be there)
+                if buf.len() - self.line_start > self.limits.max_header_size + 1 {
+                    return Err(HeaderTooLarge);
+                }
+                return Ok(self.partial(buf));
+            };
+
+            // require CRLF
+            if nl == self.line_start || buf[nl - 1] != b'\r' {
+                if self.strict {
+                    return Err(BareLineFeed);
+                }
+            } else {
+                self.add_header(buf, nl - 1)?;
+            }
+            self.line_start = nl + 1;
+            self.scanned = nl + 1;
+        }
+    }
+
+    /// Offset of the LF ending the line at `line_start`, scanning only new bytes.
+    #[inline]
+    fn find_line_end(&mut self, buf: &[u8]) -> Option<usize> {
+        match memchr(b'\n', &buf[self.scanned..]) {
+            Some(i) => Some(self.scanned + i),
+            None => {
+                self.scanned = buf.len();
+                None
+            }
+        }
+    }
+
+    /// Fails early on a request-target that is already too long in an incomplete line.
+    fn check_partial_uri(&self, buf: &[u8]) -> Result<(), HttpParsingError> {
+        if buf.len() <= self.limits.max_uri_length {
+            return Ok(());
+        }
+        let Some(sp) = memchr(b' ', buf) else {
+            return Ok(()); // still in the method, which is validated once the line is complete
+        };
+        let uri = &buf[sp + 1..];
+        match memchr(b' ', uri).unwrap_or(uri.len()) > self.limits.max_uri_length {
+            true => Err(UriTooLong),
+            false => Ok(()),
+        }
+    }
+
+    fn partial<'b>(&self, buf: &[u8]) -> ParseStatus<'b> {
+        // a line ends in CRLF, lenient mode also accepts a bare LF
+        match self.strict && buf.last() != Some(&b'\r') {
+            true => ParseStatus::Partial(2),
+            false => ParseStatus::Partial(1),
+        }
+    }
+
+    fn add_header(&mut self, buf: &[u8], line_end: usize) -> Result<(), HttpParsingError> {
+        let line = &buf[self.line_start..line_end];
+        if line.len() > self.limits.max_header_size {
+            return Err(HeaderTooLarge);
+        }
+        if self.headers.len() == self.limits.max_headers {
+            return Err(TooManyHeaders);
+        }
+        let (name, value) = parse_header_line(line)?;
+        if match_header_value_vectored(value) != value.len() {
+            return Err(InvalidHeaderValue);
+        }
+        if self.strict && name.eq_ignore_ascii_case(Headers::CONTENT_LENGTH) {
+            let len = parse_content_length(value)?;
+            if self.content_length.is_some_and(|prev| prev != len) {
+                return Err(InvalidContentLength);
+            }
+            self.content_length = Some(len);
+        }
+        let value_start = line_end - value.len();
+        self.headers.push(HeaderSpan {
+            name: (self.line_start, self.line_start + name.len()),
+            value: (value_start, line_end),
+        });
+        Ok(())
+    }
+
+    fn finish<'b>(
+        &self,
+        buf: &'b [u8],
+        buf_offset: usize,
+    ) -> Result<Request<'b>, HttpParsingError> {
+        let (method, uri, http_version) =
+            parse_request_line(&buf[..self.headers_start], self.strict)?;
+
+        let mut headers = Headers::new();
+        for span in &self.headers {
+            // SAFETY: header names were validated as tokens in `add_header`
+            let name = unsafe { std::str::from_utf8_unchecked(&buf[span.name.0..span.name.1]) };
+            let value = &buf[span.value.0..span.value.1];
+            if self.strict && name.eq_ignore_ascii_case(Headers::CONTENT_LENGTH) {
+                continue;
+            }
+            headers.add(name, value);
+        }
+        if self.strict {
+            headers.set_content_length(self.content_length);
+            check_message_length(&headers, http_version)?;
+        }
 
         Ok(Request {
             method,
             uri,
             http_version,
             headers,
-            buf_offset: start - rest.len(),
+            buf_offset,
         })
     }
 }
 
+/// Parses the request line, `line` includes the terminating LF.
+#[inline]
+fn parse_request_line(
+    line: &[u8],
+    strict: bool,
+) -> Result<(Method, RequestUri<'_>, u8), HttpParsingError> {
+    // the line is complete, so running out of bytes means a missing component
+    let eof_as = |e| move |err| if err == UnexpectedEof { e } else { err };
+    let (method, rest) = parse_method(line).map_err(eof_as(MalformedStatusLine))?;
+    let (uri, rest) = parse_uri(rest).map_err(eof_as(UnsupportedHttpVersion))?;
+    let (http_version, rest) = parse_version(rest).map_err(eof_as(UnsupportedHttpVersion))?;
+    match (rest, strict) {
+        (b"\r\n", _) | (b"\n", false) => Ok((method, uri, http_version)),
+        (b"\n", true) => Err(BareLineFeed),
+        _ => Err(MalformedStatusLine),
+    }
+}
+
+/// RFC 9112 6.1 / 6.3: the body length must be unambiguous.
+fn check_message_length(headers: &Headers, http_version: u8) -> Result<(), HttpParsingError> {
+    check_framing(headers)?;
+    match http_version == 0 && headers.get_all(Headers::TRANSFER_ENCODING).next().is_some() {
+        true => Err(InvalidTransferEncoding),
+        false => Ok(()),
+    }
+}
+
+/// The version-independent part of [`check_message_length`], shared with
+/// [`BodyReader::from_request_strict`](crate::BodyReader::from_request_strict).
+pub(crate) fn check_framing(headers: &Headers) -> Result<(), HttpParsingError> {
+    if headers.get_all(Headers::TRANSFER_ENCODING).next().is_none() {
+        return Ok(());
+    }
+    if headers.get_content_length().is_some() {
+        return Err(ContentLengthWithTransferEncoding);
+    }
+    // chunked must be the final coding, and only applied once
+    let codings = headers
+        .get_all(Headers::TRANSFER_ENCODING)
+        .flat_map(|(_, v)| v.split(|&b| b == b','))
+        .map(|c| c.trim_ascii())
+        .filter(|c| !c.is_empty());
+    let mut chunked_seen = false;
+    for coding in codings {
+        if chunked_seen {
+            return Err(InvalidTransferEncoding);
+        }
+        chunked_seen = coding.eq_ignore_ascii_case(b"chunked");
+    }
+    match chunked_seen {
+        true => Ok(()),
+        false => Err(InvalidTransferEncoding),
+    }
+}
+
 #[inline]
 fn parse_method(buf: &[u8]) -> Result<(Method, &[u8]), HttpParsingError> {
     // hot paths: GET and POST
@@ -154,16 +508,16 @@ fn parse_uri(buf: &[u8]) -> Result<(RequestUri<'_>, &[u8]), HttpParsingError> {
         let n = match_uri_vectored(&buf[i..]); // scan path up to SP
         i += n;
         match buf.get(i).copied() {
-            Some(b' ') => {}                            // all good, we found the SP
-            Some(_) => return Err(MalformedStatusLine), // invalid char
-            None => return Err(UnexpectedEof),          // TODO: is this correct?
+            Some(b' ') => {}                                         // all good, we found the SP
+            Some(b'\r' | b'\n') | None => return Err(UnexpectedEof), // line ended early
+            Some(_) => return Err(MalformedStatusLine),              // invalid char
         }
     } else {
         // otherwise we must be at SP right after the path
         match buf.get(i) {
             Some(b' ') => {}
+            Some(b'\r' | b'\n') | None => return Err(UnexpectedEof),
             Some(_) => return Err(MalformedStatusLine),
-            None => return Err(UnexpectedEof),
         }
     }
 
@@ -174,7 +528,7 @@ fn parse_uri(buf: &[u8]) -> Result<(RequestUri<'_>, &[u8]), HttpParsingError> {
     Ok((RequestUri::new(uri, path_start_i, path_end_i), rest))
 }
 
-const fn make_uri_byte_mask() -> [bool; 256] {
+pub(super) const fn make_uri_byte_mask() -> [bool; 256] {
     let mut mask = [false; 256];
     let valid =
         b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~:/?#[]@!$&'()*+,;=%";
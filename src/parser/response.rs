@@ -1,5 +1,6 @@
-use super::{parse_headers, parse_version, HttpParsingError, HttpParsingError::*};
+use super::{parse_header_line, parse_version, HttpParsingError, HttpParsingError::*};
 use crate::{Headers, Status};
+use memchr::memchr;
 
 #[derive(Debug)]
 pub struct Response<'b> {
@@ -72,3 +73,33 @@ fn parse_response_status_code(buf: &[u8]) -> Result<u16, HttpParsingError> {
 
     Ok((hundreds - b'0') as u16 * 100 + (tens - b'0') as u16 * 10 + (ones - b'0') as u16)
 }
+
+#[inline]
+fn parse_headers(buf: &[u8]) -> Result<(Headers<'_>, &[u8]), HttpParsingError> {
+    let mut headers = Headers::new();
+    let mut buf = buf;
+
+    loop {
+        if let Some(rest) = buf.strip_prefix(b"\r\n") {
+            return Ok((headers, rest));
+        }
+
+        // find '\n'
+        let nl = match memchr(b'\n', buf) {
+            Some(p) => p,
+            None => return Err(UnexpectedEof),
+        };
+
+        // require CRLF
+        if nl == 0 || buf[nl - 1] != b'\r' {
+            buf = &buf[nl + 1..];
+            continue;
+        }
+
+        let line = &buf[..nl - 1];
+        let (name, value) = parse_header_line(line)?;
+        headers.add(name, value);
+
+        buf = &buf[nl + 1..];
+    }
+}
@@ -1,4 +1,241 @@
-// Code is *heavily* adapted from:
+//! Byte-class scanners used by the parser, with SSSE3 / AVX2 (x86_64) and NEON (aarch64)
+//! code paths selected by runtime feature detection and a portable SWAR fallback.
+//!
+//! Each scanner returns the length of the longest prefix of `buf` whose bytes all belong
+//! to the class, i.e. the offset of the first byte outside of it.
+//!
+//! Set membership for the ASCII-only classes (path, uri, header name) is computed with
+//! the nibble lookup technique: byte `b` is in the class iff
+//! `LO[b & 0xf] & HI[b >> 4] != 0`, where `HI[h]` is the bit `1 << h` for `h < 8` (and 0
+//! for non-ASCII) and `LO[l]` has bit `h` set iff `(h << 4) | l` is in the class. Both
+//! lookups are a single byte shuffle per vector.
+//!
+//! The 128-bit x86 path only needs SSSE3 (`pshufb`). SSE4.2's `pcmpistri` could match
+//! these classes as byte ranges, but it has several times the latency of the two
+//! shuffles, so the backend doesn't require SSE4.2.
+
+use super::request::{is_valid_uri_byte, make_uri_byte_mask};
+use super::{is_valid_header_field_byte, make_header_field_byte_mask};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Scalar,
+    Swar,
+    Ssse3,
+    Avx2,
+    Neon,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scan {
+    /// request-target bytes up to the query (`?` and SP end the path)
+    Path,
+    /// request-target bytes
+    Uri,
+    /// token bytes
+    HeaderName,
+    /// field-value bytes: HTAB, SP, VCHAR and obs-text
+    HeaderValue,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 5] = [
+        Backend::Scalar,
+        Backend::Swar,
+        Backend::Ssse3,
+        Backend::Avx2,
+        Backend::Neon,
+    ];
+
+    /// The fastest backend supported by this CPU (detected once).
+    #[inline]
+    pub fn detect() -> Backend {
+        match DETECTED.load(Ordering::Relaxed) {
+            UNDETECTED => {
+                let backend = Self::ALL
+                    .into_iter()
+                    .rev()
+                    .find(|b| b.is_supported())
+                    .unwrap_or(Backend::Scalar);
+                DETECTED.store(backend as u8, Ordering::Relaxed);
+                backend
+            }
+            b => Self::ALL[b as usize],
+        }
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar | Backend::Swar => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::Ssse3 => std::arch::is_x86_feature_detected!("ssse3"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => std::arch::is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "aarch64")]
+            Backend::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            Backend::Swar => "swar",
+            Backend::Ssse3 => "ssse3",
+            Backend::Avx2 => "avx2",
+            Backend::Neon => "neon",
+        }
+    }
+}
+
+const UNDETECTED: u8 = u8::MAX;
+static DETECTED: AtomicU8 = AtomicU8::new(UNDETECTED);
+
+/// Runs `scan` with a specific backend, falling back to scalar if it isn't supported.
+pub fn scan_with(backend: Backend, scan: Scan, buf: &[u8]) -> usize {
+    let backend = match backend.is_supported() {
+        true => backend,
+        false => Backend::Scalar,
+    };
+    // SAFETY: the backend is supported by this CPU
+    unsafe { scan_unchecked(backend, scan, buf) }
+}
+
+#[inline]
+pub fn match_path_vectored(buf: &[u8]) -> usize {
+    // SAFETY: detected backends are supported
+    unsafe { scan_unchecked(Backend::detect(), Scan::Path, buf) }
+}
+
+#[inline]
+pub fn match_uri_vectored(buf: &[u8]) -> usize {
+    // SAFETY: detected backends are supported
+    unsafe { scan_unchecked(Backend::detect(), Scan::Uri, buf) }
+}
+
+#[inline]
+pub fn match_header_name_vectored(buf: &[u8]) -> usize {
+    // SAFETY: detected backends are supported
+    unsafe { scan_unchecked(Backend::detect(), Scan::HeaderName, buf) }
+}
+
+#[inline]
+pub fn match_header_value_vectored(buf: &[u8]) -> usize {
+    // SAFETY: detected backends are supported
+    unsafe { scan_unchecked(Backend::detect(), Scan::HeaderValue, buf) }
+}
+
+/// # Safety
+/// `backend` must be supported by the CPU.
+#[inline]
+unsafe fn scan_unchecked(backend: Backend, scan: Scan, buf: &[u8]) -> usize {
+    // short inputs aren't worth a vector setup
+    if buf.len() < 16 {
+        return scalar::scan(scan, buf);
+    }
+    match backend {
+        Backend::Swar => swar::scan(scan, buf),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Ssse3 => match class_of(scan) {
+            Some(class) => x86::ssse3_class(buf, class, scan),
+            None => x86::ssse3_value(buf),
+        },
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx2 => match class_of(scan) {
+            Some(class) => x86::avx2_class(buf, class, scan),
+            None => x86::avx2_value(buf),
+        },
+        #[cfg(target_arch = "aarch64")]
+        Backend::Neon => match class_of(scan) {
+            Some(class) => neon::class(buf, class, scan),
+            None => neon::value(buf),
+        },
+        _ => scalar::scan(scan, buf),
+    }
+}
+
+// ---------------------------------------------------------------------
+// CLASSES
+// ---------------------------------------------------------------------
+
+struct Class {
+    lo: [u8; 16],
+    hi: [u8; 16],
+}
+
+const fn nibble_class(mask: &[bool; 256]) -> Class {
+    let mut lo = [0u8; 16];
+    let mut hi = [0u8; 16];
+    let mut h = 0;
+    while h < 8 {
+        hi[h] = 1 << h;
+        h += 1;
+    }
+    let mut b = 0;
+    while b < 256 {
+        if mask[b] {
+            assert!(b < 0x80, "nibble classes are ASCII-only");
+            lo[b & 0xf] |= 1 << (b >> 4);
+        }
+        b += 1;
+    }
+    Class { lo, hi }
+}
+
+const fn without(mut mask: [bool; 256], b: u8) -> [bool; 256] {
+    mask[b as usize] = false;
+    mask
+}
+
+static PATH_CLASS: Class = nibble_class(&without(make_uri_byte_mask(), b'?'));
+static URI_CLASS: Class = nibble_class(&make_uri_byte_mask());
+static HEADER_NAME_CLASS: Class = nibble_class(&make_header_field_byte_mask());
+
+#[allow(dead_code)] // unused without a SIMD backend for the target
+fn class_of(scan: Scan) -> Option<&'static Class> {
+    match scan {
+        Scan::Path => Some(&PATH_CLASS),
+        Scan::Uri => Some(&URI_CLASS),
+        Scan::HeaderName => Some(&HEADER_NAME_CLASS),
+        Scan::HeaderValue => None,
+    }
+}
+
+#[inline(always)]
+fn is_header_value_byte(b: u8) -> bool {
+    b == b'\t' || (b >= 0x20 && b != 0x7f)
+}
+
+// ---------------------------------------------------------------------
+// SCALAR
+// ---------------------------------------------------------------------
+
+mod scalar {
+    use super::*;
+
+    #[inline]
+    pub(super) fn scan(scan: Scan, buf: &[u8]) -> usize {
+        match scan {
+            Scan::Path => position(buf, |b| b != b'?' && is_valid_uri_byte(b)),
+            Scan::Uri => position(buf, is_valid_uri_byte),
+            Scan::HeaderName => position(buf, is_valid_header_field_byte),
+            Scan::HeaderValue => position(buf, is_header_value_byte),
+        }
+    }
+
+    #[inline(always)]
+    fn position(buf: &[u8], member: impl Fn(u8) -> bool) -> usize {
+        buf.iter().position(|&b| !member(b)).unwrap_or(buf.len())
+    }
+}
+
+// ---------------------------------------------------------------------
+// SWAR
+// ---------------------------------------------------------------------
+
+// The word tricks are adapted from httparse's SWAR scanner:
 // https://github.com/seanmonstar/httparse/blob/36147265105338185f49ceac51a9bea83941a1ec/src/simd/swar.rs
 //
 // Copyright (c) 2015-2025 Sean McArthur
@@ -21,117 +258,225 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-use super::request::is_valid_uri_byte;
+/// Word-at-a-time scanning: each word gets a cheap test that never misses a byte outside
+/// the class but may flag bytes inside it, a flagged word is settled by the scalar scan.
+/// Header names are short and have too many excluded bytes to pay off, they stay scalar.
+mod swar {
+    use super::{scalar, Scan};
 
-#[inline]
-pub fn match_path_vectored(buf: &[u8]) -> usize {
-    swar_match_path_vectored(buf)
-}
+    // Adapt block-size to match native register size, i.e: 32bit => 4, 64bit => 8
+    const BLOCK_SIZE: usize = core::mem::size_of::<usize>();
 
-#[inline]
-pub fn match_uri_vectored(buf: &[u8]) -> usize {
-    swar_match_uri_vectored(buf)
-}
+    const ONE: usize = uniform_block(0x01);
+    const M128: usize = uniform_block(0x80);
 
-#[inline]
-fn swar_match_path_vectored(buf: &[u8]) -> usize {
-    let len = buf.len();
-    let mut i = 0;
+    // request-target bytes in 0x21..=0x7a that aren't in the URI set
+    const URI_EXCLUDED: [usize; 6] = [
+        uniform_block(b'"'),
+        uniform_block(b'<'),
+        uniform_block(b'>'),
+        uniform_block(b'\\'),
+        uniform_block(b'^'),
+        uniform_block(b'`'),
+    ];
+
+    pub(super) fn scan(scan: Scan, buf: &[u8]) -> usize {
+        let suspect: fn(usize) -> bool = match scan {
+            Scan::Path => |x| uri_suspect(x) || has_byte(x, uniform_block(b'?')),
+            Scan::Uri => uri_suspect,
+            Scan::HeaderName => return scalar::scan(scan, buf),
+            Scan::HeaderValue => |x| has_less(x, 0x20) || has_byte(x, uniform_block(0x7f)),
+        };
+
+        let mut i = 0;
+        while i + BLOCK_SIZE <= buf.len() {
+            // SAFETY: `i + BLOCK_SIZE <= buf.len()`, and the read is unaligned
+            let x = unsafe { core::ptr::read_unaligned(buf.as_ptr().add(i) as *const usize) };
+            if suspect(x) {
+                let n = scalar::scan(scan, &buf[i..i + BLOCK_SIZE]);
+                if n < BLOCK_SIZE {
+                    return i + n;
+                }
+            }
+            i += BLOCK_SIZE;
+        }
+        i + scalar::scan(scan, &buf[i..])
+    }
+
+    /// Controls, SP, non-ASCII, `{`, `|`, `}`, `~` (the only false positive), DEL and the
+    /// excluded printable bytes below `{`.
+    #[inline(always)]
+    fn uri_suspect(x: usize) -> bool {
+        has_less(x, 0x21) || has_more(x, 0x7a) || URI_EXCLUDED.iter().any(|&b| has_byte(x, b))
+    }
+
+    /// Some byte is `< n`, for `n <= 0x80`.
+    #[inline(always)]
+    fn has_less(x: usize, n: u8) -> bool {
+        x.wrapping_sub(uniform_block(n)) & !x & M128 != 0
+    }
+
+    /// Some byte is `> n`, for `n < 0x80`; no carries as the per-byte sums stay below 0x100.
+    #[inline(always)]
+    fn has_more(x: usize, n: u8) -> bool {
+        (x.wrapping_add(uniform_block(0x7f - n)) | x) & M128 != 0
+    }
 
-    while i + BLOCK_SIZE <= len {
-        let x = unsafe { core::ptr::read_unaligned(buf.as_ptr().add(i) as *const usize) };
+    #[inline(always)]
+    fn has_byte(x: usize, block: usize) -> bool {
+        let y = x ^ block;
+        y.wrapping_sub(ONE) & !y & M128 != 0
+    }
 
-        // byte-equality for '?' and ' ':
-        // hit(c) = ((x ^ C) - 0x01) & ~(x ^ C) & 0x80 per byte
-        const ONE: usize = uniform_block(0x01);
-        const M128: usize = uniform_block(0x80);
-        const QQ: usize = uniform_block(b'?');
-        const SP: usize = uniform_block(b' ');
+    // creates a usize whose bytes are each equal to b
+    const fn uniform_block(b: u8) -> usize {
+        usize::from_ne_bytes([b; BLOCK_SIZE])
+    }
+}
 
-        let yq = x ^ QQ;
-        let hq = yq.wrapping_sub(ONE) & !yq & M128;
+// ---------------------------------------------------------------------
+// X86_64
+// ---------------------------------------------------------------------
 
-        let ys = x ^ SP;
-        let hs = ys.wrapping_sub(ONE) & !ys & M128;
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{scalar, Class, Scan};
+    use std::arch::x86_64::*;
 
-        let hit = hq | hs;
-        if hit != 0 {
-            return i + offsetnz(hit);
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn ssse3_class(buf: &[u8], class: &Class, scan: Scan) -> usize {
+        let lo_tbl = _mm_loadu_si128(class.lo.as_ptr() as *const __m128i);
+        let hi_tbl = _mm_loadu_si128(class.hi.as_ptr() as *const __m128i);
+        let nibble = _mm_set1_epi8(0x0f);
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            let x = _mm_loadu_si128(buf.as_ptr().add(i) as *const __m128i);
+            let lo = _mm_shuffle_epi8(lo_tbl, _mm_and_si128(x, nibble));
+            let hi = _mm_shuffle_epi8(hi_tbl, _mm_and_si128(_mm_srli_epi16(x, 4), nibble));
+            let outside = _mm_cmpeq_epi8(_mm_and_si128(lo, hi), _mm_setzero_si128());
+            let bits = _mm_movemask_epi8(outside) as u32;
+            if bits != 0 {
+                return i + bits.trailing_zeros() as usize;
+            }
+            i += 16;
         }
-        i += BLOCK_SIZE;
+        i + scalar::scan(scan, &buf[i..])
     }
 
-    // read tail
-    while i < len {
-        let b = unsafe { *buf.get_unchecked(i) };
-        if b == b'?' || b == b' ' {
-            break;
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn ssse3_value(buf: &[u8]) -> usize {
+        let max_ctl = _mm_set1_epi8(0x1f);
+        let tab = _mm_set1_epi8(b'\t' as i8);
+        let del = _mm_set1_epi8(0x7f);
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            let x = _mm_loadu_si128(buf.as_ptr().add(i) as *const __m128i);
+            let ctl = _mm_cmpeq_epi8(_mm_min_epu8(x, max_ctl), x);
+            let ctl = _mm_andnot_si128(_mm_cmpeq_epi8(x, tab), ctl);
+            let outside = _mm_or_si128(ctl, _mm_cmpeq_epi8(x, del));
+            let bits = _mm_movemask_epi8(outside) as u32;
+            if bits != 0 {
+                return i + bits.trailing_zeros() as usize;
+            }
+            i += 16;
         }
-        i += 1;
+        i + scalar::scan(Scan::HeaderValue, &buf[i..])
     }
-    i
-}
 
-#[inline]
-fn swar_match_uri_vectored(buf: &[u8]) -> usize {
-    let len = buf.len();
-    let mut i = 0;
-
-    while i + BLOCK_SIZE <= len {
-        let x = unsafe { core::ptr::read_unaligned(buf.as_ptr().add(i) as *const usize) };
-        // 33 <= (x != 127) <= 255
-        const M: u8 = 0x21;
-        // uniform block full of exclamation mark (!) (33).
-        const BM: usize = uniform_block(M);
-        // uniform block full of 1.
-        const ONE: usize = uniform_block(0x01);
-        // uniform block full of DEL (127).
-        const DEL: usize = uniform_block(0x7f);
-        // uniform block full of 128.
-        const M128: usize = uniform_block(128);
-
-        let lt = x.wrapping_sub(BM) & !x;
-        let y = x ^ DEL;
-        let eq = y.wrapping_sub(ONE) & !y;
-
-        let hit = (lt | eq) & M128;
-        if hit != 0 {
-            // find the first offending byte in this word and return
-            return i + offsetnz(hit);
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn avx2_class(buf: &[u8], class: &Class, scan: Scan) -> usize {
+        // the shuffle works per 128-bit lane, so both lanes get the tables
+        let lo_tbl = _mm256_broadcastsi128_si256(_mm_loadu_si128(class.lo.as_ptr() as *const _));
+        let hi_tbl = _mm256_broadcastsi128_si256(_mm_loadu_si128(class.hi.as_ptr() as *const _));
+        let nibble = _mm256_set1_epi8(0x0f);
+        let mut i = 0;
+        while i + 32 <= buf.len() {
+            let x = _mm256_loadu_si256(buf.as_ptr().add(i) as *const __m256i);
+            let lo = _mm256_shuffle_epi8(lo_tbl, _mm256_and_si256(x, nibble));
+            let hi = _mm256_shuffle_epi8(hi_tbl, _mm256_and_si256(_mm256_srli_epi16(x, 4), nibble));
+            let outside = _mm256_cmpeq_epi8(_mm256_and_si256(lo, hi), _mm256_setzero_si256());
+            let bits = _mm256_movemask_epi8(outside) as u32;
+            if bits != 0 {
+                return i + bits.trailing_zeros() as usize;
+            }
+            i += 32;
         }
-        i += BLOCK_SIZE;
+        i + ssse3_class(&buf[i..], class, scan)
     }
 
-    // read tail
-    while i < len {
-        if !is_valid_uri_byte(unsafe { *buf.get_unchecked(i) }) {
-            break;
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn avx2_value(buf: &[u8]) -> usize {
+        let max_ctl = _mm256_set1_epi8(0x1f);
+        let tab = _mm256_set1_epi8(b'\t' as i8);
+        let del = _mm256_set1_epi8(0x7f);
+        let mut i = 0;
+        while i + 32 <= buf.len() {
+            let x = _mm256_loadu_si256(buf.as_ptr().add(i) as *const __m256i);
+            let ctl = _mm256_cmpeq_epi8(_mm256_min_epu8(x, max_ctl), x);
+            let ctl = _mm256_andnot_si256(_mm256_cmpeq_epi8(x, tab), ctl);
+            let outside = _mm256_or_si256(ctl, _mm256_cmpeq_epi8(x, del));
+            let bits = _mm256_movemask_epi8(outside) as u32;
+            if bits != 0 {
+                return i + bits.trailing_zeros() as usize;
+            }
+            i += 32;
         }
-        i += 1;
+        i + ssse3_value(&buf[i..])
     }
-    i
 }
 
-// Adapt block-size to match native register size, i.e: 32bit => 4, 64bit => 8
-const BLOCK_SIZE: usize = core::mem::size_of::<usize>();
+// ---------------------------------------------------------------------
+// AARCH64
+// ---------------------------------------------------------------------
 
-// creates a u64 whose bytes are each equal to b
-const fn uniform_block(b: u8) -> usize {
-    usize::from_ne_bytes([b; BLOCK_SIZE])
-}
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::{scalar, Class, Scan};
+    use std::arch::aarch64::*;
 
-#[inline]
-fn offsetnz(block: usize) -> usize {
-    // fast path optimistic case (common for long valid sequences)
-    if block == 0 {
-        return BLOCK_SIZE;
+    /// Index of the first 0xff byte of a comparison result, if any.
+    #[inline(always)]
+    unsafe fn first_set(mask: uint8x16_t) -> Option<usize> {
+        // narrow each byte to a nibble: 64 bits, 4 per input byte
+        let nibbles = vshrn_n_u16::<4>(vreinterpretq_u16_u8(mask));
+        let bits = vget_lane_u64::<0>(vreinterpret_u64_u8(nibbles));
+        (bits != 0).then(|| bits.trailing_zeros() as usize / 4)
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn class(buf: &[u8], class: &Class, scan: Scan) -> usize {
+        let lo_tbl = vld1q_u8(class.lo.as_ptr());
+        let hi_tbl = vld1q_u8(class.hi.as_ptr());
+        let nibble = vdupq_n_u8(0x0f);
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            let x = vld1q_u8(buf.as_ptr().add(i));
+            let lo = vqtbl1q_u8(lo_tbl, vandq_u8(x, nibble));
+            let hi = vqtbl1q_u8(hi_tbl, vshrq_n_u8::<4>(x));
+            let outside = vceqzq_u8(vandq_u8(lo, hi));
+            if let Some(j) = first_set(outside) {
+                return i + j;
+            }
+            i += 16;
+        }
+        i + scalar::scan(scan, &buf[i..])
     }
 
-    // perf: rust will unroll this loop
-    for (i, b) in block.to_ne_bytes().iter().copied().enumerate() {
-        if b != 0 {
-            return i;
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn value(buf: &[u8]) -> usize {
+        let space = vdupq_n_u8(0x20);
+        let tab = vdupq_n_u8(b'\t');
+        let del = vdupq_n_u8(0x7f);
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            let x = vld1q_u8(buf.as_ptr().add(i));
+            let ctl = vbicq_u8(vcltq_u8(x, space), vceqq_u8(x, tab));
+            let outside = vorrq_u8(ctl, vceqq_u8(x, del));
+            if let Some(j) = first_set(outside) {
+                return i + j;
+            }
+            i += 16;
         }
+        i + scalar::scan(Scan::HeaderValue, &buf[i..])
     }
-    unreachable!()
 }
@@ -0,0 +1,108 @@
+//! Percent-encoding (RFC 3986 2.1) and `application/x-www-form-urlencoded` helpers.
+
+use std::borrow::Cow;
+
+/// Strictly decodes `%XX` escapes. Returns `None` on a malformed escape or if
+/// the decoded bytes are not valid UTF-8. `+` is left as is.
+pub fn decode(s: &str) -> Option<Cow<'_, str>> {
+    match decode_bytes(s.as_bytes())? {
+        Cow::Borrowed(_) => Some(Cow::Borrowed(s)),
+        Cow::Owned(bytes) => String::from_utf8(bytes).ok().map(Cow::Owned),
+    }
+}
+
+/// Strictly decodes `%XX` escapes in raw bytes, `None` on a malformed escape.
+pub fn decode_bytes(bytes: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let Some(first) = memchr::memchr(b'%', bytes) else {
+        return Some(Cow::Borrowed(bytes));
+    };
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..first]);
+    let mut i = first;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            out.push(hex_pair(bytes.get(i + 1..i + 3)?)?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(Cow::Owned(out))
+}
+
+/// Decodes a form/query component: `+` becomes a space, malformed escapes are kept
+/// literally and invalid UTF-8 is replaced with U+FFFD (as browsers do).
+pub fn decode_form(s: &str) -> Cow<'_, str> {
+    let bytes = s.as_bytes();
+    if !bytes.iter().any(|&b| b == b'%' || b == b'+') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match bytes.get(i + 1..i + 3).and_then(hex_pair) {
+                Some(b) => {
+                    out.push(b);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    match String::from_utf8(out) {
+        Ok(s) => Cow::Owned(s),
+        Err(e) => Cow::Owned(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+    }
+}
+
+/// Encodes everything except unreserved characters (`A-Z a-z 0-9 - . _ ~`).
+pub fn encode_component(s: &str) -> Cow<'_, str> {
+    encode_with(s, |_| false, false)
+}
+
+/// Encodes a single path segment, keeping `pchar` characters (sub-delims, `:` and `@`).
+/// `/` is encoded.
+pub fn encode_path_segment(s: &str) -> Cow<'_, str> {
+    encode_with(s, |b| b"!$&'()*+,;=:@".contains(&b), false)
+}
+
+/// Encodes a form/query component, spaces become `+`.
+pub fn encode_form(s: &str) -> Cow<'_, str> {
+    encode_with(s, |_| false, true)
+}
+
+fn encode_with(s: &str, keep: impl Fn(u8) -> bool, space_as_plus: bool) -> Cow<'_, str> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    let is_safe = |b: u8| b.is_ascii_alphanumeric() || b"-._~".contains(&b) || keep(b);
+    let bytes = s.as_bytes();
+    let Some(first) = bytes.iter().position(|&b| !is_safe(b)) else {
+        return Cow::Borrowed(s);
+    };
+
+    let mut out = String::with_capacity(bytes.len() + 16);
+    out.push_str(&s[..first]);
+    for &b in &bytes[first..] {
+        if is_safe(b) {
+            out.push(b as char);
+        } else if b == b' ' && space_as_plus {
+            out.push('+');
+        } else {
+            out.push('%');
+            out.push(HEX[(b >> 4) as usize] as char);
+            out.push(HEX[(b & 0xf) as usize] as char);
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn hex_pair(pair: &[u8]) -> Option<u8> {
+    let hi = (pair[0] as char).to_digit(16)?;
+    let lo = (pair[1] as char).to_digit(16)?;
+    Some((hi * 16 + lo) as u8)
+}
@@ -127,6 +127,17 @@ impl HttpPrinter {
         }
     }
 
+    /// Writes only the response head, announcing a body of `content_length` bytes.
+    pub fn write_response_head<W: Write>(
+        mut writer: W,
+        status: &Status,
+        headers: &Headers,
+        content_length: u64,
+    ) -> io::Result<()> {
+        let strat = BodyStrategy::Streaming(io::empty(), content_length);
+        writer.write_all(&build_response_head(status, headers, &strat))
+    }
+
     #[cfg(feature = "client")]
     pub fn write_request<W: Write, R: Read>(
         writer: W,
@@ -334,7 +345,7 @@ fn u16_to_ascii(n: u16) -> [u8; 4] {
     ]
 }
 
-fn probe_body<R: Read>(src: &mut R, max: usize) -> io::Result<(Vec<u8>, bool)> {
+pub(crate) fn probe_body<R: Read>(src: &mut R, max: usize) -> io::Result<(Vec<u8>, bool)> {
     let mut collected = Vec::with_capacity(128);
 
     while collected.len() < max {
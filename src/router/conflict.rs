@@ -0,0 +1,105 @@
+//! Route conflicts reported by [`RouterBuilder::try_build`](super::RouterBuilder::try_build).
+
+use super::tree::rank_of;
+use super::{RoutePattern, RouteSegment};
+use crate::Method;
+use std::{error::Error, fmt};
+
+/// Why [`RouterBuilder::try_build`](super::RouterBuilder::try_build) rejected the routes.
+/// Paths are the full paths, group prefixes included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteConflict {
+    /// `route` differs from `existing` at most in param names, [`build`] would replace
+    /// `existing` with it.
+    ///
+    /// [`build`]: super::RouterBuilder::build
+    Duplicate {
+        method: Method,
+        route: String,
+        existing: String,
+    },
+    /// `route` never matches: `by` matches every path it does and takes precedence, e.g.
+    /// `/a/:id` over `/a/*`.
+    Shadowed {
+        method: Method,
+        route: String,
+        by: String,
+    },
+    /// A route name used more than once, reported by [`build`] (as a panic) too.
+    ///
+    /// [`build`]: super::RouterBuilder::build
+    DuplicateName(String),
+    /// A host pattern registered more than once (ignoring case), [`build`] keeps the routes
+    /// of the last one.
+    ///
+    /// [`build`]: super::RouterBuilder::build
+    DuplicateHost(String),
+}
+
+impl fmt::Display for RouteConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteConflict::Duplicate {
+                method,
+                route,
+                existing,
+            } => write!(f, "route {method} {route} duplicates {method} {existing}"),
+            RouteConflict::Shadowed { method, route, by } => {
+                write!(f, "route {method} {route} is shadowed by {method} {by}")
+            }
+            RouteConflict::DuplicateName(name) => write!(f, "duplicate route name: {name}"),
+            RouteConflict::DuplicateHost(pattern) => write!(f, "duplicate host: {pattern}"),
+        }
+    }
+}
+
+impl Error for RouteConflict {}
+
+/// First pattern route (index into `patterns`, in registration order) that another one
+/// shadows, with the index of that one.
+pub(super) fn find_shadowed(patterns: &[&RoutePattern]) -> Option<(usize, usize)> {
+    for (a, pattern) in patterns.iter().enumerate() {
+        let rank = rank_of(pattern, a);
+        let by = patterns
+            .iter()
+            .enumerate()
+            .find(|&(b, other)| b != a && rank_of(other, b) > rank && covers(other, pattern));
+        if let Some((b, _)) = by {
+            return Some((a, b));
+        }
+    }
+    None
+}
+
+/// Whether `b` matches every path `a` matches. Only decided for `a` without `**`, and `b`
+/// without `**` or ending in it, else `false`.
+fn covers(b: &RoutePattern, a: &RoutePattern) -> bool {
+    if a.pattern.contains(&RouteSegment::DoubleWildcard) {
+        return false;
+    }
+    let (segments, open) = match b.pattern.split_last() {
+        Some((RouteSegment::DoubleWildcard, prefix)) => (prefix, true),
+        _ => (&b.pattern[..], false),
+    };
+    if segments.contains(&RouteSegment::DoubleWildcard) {
+        return false;
+    }
+    let lengths_ok = match open {
+        true => a.pattern.len() >= segments.len(),
+        false => a.pattern.len() == segments.len(),
+    };
+    lengths_ok
+        && segments
+            .iter()
+            .zip(&a.pattern)
+            .all(|(b, a)| segment_covers(b, a))
+}
+
+fn segment_covers(b: &RouteSegment, a: &RouteSegment) -> bool {
+    match (b, a) {
+        (RouteSegment::Wildcard, _) => true,
+        (RouteSegment::Param(p), _) if p.constraint.is_none() && p.suffix.is_empty() => true,
+        (RouteSegment::Param(p), RouteSegment::Literal(lit)) => p.match_value(lit).is_some(),
+        (b, a) => b == a,
+    }
+}
@@ -0,0 +1,85 @@
+//! Host patterns of virtual hosts, see [`RouterBuilder::host`](super::RouterBuilder::host).
+
+use super::RouteParams;
+
+/// `example.com`, `*.example.com` or `:tenant.example.com`, matched case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct HostPattern {
+    labels: Vec<HostLabel>,
+    /// leading `*`, matches one or more labels
+    wildcard: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostLabel {
+    Literal(String),
+    Param(String),
+}
+
+impl HostPattern {
+    pub(super) fn parse(pattern: &str) -> HostPattern {
+        let trimmed = pattern.trim_end_matches('.');
+        let (wildcard, rest) = match trimmed.strip_prefix("*.") {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let labels = rest
+            .split('.')
+            .map(|label| match label.strip_prefix(':') {
+                Some(name) if !name.is_empty() => HostLabel::Param(name.to_string()),
+                _ if label.is_empty() || label.contains([':', '*']) => {
+                    panic!("invalid host pattern: {pattern}")
+                }
+                _ => HostLabel::Literal(label.to_ascii_lowercase()),
+            })
+            .collect();
+        HostPattern { labels, wildcard }
+    }
+
+    /// Patterns are tried in descending order: exact names, then names with params, then
+    /// wildcards, each with more labels first.
+    pub(super) fn rank(&self) -> (bool, bool, usize) {
+        let exact = self
+            .labels
+            .iter()
+            .all(|l| matches!(l, HostLabel::Literal(_)));
+        (!self.wildcard, exact, self.labels.len())
+    }
+
+    /// The host params if `hostname` (see [`hostname`]) matches.
+    pub(super) fn matches<'a, 'r>(&'a self, hostname: &'r str) -> Option<RouteParams<'a, 'r>> {
+        let mut params = RouteParams::new();
+        let mut parts = hostname.rsplit('.');
+        for label in self.labels.iter().rev() {
+            match (label, parts.next()) {
+                (HostLabel::Literal(lit), Some(part)) if lit.eq_ignore_ascii_case(part) => {}
+                (HostLabel::Param(name), Some(part)) if !part.is_empty() => {
+                    params.insert(name.as_str(), part)
+                }
+                _ => return None,
+            }
+        }
+        match parts.next() {
+            None => (!self.wildcard).then_some(params),
+            Some(part) => {
+                let subdomain = !part.is_empty() && parts.all(|p| !p.is_empty());
+                (self.wildcard && subdomain).then_some(params)
+            }
+        }
+    }
+}
+
+/// The host name of a `host` header value or request-target authority: without
+/// userinfo, port and trailing dot.
+pub(super) fn hostname(authority: &str) -> &str {
+    let host = match authority.rsplit_once('@') {
+        Some((_, host)) => host,
+        None => authority,
+    };
+    let host = match host.rfind(':') {
+        // "[::1]" has colons but no port
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    };
+    host.strip_suffix('.').unwrap_or(host)
+}
@@ -0,0 +1,1133 @@
+use crate::{percent, Method};
+use host::HostPattern;
+use tree::PatternTree;
+
+mod conflict;
+mod host;
+mod tree;
+
+pub use conflict::RouteConflict;
+use std::str::FromStr;
+use std::{array::from_fn, borrow::Cow, collections::HashMap, error::Error, fmt, io, mem};
+
+pub struct RouterBuilder<T> {
+    routes: Vec<RouteDef<T>>,
+    groups: Vec<(String, T)>,
+    hosts: Vec<(String, RouterBuilder<T>)>,
+    unknown_host: UnknownHost,
+    fallback_route: Option<T>,
+}
+
+/// A registered route; `depth` is the number of leading segments that come from
+/// [`RouterBuilder::nest`] prefixes.
+struct RouteDef<T> {
+    method: Method,
+    path: String,
+    depth: usize,
+    name: Option<String>,
+    route: T,
+}
+
+pub struct Router<T> {
+    methods: [MethodBucket<Scoped<T>>; 8],
+    extensions: HashMap<String, MethodBucket<Scoped<T>>>,
+    /// group fallbacks by prefix pattern, deepest first
+    groups: Vec<(Vec<RouteSegment>, T)>,
+    /// in registration order, see [`Router::routes`]
+    routes: Vec<RouteInfo>,
+    /// patterns of named routes, see [`Router::url_for`]
+    names: HashMap<String, Vec<RouteSegment>>,
+    /// virtual hosts in the order they are tried
+    hosts: Vec<(HostPattern, Router<T>)>,
+    unknown_host: UnknownHost,
+    /// `None` only for virtual hosts, which then use the router's fallback route
+    fallback_route: Option<T>,
+}
+
+/// A route of a [`Router`], see [`Router::routes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    pub method: Method,
+    /// The full path pattern, group prefixes included.
+    pub path: String,
+    pub name: Option<String>,
+    /// The host pattern for routes of virtual hosts.
+    pub host: Option<String>,
+}
+
+/// What [`Router::match_host_route`] does with requests for a host that no virtual host
+/// matches (or without a host).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownHost {
+    /// Use the routes registered on the router itself.
+    #[default]
+    DefaultHost,
+    /// No match, the server answers with 421 Misdirected Request.
+    Misdirected,
+    /// No match, the server answers with 404 Not Found.
+    NotFound,
+}
+
+#[derive(Debug, Clone)]
+struct Scoped<T> {
+    route: T,
+    depth: usize,
+    /// full path pattern, see [`Match::pattern`]
+    path: String,
+    name: Option<String>,
+}
+
+impl<T> Scoped<T> {
+    fn matched<'a, 'r>(&'a self, params: RouteParams<'a, 'r>, prefix: &'r str) -> Match<'a, 'r, T> {
+        Match {
+            route: &self.route,
+            params,
+            prefix,
+            pattern: Some(&self.path),
+            name: self.name.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Match<'a, 'r, T> {
+    pub route: &'a T,
+    pub params: RouteParams<'a, 'r>,
+    /// The part of the path matched by the prefix of the route's group (e.g. `/api/v1`),
+    /// empty for routes registered outside of groups.
+    pub prefix: &'r str,
+    /// The path pattern of the route (e.g. `/api/users/:id`), `None` for fallback routes.
+    pub pattern: Option<&'a str>,
+    pub name: Option<&'a str>,
+}
+
+impl<'a, 'r, T> Match<'a, 'r, T> {
+    pub fn new(route: &'a T, params: RouteParams<'a, 'r>) -> Self {
+        Match {
+            route,
+            params,
+            prefix: "",
+            pattern: None,
+            name: None,
+        }
+    }
+
+    pub fn no_params(route: &'a T) -> Self {
+        Match::new(route, RouteParams::new())
+    }
+}
+
+/// Route params, values are percent-decoded (raw value kept if it doesn't decode to UTF-8).
+#[derive(Debug, Clone, Default)]
+pub struct RouteParams<'a, 'r>(Vec<(&'a str, Cow<'r, str>)>);
+
+impl<'a, 'r> RouteParams<'a, 'r> {
+    #[inline]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// The value borrows from the params, which own decoded values. See
+    /// [`get_cow`](RouteParams::get_cow) to keep a value longer.
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find_map(|(k, v)| (*k == key).then_some(&**v))
+    }
+
+    /// Like [`get`](RouteParams::get), but values without escapes still borrow from the
+    /// request and only decoded values are copied.
+    #[inline]
+    pub fn get_cow(&self, key: &str) -> Option<Cow<'r, str>> {
+        self.0
+            .iter()
+            .find_map(|(k, v)| (*k == key).then(|| v.clone()))
+    }
+
+    #[inline]
+    pub fn insert<V: Into<Cow<'r, str>>>(&mut self, key: &'a str, val: V) {
+        if self.0.is_empty() {
+            self.0.reserve_exact(1)
+        }
+        self.0.push((key, val.into()));
+    }
+
+    /// The param parsed as `V`. The error converts into an `io::Error` that the server
+    /// answers with 400 when a route handler returns it, e.g.
+    /// `let id: u64 = ctx.params.parse("id")?;` (before sending a response).
+    pub fn parse<V: FromStr>(&self, key: &str) -> Result<V, ParamError> {
+        let value = self.get(key).ok_or_else(|| ParamError {
+            name: key.to_string(),
+            value: None,
+        })?;
+        value.parse().map_err(|_| ParamError {
+            name: key.to_string(),
+            value: Some(value.to_string()),
+        })
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &str)> + '_ {
+        self.0.iter().map(|(k, v)| (*k, &**v))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A route param that is missing or doesn't parse, see [`RouteParams::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamError {
+    name: String,
+    value: Option<String>,
+}
+
+impl ParamError {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether `err` (e.g. returned by a route handler) wraps a `ParamError`.
+    pub fn is_param_error(err: &io::Error) -> bool {
+        err.get_ref().is_some_and(|e| e.is::<ParamError>())
+    }
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "invalid route param {}: {value:?}", self.name),
+            None => write!(f, "missing route param {}", self.name),
+        }
+    }
+}
+
+impl Error for ParamError {}
+
+impl From<ParamError> for io::Error {
+    fn from(e: ParamError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+/// Why [`Router::url_for`] couldn't build a url.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+    UnknownRoute(String),
+    MissingParam(String),
+    /// a value that no segment of the route uses
+    UnexpectedParam(String),
+    /// an empty value, or one that doesn't satisfy the param's constraint
+    InvalidParam {
+        name: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::UnknownRoute(name) => write!(f, "no route named {name}"),
+            UrlError::MissingParam(name) => write!(f, "missing route param {name}"),
+            UrlError::UnexpectedParam(name) => write!(f, "unexpected route param {name}"),
+            UrlError::InvalidParam { name, value } => {
+                write!(f, "invalid route param {name}: {value:?}")
+            }
+        }
+    }
+}
+
+impl Error for UrlError {}
+
+impl From<UrlError> for io::Error {
+    fn from(e: UrlError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RoutePattern {
+    pattern: Vec<RouteSegment>,
+    last_prec: Precedence,
+    /// constraints plus suffixes of all params, breaks remaining ties
+    specificity: usize,
+}
+
+/// Per-method storage:
+/// - `literals`: exact, all-literal paths as full strings (normalized, no leading '/')
+/// - `patterns`: param/wildcard routes
+/// - `tree`: radix tree over `patterns`
+#[derive(Debug, Clone)]
+struct MethodBucket<T> {
+    literals: Vec<(String, T)>,
+    patterns: Vec<(RoutePattern, T)>,
+    tree: PatternTree,
+}
+
+impl<T> Default for MethodBucket<T> {
+    fn default() -> Self {
+        Self {
+            literals: Vec::new(),
+            patterns: Vec::new(),
+            tree: PatternTree::default(),
+        }
+    }
+}
+
+impl<T> MethodBucket<T> {
+    fn add_route(&mut self, path: &str, route: T) {
+        let (norm, entry) = parse_route(path);
+        if is_literal(&entry) {
+            self.literals.retain(|(k, _)| k != &norm);
+            self.literals.push((norm, route));
+        } else {
+            self.patterns.retain(|(k, _)| k != &entry);
+            self.patterns.push((entry, route));
+        }
+    }
+
+    /// Finalize once at build time: sort literals for binary search, build the tree.
+    fn finalize(&mut self) {
+        self.literals.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        self.tree = PatternTree::new(&self.patterns);
+    }
+
+    #[inline]
+    fn find_literal(&self, norm_path: &str) -> Option<&T> {
+        match self.literals.binary_search_by_key(&norm_path, |(k, _)| k) {
+            Ok(i) => Some(&self.literals[i].1),
+            Err(_) => None,
+        }
+    }
+
+    fn find<'a, 'r>(&'a self, uri: &'r str) -> Option<(&'a T, RouteParams<'a, 'r>)> {
+        let (i, params) = self.tree.find(&self.patterns, uri)?;
+        Some((&self.patterns[i].1, params))
+    }
+
+    /// Best pattern route by trying every pattern in turn.
+    fn find_linear<'a, 'r>(&'a self, uri: &'r str) -> Option<(&'a T, RouteParams<'a, 'r>)> {
+        let mut best_lml: i32 = -1;
+        let mut best_prec = Precedence::DoubleWildcard;
+        let mut best_specificity = 0;
+        let mut best_route: Option<&T> = None;
+        let mut best_params = RouteParams::new();
+
+        let mut route_params = RouteParams::new();
+        for (
+            RoutePattern {
+                pattern,
+                last_prec,
+                specificity,
+            },
+            route,
+        ) in &self.patterns
+        {
+            let mut uri_iter = uri.split('/');
+            let mut ok = true;
+            let mut lml = 0; // longest matching literal
+            let mut counting_prefix = true;
+
+            route_params.clear();
+            for seg_part in pattern.iter() {
+                let uri_part = uri_iter.next();
+
+                match seg_part {
+                    RouteSegment::DoubleWildcard => break, // matches until end
+                    RouteSegment::Wildcard => {
+                        if uri_part.is_none() {
+                            ok = false;
+                            break;
+                        }
+                        counting_prefix = false;
+                    }
+                    RouteSegment::Param(param) => {
+                        match uri_part.and_then(|v| param.match_value(v)) {
+                            Some(v) => route_params.insert(param.name.as_str(), v),
+                            None => {
+                                ok = false;
+                                break;
+                            }
+                        }
+                        counting_prefix = false;
+                    }
+                    RouteSegment::Literal(lit) => {
+                        if let Some(v) = uri_part {
+                            if lit == v {
+                                if counting_prefix {
+                                    lml += 1;
+                                }
+                            } else {
+                                ok = false;
+                                break;
+                            }
+                        } else {
+                            ok = false;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // if uri has extra parts, pattern must end with "**"
+            if ok && uri_iter.next().is_some() {
+                ok = *last_prec == Precedence::DoubleWildcard;
+            }
+
+            if !ok {
+                continue;
+            }
+
+            // compare against best (tie-break on precedence)
+            if (lml, *last_prec, *specificity) > (best_lml, best_prec, best_specificity) {
+                best_lml = lml;
+                best_prec = *last_prec;
+                best_specificity = *specificity;
+                best_route = Some(route);
+                mem::swap(&mut best_params, &mut route_params);
+            }
+        }
+
+        best_route.map(|route| (route, best_params))
+    }
+}
+
+impl<T> RouterBuilder<T> {
+    pub fn new(fallback_route: T) -> Self {
+        Self {
+            routes: Vec::new(),
+            groups: Vec::new(),
+            hosts: Vec::new(),
+            unknown_host: UnknownHost::default(),
+            fallback_route: Some(fallback_route),
+        }
+    }
+
+    /// A builder for a group of routes to be [`nest`](RouterBuilder::nest)ed into another
+    /// one. It has no fallback route unless one is set, and can't be built on its own.
+    pub fn group() -> Self {
+        Self {
+            routes: Vec::new(),
+            groups: Vec::new(),
+            hosts: Vec::new(),
+            unknown_host: UnknownHost::default(),
+            fallback_route: None,
+        }
+    }
+
+    pub fn add_route(&mut self, method: &Method, path: &str, route: T) {
+        self.routes.push(RouteDef {
+            method: method.clone(),
+            path: path.to_string(),
+            depth: 0,
+            name: None,
+            route,
+        });
+    }
+
+    /// Like [`add_route`](RouterBuilder::add_route), the route's url can be built from
+    /// `name` with [`Router::url_for`]. Names are global, also for nested groups.
+    pub fn add_named_route(&mut self, name: &str, method: &Method, path: &str, route: T) {
+        self.routes.push(RouteDef {
+            method: method.clone(),
+            path: path.to_string(),
+            depth: 0,
+            name: Some(name.to_string()),
+            route,
+        });
+    }
+
+    pub fn set_fallback_route(&mut self, route: T) {
+        self.fallback_route = Some(route);
+    }
+
+    /// Mounts the routes of `group` under `prefix` (literal and `:param` segments). The
+    /// group's fallback route, if any, handles unmatched requests under the prefix.
+    /// Matches report the prefix part of the path in [`Match::prefix`].
+    pub fn nest(&mut self, prefix: &str, group: RouterBuilder<T>) {
+        let prefix = prefix.trim_matches('/');
+        let depth = match prefix {
+            "" => 0,
+            _ => prefix.split('/').count(),
+        };
+        for def in group.routes {
+            self.routes.push(RouteDef {
+                path: join_paths(prefix, &def.path),
+                depth: depth + def.depth,
+                ..def
+            });
+        }
+        for (group_prefix, route) in group.groups {
+            self.groups.push((join_paths(prefix, &group_prefix), route));
+        }
+        if let Some(route) = group.fallback_route {
+            self.groups.push((join_paths(prefix, ""), route));
+        }
+    }
+
+    /// Serves requests for hosts matching `pattern` with the routes of `routes` (usually a
+    /// [`group`](RouterBuilder::group)), see [`Router::match_host_route`]. Patterns are
+    /// exact names (`example.com`), wildcard subdomains (`*.example.com`, one or more
+    /// labels) or have `:param` labels (`:tenant.example.com`) captured into the params.
+    /// Without a fallback route of its own, the host uses the router's fallback route.
+    /// A pattern registered again (ignoring case) replaces the earlier routes, see
+    /// [`RouteConflict::DuplicateHost`].
+    pub fn host(&mut self, pattern: &str, routes: RouterBuilder<T>) {
+        assert!(routes.hosts.is_empty(), "virtual hosts can't be nested");
+        self.hosts.push((pattern.to_string(), routes));
+    }
+
+    /// Applies `f` to every route, fallback routes included.
+    pub(crate) fn map_routes(self, f: &impl Fn(T) -> T) -> Self {
+        RouterBuilder {
+            routes: self
+                .routes
+                .into_iter()
+                .map(|def| RouteDef {
+                    route: f(def.route),
+                    ..def
+                })
+                .collect(),
+            groups: self
+                .groups
+                .into_iter()
+                .map(|(prefix, route)| (prefix, f(route)))
+                .collect(),
+            hosts: self
+                .hosts
+                .into_iter()
+                .map(|(pattern, routes)| (pattern, routes.map_routes(f)))
+                .collect(),
+            unknown_host: self.unknown_host,
+            fallback_route: self.fallback_route.map(f),
+        }
+    }
+
+    /// See [`UnknownHost`], the default is [`UnknownHost::DefaultHost`].
+    pub fn set_unknown_host(&mut self, policy: UnknownHost) {
+        self.unknown_host = policy;
+    }
+
+    /// A route with the same method and path as an earlier one (up to param names)
+    /// replaces it, and the name of the replaced route is dropped along with it. Names
+    /// are always strict though: panics for a name used by more than one of the routes
+    /// that are kept (across all hosts), and for a [`group`](RouterBuilder::group)
+    /// without a fallback route.
+    pub fn build(self) -> Router<T> {
+        assert!(
+            self.fallback_route.is_some(),
+            "router has no fallback route"
+        );
+        match self.build_table(false) {
+            Ok(router) => router,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Like [`build`](RouterBuilder::build), but fails on duplicate and shadowed routes,
+    /// duplicate route names and duplicate host patterns, see [`RouteConflict`].
+    pub fn try_build(self) -> Result<Router<T>, RouteConflict> {
+        assert!(
+            self.fallback_route.is_some(),
+            "router has no fallback route"
+        );
+        self.build_table(true)
+    }
+
+    fn build_table(self, strict: bool) -> Result<Router<T>, RouteConflict> {
+        let mut methods: [MethodBucket<Scoped<T>>; 8] = from_fn(|_| MethodBucket::default());
+        let mut extensions: HashMap<String, MethodBucket<Scoped<T>>> = HashMap::new();
+        let mut names = HashMap::new();
+        let mut table: Vec<(RouteInfo, RoutePattern)> = Vec::new();
+        for RouteDef {
+            method,
+            path,
+            depth,
+            name,
+            route,
+        } in self.routes
+        {
+            let (_, pattern) = parse_route(&path);
+            let full_path = format!("/{}", path.strip_prefix('/').unwrap_or(&path));
+            if let Some(i) = table
+                .iter()
+                .position(|(info, p)| info.method == method && *p == pattern)
+            {
+                let (existing, _) = table.remove(i);
+                if strict {
+                    return Err(RouteConflict::Duplicate {
+                        method,
+                        route: full_path,
+                        existing: existing.path,
+                    });
+                }
+            }
+            let scoped = Scoped {
+                route,
+                depth,
+                path: full_path.clone(),
+                name: name.clone(),
+            };
+            let info = RouteInfo {
+                method: method.clone(),
+                path: full_path,
+                name,
+                host: None,
+            };
+            table.push((info, pattern));
+
+            let bucket = match method {
+                Method::Custom(x) => extensions.entry(x).or_default(),
+                _ => &mut methods[method.index()],
+            };
+            bucket.add_route(&path, scoped);
+        }
+        if strict {
+            shadowed_route(&table)?;
+        }
+        // after replacements, so a replaced route's name goes with it
+        for (info, pattern) in &table {
+            if let Some(name) = &info.name {
+                if names
+                    .insert(name.clone(), pattern.pattern.clone())
+                    .is_some()
+                {
+                    return Err(RouteConflict::DuplicateName(name.clone()));
+                }
+            }
+        }
+        for bucket in &mut methods {
+            bucket.finalize();
+        }
+        for bucket in extensions.values_mut() {
+            bucket.finalize();
+        }
+
+        let mut groups: Vec<(Vec<RouteSegment>, T)> = Vec::new();
+        for (prefix, route) in self.groups {
+            let pattern = match prefix.trim_start_matches('/') {
+                "" => Vec::new(),
+                p => p.split('/').map(parse_route_segment).collect(),
+            };
+            groups.retain(|(p, _)| *p != pattern);
+            groups.push((pattern, route));
+        }
+        groups.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+
+        let mut host_routes: Vec<(String, RouterBuilder<T>)> = Vec::new();
+        for (pattern, routes) in self.hosts {
+            if let Some(i) = host_routes
+                .iter()
+                .position(|(p, _)| p.eq_ignore_ascii_case(&pattern))
+            {
+                if strict {
+                    return Err(RouteConflict::DuplicateHost(pattern));
+                }
+                host_routes.remove(i);
+            }
+            host_routes.push((pattern, routes));
+        }
+
+        let mut hosts = Vec::new();
+        for (pattern, routes) in host_routes {
+            let mut host = routes.build_table(strict)?;
+            for (name, pattern) in mem::take(&mut host.names) {
+                if names.insert(name.clone(), pattern).is_some() {
+                    return Err(RouteConflict::DuplicateName(name));
+                }
+            }
+            for info in &mut host.routes {
+                info.host = Some(pattern.clone());
+            }
+            hosts.push((HostPattern::parse(&pattern), host));
+        }
+        // stable, so equally ranked patterns keep registration order
+        hosts.sort_by_key(|(p, _)| std::cmp::Reverse(p.rank()));
+
+        Ok(Router {
+            methods,
+            extensions,
+            groups,
+            routes: table.into_iter().map(|(info, _)| info).collect(),
+            names,
+            hosts,
+            unknown_host: self.unknown_host,
+            fallback_route: self.fallback_route,
+        })
+    }
+}
+
+/// Literal routes always win over pattern routes, so only pattern routes of the same
+/// method can shadow each other.
+fn shadowed_route(table: &[(RouteInfo, RoutePattern)]) -> Result<(), RouteConflict> {
+    let mut methods: Vec<&Method> = Vec::new();
+    for (info, _) in table {
+        if !methods.contains(&&info.method) {
+            methods.push(&info.method);
+        }
+    }
+    for method in methods {
+        let routes: Vec<_> = table
+            .iter()
+            .filter(|(info, p)| info.method == *method && !is_literal(p))
+            .collect();
+        let patterns: Vec<_> = routes.iter().map(|(_, p)| p).collect();
+        if let Some((a, b)) = conflict::find_shadowed(&patterns) {
+            return Err(RouteConflict::Shadowed {
+                method: method.clone(),
+                route: routes[a].0.path.clone(),
+                by: routes[b].0.path.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+impl<T> Router<T> {
+    /// The routes in registration order, routes of virtual hosts last. Routes replaced
+    /// by a later one with the same method and path are left out.
+    pub fn routes(&self) -> impl Iterator<Item = &RouteInfo> {
+        let hosts = self.hosts.iter().flat_map(|(_, host)| &host.routes);
+        self.routes.iter().chain(hosts)
+    }
+
+    /// Matches on the routes registered on the router itself, virtual hosts aside.
+    pub fn match_route<'a, 'r>(&'a self, method: &Method, path: &'r str) -> Match<'a, 'r, T> {
+        self.match_with(method, path, self.fallback(), MethodBucket::find)
+    }
+
+    /// Matches on the routes of the first virtual host matching `host` (a `host` header
+    /// value or request-target authority, the port is ignored), host params come first
+    /// in [`Match::params`]. Other hosts are handled by the [`UnknownHost`] policy,
+    /// `None` if it rejects them.
+    pub fn match_host_route<'a, 'r>(
+        &'a self,
+        host: Option<&'r str>,
+        method: &Method,
+        path: &'r str,
+    ) -> Option<Match<'a, 'r, T>> {
+        if let Some(hostname) = host.map(host::hostname) {
+            for (pattern, table) in &self.hosts {
+                let Some(mut params) = pattern.matches(hostname) else {
+                    continue;
+                };
+                let fallback = table.fallback_route.as_ref().unwrap_or(self.fallback());
+                let m = table.match_with(method, path, fallback, MethodBucket::find);
+                params.0.extend(m.params.0);
+                return Some(Match { params, ..m });
+            }
+        }
+        match self.unknown_host {
+            UnknownHost::DefaultHost => Some(self.match_route(method, path)),
+            _ => None,
+        }
+    }
+
+    pub fn unknown_host(&self) -> UnknownHost {
+        self.unknown_host
+    }
+
+    fn fallback(&self) -> &T {
+        self.fallback_route
+            .as_ref()
+            .expect("router has no fallback route")
+    }
+
+    /// Path of the route registered as `name`, with its `:param` segments filled from
+    /// `params` (percent-encoded). Values for `*` and `**` segments are given in order
+    /// under the keys `"*"` and `"**"`, a `**` value may contain slashes. Every segment
+    /// needs exactly one value and every value must be used.
+    ///
+    /// ```
+    /// # use khttp::{Method::Get, RouterBuilder};
+    /// let mut builder = RouterBuilder::new(0);
+    /// builder.add_named_route("user_profile", &Get, "/users/:id<u64>/profile", 1);
+    /// let router = builder.build();
+    /// let url = router.url_for("user_profile", &[("id", "42")]).unwrap();
+    /// assert_eq!(url, "/users/42/profile");
+    /// ```
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        let pattern = self
+            .names
+            .get(name)
+            .ok_or_else(|| UrlError::UnknownRoute(name.to_string()))?;
+
+        let mut used = vec![false; params.len()];
+        let mut take = |key: &str| {
+            let i = (0..params.len()).find(|&i| !used[i] && params[i].0 == key);
+            let i = i.ok_or_else(|| UrlError::MissingParam(key.to_string()))?;
+            used[i] = true;
+            Ok(params[i].1)
+        };
+
+        let mut url = String::new();
+        for segment in pattern {
+            url.push('/');
+            match segment {
+                RouteSegment::Literal(lit) => url.push_str(lit),
+                RouteSegment::Param(param) => {
+                    let value = take(&param.name)?;
+                    // an empty segment never matches the param
+                    if value.is_empty()
+                        || param.constraint.as_ref().is_some_and(|c| !c.matches(value))
+                    {
+                        return Err(UrlError::InvalidParam {
+                            name: param.name.clone(),
+                            value: value.to_string(),
+                        });
+                    }
+                    url.push_str(&percent::encode_path_segment(value));
+                    url.push_str(&param.suffix);
+                }
+                RouteSegment::Wildcard => match take("*")? {
+                    "" => {
+                        return Err(UrlError::InvalidParam {
+                            name: "*".to_string(),
+                            value: String::new(),
+                        })
+                    }
+                    value => url.push_str(&percent::encode_path_segment(value)),
+                },
+                RouteSegment::DoubleWildcard => match take("**")?.trim_start_matches('/') {
+                    "" => _ = url.pop(), // `/static/**` matches `/static`
+                    value => {
+                        let parts = value.split('/').map(percent::encode_path_segment);
+                        url.push_str(&parts.collect::<Vec<_>>().join("/"));
+                    }
+                },
+            }
+        }
+        if let Some(i) = used.iter().position(|used| !used) {
+            return Err(UrlError::UnexpectedParam(params[i].0.to_string()));
+        }
+        if url.is_empty() {
+            url.push('/');
+        }
+        Ok(url)
+    }
+
+    /// Reference matcher scanning every pattern route, for conformance tests and benches.
+    #[doc(hidden)]
+    pub fn match_route_linear<'a, 'r>(
+        &'a self,
+        method: &Method,
+        path: &'r str,
+    ) -> Match<'a, 'r, T> {
+        self.match_with(method, path, self.fallback(), MethodBucket::find_linear)
+    }
+
+    #[inline]
+    fn match_with<'a, 'r>(
+        &'a self,
+        method: &Method,
+        path: &'r str,
+        fallback: &'a T,
+        find_pattern: impl FnOnce(
+            &'a MethodBucket<Scoped<T>>,
+            &'r str,
+        ) -> Option<(&'a Scoped<T>, RouteParams<'a, 'r>)>,
+    ) -> Match<'a, 'r, T> {
+        let uri = path.strip_prefix('/').unwrap_or(path); // normalize: strip leading slash
+        let prefix_of = |depth: usize| match depth {
+            0 => "",
+            _ => {
+                let len: usize = uri.split('/').take(depth).map(|s| s.len() + 1).sum();
+                &path[..path.len() - uri.len() + len - 1]
+            }
+        };
+
+        let Some(bucket) = (match method {
+            Method::Custom(x) => self.extensions.get(x),
+            _ => Some(&self.methods[method.index()]),
+        }) else {
+            return self.match_fallback(uri, fallback, prefix_of);
+        };
+
+        // fast path: exact literal route
+        if let Some(scoped) = bucket.find_literal(uri) {
+            return scoped.matched(RouteParams::new(), prefix_of(scoped.depth));
+        }
+
+        match find_pattern(bucket, uri) {
+            Some((scoped, params)) => scoped.matched(params, prefix_of(scoped.depth)),
+            None => self.match_fallback(uri, fallback, prefix_of),
+        }
+    }
+
+    /// Fallback route of the deepest group whose prefix matches, else `fallback`.
+    fn match_fallback<'a, 'r>(
+        &'a self,
+        uri: &'r str,
+        fallback: &'a T,
+        prefix_of: impl Fn(usize) -> &'r str,
+    ) -> Match<'a, 'r, T> {
+        let mut params = RouteParams::new();
+        for (pattern, route) in &self.groups {
+            let mut parts = uri.split('/');
+            params.clear();
+            let matched = pattern.iter().all(|seg| match (seg, parts.next()) {
+                (RouteSegment::Literal(lit), Some(v)) => lit == v,
+                (RouteSegment::Param(param), Some(v)) => match param.match_value(v) {
+                    Some(v) => {
+                        params.insert(param.name.as_str(), v);
+                        true
+                    }
+                    None => false,
+                },
+                (_, Some(_)) => true,
+                (_, None) => false,
+            });
+            if matched {
+                return Match {
+                    prefix: prefix_of(pattern.len()),
+                    ..Match::new(route, params)
+                };
+            }
+        }
+        Match::no_params(fallback)
+    }
+}
+
+/// Joins a group prefix (without slashes at either end) and a route path.
+fn join_paths(prefix: &str, path: &str) -> String {
+    match (prefix, path.trim_start_matches('/')) {
+        ("", path) => format!("/{path}"),
+        (prefix, "") => format!("/{prefix}"),
+        (prefix, path) => format!("/{prefix}/{path}"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RouteSegment {
+    Literal(String),
+    Param(Param),
+    Wildcard,
+    DoubleWildcard,
+}
+
+/// `:name`, optionally with a constraint and a literal suffix: `:id<u64>`,
+/// `:slug<[a-z0-9-]+>`, `:file.json`, `:id<u32>.json`.
+#[derive(Debug, Clone, Eq)]
+struct Param {
+    name: String,
+    constraint: Option<Constraint>,
+    suffix: String,
+}
+
+/// Params with a different name but the same constraint and suffix are the same route.
+impl PartialEq for Param {
+    fn eq(&self, other: &Self) -> bool {
+        self.constraint == other.constraint && self.suffix == other.suffix
+    }
+}
+
+impl Param {
+    fn parse(s: &str) -> Param {
+        let name_end = s
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(s.len());
+        let (name, mut rest) = s.split_at(name_end);
+        assert!(!name.is_empty(), "route param without a name: :{s}");
+
+        let mut constraint = None;
+        if let Some(r) = rest.strip_prefix('<') {
+            // a class may itself contain '>'
+            let from = match r.starts_with('[') {
+                true => r.find(']').unwrap_or(0),
+                false => 0,
+            };
+            let end = r[from..]
+                .find('>')
+                .map(|i| from + i)
+                .unwrap_or_else(|| panic!("unterminated route param constraint: :{s}"));
+            constraint = Some(Constraint::parse(&r[..end]));
+            rest = &r[end + 1..];
+        }
+        Param {
+            name: name.to_string(),
+            constraint,
+            suffix: rest.to_string(),
+        }
+    }
+
+    /// 0 for a plain `:name`, +1 each for a constraint and a suffix.
+    fn specificity(&self) -> usize {
+        self.constraint.is_some() as usize + !self.suffix.is_empty() as usize
+    }
+
+    /// The percent-decoded value if the uri segment matches.
+    fn match_value<'r>(&self, segment: &'r str) -> Option<Cow<'r, str>> {
+        let raw = segment.strip_suffix(self.suffix.as_str())?;
+        if raw.is_empty() && !self.suffix.is_empty() {
+            return None;
+        }
+        let value = percent::decode(raw).unwrap_or(Cow::Borrowed(raw));
+        match &self.constraint {
+            Some(c) if !c.matches(&value) => None,
+            _ => Some(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Constraint {
+    /// unsigned integer of this many bits
+    Uint(u32),
+    /// signed integer of this many bits
+    Int(u32),
+    /// ASCII character class with a repetition range, e.g. `[a-z-]+` or `[0-9]{2,4}`
+    Class { set: u128, min: usize, max: usize },
+}
+
+impl Constraint {
+    fn parse(s: &str) -> Constraint {
+        match s {
+            "u8" => return Constraint::Uint(8),
+            "u16" => return Constraint::Uint(16),
+            "u32" => return Constraint::Uint(32),
+            "u64" => return Constraint::Uint(64),
+            "usize" => return Constraint::Uint(usize::BITS),
+            "i8" => return Constraint::Int(8),
+            "i16" => return Constraint::Int(16),
+            "i32" => return Constraint::Int(32),
+            "i64" => return Constraint::Int(64),
+            "isize" => return Constraint::Int(isize::BITS),
+            _ => {}
+        }
+        let invalid = || -> ! { panic!("invalid route param constraint: <{s}>") };
+        let Some((class, quantifier)) = s.strip_prefix('[').and_then(|s| s.split_once(']')) else {
+            invalid()
+        };
+
+        let mut set = 0u128;
+        let bytes = class.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let (lo, hi, len) = match bytes.get(i + 1..i + 3) {
+                Some(&[b'-', hi]) => (bytes[i], hi, 3),
+                _ => (bytes[i], bytes[i], 1),
+            };
+            if lo > hi || hi >= 0x80 {
+                invalid();
+            }
+            for b in lo..=hi {
+                set |= 1 << b;
+            }
+            i += len;
+        }
+
+        let (min, max) = match quantifier {
+            "" => (1, 1),
+            "+" => (1, usize::MAX),
+            "*" => (0, usize::MAX),
+            "?" => (0, 1),
+            q => {
+                let Some(q) = q.strip_prefix('{').and_then(|q| q.strip_suffix('}')) else {
+                    invalid()
+                };
+                let parse = |n: &str| n.parse::<usize>().unwrap_or_else(|_| invalid());
+                match q.split_once(',') {
+                    Some((min, "")) => (parse(min), usize::MAX),
+                    Some((min, max)) => (parse(min), parse(max)),
+                    None => (parse(q), parse(q)),
+                }
+            }
+        };
+        Constraint::Class { set, min, max }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        let digits = |v: &str| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit());
+        match *self {
+            Constraint::Uint(bits) => {
+                digits(value)
+                    && value
+                        .parse::<u64>()
+                        .is_ok_and(|n| n >> (bits - 1) >> 1 == 0)
+            }
+            Constraint::Int(bits) => {
+                digits(value.strip_prefix('-').unwrap_or(value))
+                    && value.parse::<i64>().is_ok_and(|n| {
+                        let bound = 1i128 << (bits - 1);
+                        (-bound..bound).contains(&(n as i128))
+                    })
+            }
+            Constraint::Class { set, min, max } => {
+                (min..=max).contains(&value.len())
+                    && value.bytes().all(|b| b < 0x80 && set & (1 << b) != 0)
+            }
+        }
+    }
+}
+
+/// Returns ("normalized path without leading slash", "route pattern")
+fn parse_route(mut route_str: &str) -> (String, RoutePattern) {
+    if route_str.starts_with('/') {
+        route_str = &route_str[1..];
+    }
+    let norm = route_str.to_string(); // "" for "/"
+    let pattern: Vec<RouteSegment> = route_str.split('/').map(parse_route_segment).collect();
+    let last_prec = precedence_of(pattern.last());
+    let specificity = pattern
+        .iter()
+        .map(|seg| match seg {
+            RouteSegment::Param(p) => p.specificity(),
+            _ => 0,
+        })
+        .sum();
+    (
+        norm,
+        RoutePattern {
+            pattern,
+            last_prec,
+            specificity,
+        },
+    )
+}
+
+fn is_literal(pattern: &RoutePattern) -> bool {
+    pattern
+        .pattern
+        .iter()
+        .all(|seg| matches!(seg, RouteSegment::Literal(_)))
+}
+
+fn parse_route_segment(s: &str) -> RouteSegment {
+    match s {
+        "*" => RouteSegment::Wildcard,
+        "**" => RouteSegment::DoubleWildcard,
+        _ if s.starts_with(':') => RouteSegment::Param(Param::parse(&s[1..])),
+        x => RouteSegment::Literal(x.to_string()),
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum Precedence {
+    DoubleWildcard = 0,
+    Wildcard = 1,
+    Param = 2,
+    ConstrainedParam = 3,
+    Literal = 4,
+}
+
+fn precedence_of(last: Option<&RouteSegment>) -> Precedence {
+    match last {
+        Some(RouteSegment::Literal(_)) => Precedence::Literal,
+        Some(RouteSegment::Param(p)) if p.specificity() > 0 => Precedence::ConstrainedParam,
+        Some(RouteSegment::Param(_)) => Precedence::Param,
+        Some(RouteSegment::Wildcard) => Precedence::Wildcard,
+        Some(RouteSegment::DoubleWildcard) => Precedence::DoubleWildcard,
+        None => Precedence::DoubleWildcard,
+    }
+}
@@ -0,0 +1,211 @@
+//! Radix tree over the pattern routes of a method bucket.
+//!
+//! Edges are path segments: literal edges are compressed (a chain of literal segments
+//! without branches is a single edge like `api/v1/users`), params with the same
+//! constraint and suffix share an edge, and `*` / `**` have their own slots. A lookup
+//! walks every branch consistent with the path and keeps the best route by [`Rank`],
+//! which orders routes exactly like the linear matcher: longest literal prefix, then
+//! `Precedence` of the last segment, then specificity, then registration order. Each
+//! node knows the best rank below it, so branches that can't win are skipped.
+
+use super::{Param, RoutePattern, RouteSegment};
+use super::{Precedence, RouteParams};
+use std::cmp::Reverse;
+
+/// (literal prefix length, last segment precedence, specificity, earlier registration)
+pub(super) type Rank = (usize, Precedence, usize, Reverse<usize>);
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct PatternTree {
+    root: Node,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// pattern route ending at this node
+    route: Option<(usize, Rank)>,
+    /// routes with `**` at this depth, `true` if the pattern doesn't end in `**` (it then
+    /// only matches up to one more segment, like in the linear matcher)
+    double_wildcards: Vec<(usize, Rank, bool)>,
+    /// sorted by the first segment of the label
+    literals: Vec<LiteralEdge>,
+    params: Vec<(Param, Node)>,
+    wildcard: Option<Box<Node>>,
+    best: Option<Rank>,
+}
+
+#[derive(Debug, Clone)]
+struct LiteralEdge {
+    first: String,
+    label: String,
+    node: Node,
+}
+
+impl PatternTree {
+    pub(super) fn new<T>(patterns: &[(RoutePattern, T)]) -> Self {
+        let mut root = Node::default();
+        for (i, (pattern, _)) in patterns.iter().enumerate() {
+            root.insert(&pattern.pattern, i, rank_of(pattern, i));
+        }
+        root.compress();
+        PatternTree { root }
+    }
+
+    /// Index of the best matching pattern and its params.
+    pub(super) fn find<'a, 'r, T>(
+        &self,
+        patterns: &'a [(RoutePattern, T)],
+        uri: &'r str,
+    ) -> Option<(usize, RouteParams<'a, 'r>)> {
+        let mut best = None;
+        self.root.search(Some(uri), &mut best);
+        let (_, route) = best?;
+
+        // only the winner's params are collected, the search itself doesn't allocate
+        let mut params = RouteParams::new();
+        let segments = patterns[route].0.pattern.iter();
+        for (segment, part) in segments.zip(uri.split('/')) {
+            match segment {
+                RouteSegment::Param(p) => params.insert(p.name.as_str(), p.match_value(part)?),
+                RouteSegment::DoubleWildcard => break,
+                _ => {}
+            }
+        }
+        Some((route, params))
+    }
+}
+
+pub(super) fn rank_of(pattern: &RoutePattern, i: usize) -> Rank {
+    let lml = pattern
+        .pattern
+        .iter()
+        .take_while(|seg| matches!(seg, RouteSegment::Literal(_)))
+        .count();
+    (lml, pattern.last_prec, pattern.specificity, Reverse(i))
+}
+
+impl Node {
+    fn insert(&mut self, segments: &[RouteSegment], route: usize, rank: Rank) {
+        self.best = self.best.max(Some(rank));
+        let Some((segment, rest)) = segments.split_first() else {
+            self.route = Some((route, rank));
+            return;
+        };
+        let child = match segment {
+            RouteSegment::Literal(lit) => {
+                let i = match self.literals.binary_search_by(|e| e.first.cmp(lit)) {
+                    Ok(i) => i,
+                    Err(i) => {
+                        let edge = LiteralEdge {
+                            first: lit.clone(),
+                            label: lit.clone(),
+                            node: Node::default(),
+                        };
+                        self.literals.insert(i, edge);
+                        i
+                    }
+                };
+                &mut self.literals[i].node
+            }
+            RouteSegment::Param(param) => match self.params.iter().position(|(p, _)| p == param) {
+                Some(i) => &mut self.params[i].1,
+                None => {
+                    self.params.push((param.clone(), Node::default()));
+                    &mut self.params.last_mut().unwrap().1
+                }
+            },
+            RouteSegment::Wildcard => self.wildcard.get_or_insert_with(Default::default),
+            RouteSegment::DoubleWildcard => {
+                // (rank.1 is the precedence of the pattern's last segment)
+                let limited = rank.1 != Precedence::DoubleWildcard;
+                self.double_wildcards.push((route, rank, limited));
+                return;
+            }
+        };
+        child.insert(rest, route, rank);
+    }
+
+    /// Merges chains of literal edges without branches into single edges.
+    fn compress(&mut self) {
+        for edge in &mut self.literals {
+            while edge.node.is_literal_link() {
+                let next = edge.node.literals.pop().unwrap();
+                edge.label = format!("{}/{}", edge.label, next.label);
+                edge.node = next.node;
+            }
+            edge.node.compress();
+        }
+        for (_, node) in &mut self.params {
+            node.compress();
+        }
+        if let Some(node) = &mut self.wildcard {
+            node.compress();
+        }
+    }
+
+    fn is_literal_link(&self) -> bool {
+        self.route.is_none()
+            && self.double_wildcards.is_empty()
+            && self.params.is_empty()
+            && self.wildcard.is_none()
+            && self.literals.len() == 1
+    }
+
+    /// `rest` is the unmatched part of the path, `None` once every segment is consumed.
+    fn search(&self, rest: Option<&str>, best: &mut Option<(Rank, usize)>) {
+        if self.best <= best.map(|(rank, _)| rank) {
+            return; // nothing below can beat the current best
+        }
+
+        if let (None, Some((route, rank))) = (rest, self.route) {
+            consider(route, rank, best);
+        }
+        for &(route, rank, limited) in &self.double_wildcards {
+            if !limited || rest.is_none_or(|r| !r.contains('/')) {
+                consider(route, rank, best);
+            }
+        }
+
+        let Some(rest) = rest else {
+            return;
+        };
+        let (segment, next) = match rest.split_once('/') {
+            Some((segment, next)) => (segment, Some(next)),
+            None => (rest, None),
+        };
+
+        if let Ok(i) = self
+            .literals
+            .binary_search_by(|e| e.first.as_str().cmp(segment))
+        {
+            let edge = &self.literals[i];
+            if let Some(next) = strip_label(rest, &edge.label) {
+                edge.node.search(next, best);
+            }
+        }
+        for (param, node) in &self.params {
+            if param.match_value(segment).is_some() {
+                node.search(next, best);
+            }
+        }
+        if let Some(node) = &self.wildcard {
+            node.search(next, best);
+        }
+    }
+}
+
+fn consider(route: usize, rank: Rank, best: &mut Option<(Rank, usize)>) {
+    if best.is_none_or(|(b, _)| b < rank) {
+        *best = Some((rank, route));
+    }
+}
+
+/// Matches a (possibly compressed) literal label against the start of `rest`.
+fn strip_label<'r>(rest: &'r str, label: &str) -> Option<Option<&'r str>> {
+    let after = rest.strip_prefix(label)?;
+    match after.strip_prefix('/') {
+        _ if after.is_empty() => Some(None),
+        Some(next) => Some(Some(next)),
+        None => None,
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io;
+use std::net::TcpStream;
+
+/// Copies `len` bytes of `file`, starting at `offset`, to the socket.
+/// Uses `sendfile(2)` on Linux (with `--features sendfile`), the file cursor is left untouched.
+#[cfg(all(feature = "sendfile", target_os = "linux"))]
+pub(crate) fn copy_file_to_stream(
+    stream: &TcpStream,
+    file: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // linux transfers at most 0x7ffff000 bytes per call
+    const MAX_CHUNK: u64 = 0x7fff_f000;
+
+    let mut offset = offset as libc::off_t;
+    let mut remaining = len;
+    while remaining > 0 {
+        let count = remaining.min(MAX_CHUNK) as usize;
+        // SAFETY: both fds stay open while `stream` and `file` are borrowed, and `offset`
+        // is a live local that sendfile only reads and advances
+        let n = unsafe { libc::sendfile(stream.as_raw_fd(), file.as_raw_fd(), &mut offset, count) };
+        match n {
+            -1 => match io::Error::last_os_error() {
+                e if e.kind() == io::ErrorKind::Interrupted => continue,
+                e => return Err(e),
+            },
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file truncated during sendfile",
+                ))
+            }
+            n => remaining -= n as u64,
+        }
+    }
+    Ok(())
+}
+
+/// Fallback: positional reads copied through userspace, the file cursor is left untouched.
+#[cfg(all(unix, not(all(feature = "sendfile", target_os = "linux"))))]
+pub(crate) fn copy_file_to_stream(
+    mut stream: &TcpStream,
+    file: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::FileExt;
+
+    let mut buf = vec![0u8; len.min(64 * 1024) as usize];
+    let mut offset = offset;
+    let mut remaining = len;
+    while remaining > 0 {
+        let count = remaining.min(buf.len() as u64) as usize;
+        let n = match file.read_at(&mut buf[..count], offset) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file truncated during copy",
+                ))
+            }
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        stream.write_all(&buf[..n])?;
+        offset += n as u64;
+        remaining -= n as u64;
+    }
+    Ok(())
+}
+
+/// Portable fallback: seek and copy through userspace, moves the file cursor.
+#[cfg(not(unix))]
+pub(crate) fn copy_file_to_stream(
+    mut stream: &TcpStream,
+    mut file: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(offset))?;
+    let copied = io::copy(&mut file.take(len), &mut stream)?;
+    if copied < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "file truncated during copy",
+        ));
+    }
+    Ok(())
+}
@@ -1,15 +1,20 @@
 use super::{
-    ConnectionSetupAction, ConnectionSetupHookFn, HandlerConfig, PreRoutingAction,
+    ConnectionInfo, ConnectionSetupAction, ConnectionSetupHookFn, HandlerConfig, PreRoutingAction,
     PreRoutingHookFn, RequestContext, ResponseHandle, RouteFn, Server,
 };
-use crate::parser::Request;
-use crate::router::RouterBuilder;
+use crate::middleware::{self, Middleware};
+use crate::parser::{Request, RequestLimits};
+use crate::router::{RouteConflict, Router, RouterBuilder, UnknownHost};
 use crate::server::ConnectionTeardownHookFn;
-use crate::{Headers, Method, Status};
+use crate::{Extensions, Headers, Method, PathNormalization, Status};
 use std::io::{self};
+use std::mem;
 use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::sync::Arc;
 
+#[cfg(feature = "compression")]
+use crate::CompressionConfig;
+
 const DEFAULT_MAX_REQUEST_HEAD: usize = 4096; // should be plenty, this is what nginx uses by default
 const DEFAULT_EPOLL_QUEUE_MAXEVENTS: usize = 512;
 
@@ -18,10 +23,17 @@ pub struct ServerBuilder {
     router: RouterBuilder<Box<RouteFn>>,
     connection_setup_hook: Option<Box<ConnectionSetupHookFn>>,
     connection_teardown_hook: Option<Box<ConnectionTeardownHookFn>>,
-    pre_routing_hook: Option<Box<PreRoutingHookFn>>,
+    pre_routing_hooks: Vec<Box<PreRoutingHookFn>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    state: Extensions,
     thread_count: usize,
     max_request_head_size: usize,
     epoll_queue_max_events: usize,
+    strict_parsing: bool,
+    request_limits: RequestLimits,
+    path_normalization: Option<PathNormalization>,
+    #[cfg(feature = "compression")]
+    compression: Option<CompressionConfig>,
 }
 
 impl ServerBuilder {
@@ -42,23 +54,49 @@ impl ServerBuilder {
             })),
             connection_setup_hook: None,
             connection_teardown_hook: None,
-            pre_routing_hook: None,
+            pre_routing_hooks: Vec::new(),
+            middleware: Vec::new(),
+            state: Extensions::new(),
             thread_count: get_default_thread_count(),
             max_request_head_size: DEFAULT_MAX_REQUEST_HEAD,
             epoll_queue_max_events: DEFAULT_EPOLL_QUEUE_MAXEVENTS,
+            strict_parsing: true,
+            request_limits: RequestLimits::default(),
+            path_normalization: Some(PathNormalization::default()),
+            #[cfg(feature = "compression")]
+            compression: None,
         })
     }
 
-    pub fn build(self) -> Server {
+    pub fn build(mut self) -> Server {
+        let router = mem::replace(&mut self.router, RouterBuilder::group()).build();
+        self.finish(router)
+    }
+
+    /// Like [`build`](Self::build), but fails on conflicting routes, see
+    /// [`RouterBuilder::try_build`].
+    pub fn try_build(mut self) -> Result<Server, RouteConflict> {
+        let router = mem::replace(&mut self.router, RouterBuilder::group()).try_build()?;
+        Ok(self.finish(router))
+    }
+
+    fn finish(self, router: Router<Box<RouteFn>>) -> Server {
         Server {
             bind_addrs: self.bind_addrs,
             thread_count: self.thread_count,
             connection_setup_hook: self.connection_setup_hook,
             handler_config: Arc::new(HandlerConfig {
-                router: self.router.build(),
-                pre_routing_hook: self.pre_routing_hook,
+                router,
+                state: self.state,
+                middleware: self.middleware,
+                pre_routing_hooks: self.pre_routing_hooks,
                 connection_teardown_hook: self.connection_teardown_hook,
                 max_request_head: self.max_request_head_size,
+                strict_parsing: self.strict_parsing,
+                request_limits: self.request_limits,
+                path_normalization: self.path_normalization,
+                #[cfg(feature = "compression")]
+                compression: self.compression,
             }),
             epoll_queue_max_events: self.epoll_queue_max_events,
         }
@@ -72,6 +110,38 @@ impl ServerBuilder {
         self
     }
 
+    /// A route whose url can be built from `name`, see [`RequestContext::url_for`].
+    pub fn named_route<F>(
+        &mut self,
+        name: &str,
+        method: Method,
+        path: &str,
+        route_fn: F,
+    ) -> &mut Self
+    where
+        F: Fn(RequestContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.router
+            .add_named_route(name, &method, path, Box::new(route_fn));
+        self
+    }
+
+    /// A route behind its own middleware `stack`, see [`Middleware`].
+    pub fn route_with<F>(
+        &mut self,
+        method: Method,
+        path: &str,
+        stack: &[Arc<dyn Middleware>],
+        route_fn: F,
+    ) -> &mut Self
+    where
+        F: Fn(RequestContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
+    {
+        let route = middleware::layer(stack.to_vec(), Box::new(route_fn));
+        self.router.add_route(&method, path, route);
+        self
+    }
+
     pub fn thread_count(&mut self, thread_count: usize) -> &mut Self {
         self.thread_count = thread_count;
         self
@@ -87,17 +157,41 @@ impl ServerBuilder {
 
     pub fn connection_teardown_hook<F>(&mut self, f: F) -> &mut Self
     where
-        F: Fn(TcpStream, io::Result<()>) + Send + Sync + 'static,
+        F: Fn(TcpStream, Extensions, io::Result<()>) + Send + Sync + 'static,
     {
         self.connection_teardown_hook = Some(Box::new(f));
         self
     }
 
+    /// Runs after a request head is parsed, before routing. Hooks run in registration
+    /// order until one returns [`PreRoutingAction::Drop`], values they insert into the
+    /// [`Extensions`] end up in [`RequestContext::extensions`](crate::RequestContext::extensions).
     pub fn pre_routing_hook<F>(&mut self, f: F) -> &mut Self
     where
-        F: Fn(&mut Request<'_>, &mut ResponseHandle) -> PreRoutingAction + Send + Sync + 'static,
+        F: Fn(
+                &mut Request<'_>,
+                &ConnectionInfo,
+                &mut Extensions,
+                &mut ResponseHandle,
+            ) -> PreRoutingAction
+            + Send
+            + Sync
+            + 'static,
     {
-        self.pre_routing_hook = Some(Box::new(f));
+        self.pre_routing_hooks.push(Box::new(f));
+        self
+    }
+
+    /// Application state shared by all requests (a database pool, config, ...), one value
+    /// per type. Handlers and middleware get it with [`RequestContext::state`].
+    pub fn state<T: Send + Sync + 'static>(&mut self, value: T) -> &mut Self {
+        self.state.insert(value);
+        self
+    }
+
+    /// Middleware around every route, fallback routes included, see [`Middleware`].
+    pub fn middleware<M: Middleware + 'static>(&mut self, middleware: M) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
         self
     }
 
@@ -109,6 +203,55 @@ impl ServerBuilder {
         self
     }
 
+    /// Registers a group of routes under `prefix`, see [`Scope`].
+    ///
+    /// ```no_run
+    /// # use khttp::{Headers, Method::*, Server};
+    /// let mut app = Server::builder("127.0.0.1:8080").unwrap();
+    /// app.scope("/api/v1", |api| {
+    ///     api.route(Get, "/users/:id", |ctx, res| res.ok(Headers::empty(), ctx.scoped_path()));
+    ///     api.fallback_route(|_, res| res.send0(&khttp::Status::NOT_FOUND, Headers::empty()));
+    /// });
+    /// ```
+    pub fn scope<F>(&mut self, prefix: &str, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut Scope),
+    {
+        let mut scope = Scope::new();
+        f(&mut scope);
+        self.router.nest(prefix, scope.into_router());
+        self
+    }
+
+    /// Routes for requests whose `host` (or absolute-form request-target authority)
+    /// matches `pattern`: `example.com`, `*.example.com` or `:tenant.example.com` with
+    /// the `:param` labels in [`RequestContext::params`]. Routes registered on the
+    /// builder itself serve the other hosts, unless [`unknown_host`](Self::unknown_host)
+    /// says otherwise.
+    ///
+    /// ```no_run
+    /// # use khttp::{Headers, Method::*, Server};
+    /// let mut app = Server::builder("127.0.0.1:8080").unwrap();
+    /// app.host(":tenant.example.com", |site| {
+    ///     site.route(Get, "/", |ctx, res| res.ok(Headers::empty(), ctx.params.get("tenant").unwrap()));
+    /// });
+    /// ```
+    pub fn host<F>(&mut self, pattern: &str, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut Scope),
+    {
+        let mut scope = Scope::new();
+        f(&mut scope);
+        self.router.host(pattern, scope.into_router());
+        self
+    }
+
+    /// How requests for hosts without routes of their own are handled, see [`UnknownHost`].
+    pub fn unknown_host(&mut self, policy: UnknownHost) -> &mut Self {
+        self.router.set_unknown_host(policy);
+        self
+    }
+
     pub fn max_request_head_size(&mut self, value: usize) -> &mut Self {
         self.max_request_head_size = value;
         self
@@ -118,6 +261,36 @@ impl ServerBuilder {
         self.epoll_queue_max_events = value;
         self
     }
+
+    /// Reject ambiguous request framing (bare LF, conflicting `content-length`,
+    /// `content-length` with `transfer-encoding`, ...) with 400, see [`Request::parse`].
+    /// On by default.
+    pub fn strict_parsing(&mut self, strict: bool) -> &mut Self {
+        self.strict_parsing = strict;
+        self
+    }
+
+    /// Limits on header count, header size (both answered with 431) and request-target
+    /// length (414). `max_request_head_size` still caps the whole head.
+    pub fn request_limits(&mut self, limits: RequestLimits) -> &mut Self {
+        self.request_limits = limits;
+        self
+    }
+
+    /// Path normalization applied before routing, `None` routes on the raw path.
+    pub fn path_normalization(&mut self, config: Option<PathNormalization>) -> &mut Self {
+        self.path_normalization = config;
+        self
+    }
+
+    /// Enables response compression, off by default (also with `--features compression`).
+    /// `None` disables it again; single responses opt out with
+    /// [`ResponseHandle::disable_compression`](crate::ResponseHandle::disable_compression).
+    #[cfg(feature = "compression")]
+    pub fn compression(&mut self, config: Option<CompressionConfig>) -> &mut Self {
+        self.compression = config;
+        self
+    }
 }
 
 fn get_default_thread_count() -> usize {
@@ -127,3 +300,101 @@ fn get_default_thread_count() -> usize {
         Err(_) => FALLBACK_THREAD_COUNT,
     }
 }
+
+/// Routes registered under a common path prefix with [`ServerBuilder::scope`], or for a
+/// virtual host with [`ServerBuilder::host`].
+///
+/// Route paths are relative to the prefix, which may contain `:param` segments. Handlers
+/// see the full path in [`RequestContext::path`], and the prefix and the rest of the path
+/// in [`RequestContext::scope_prefix`] and [`RequestContext::scoped_path`]. A scope's
+/// fallback route handles requests under the prefix that no route matches, in place of
+/// the server's fallback route.
+pub struct Scope {
+    router: RouterBuilder<Box<RouteFn>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Scope {
+    fn new() -> Self {
+        Scope {
+            router: RouterBuilder::group(),
+            middleware: Vec::new(),
+        }
+    }
+
+    /// The routes, each behind the scope's middleware.
+    fn into_router(self) -> RouterBuilder<Box<RouteFn>> {
+        if self.middleware.is_empty() {
+            return self.router;
+        }
+        let stack = self.middleware;
+        self.router
+            .map_routes(&|route| middleware::layer(stack.clone(), route))
+    }
+
+    /// Middleware around the routes of this scope and nested scopes, fallback routes
+    /// included, see [`Middleware`].
+    pub fn middleware<M: Middleware + 'static>(&mut self, middleware: M) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    pub fn route<F>(&mut self, method: Method, path: &str, route_fn: F) -> &mut Self
+    where
+        F: Fn(RequestContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.router.add_route(&method, path, Box::new(route_fn));
+        self
+    }
+
+    /// A route whose url can be built from `name`, see [`RequestContext::url_for`].
+    pub fn named_route<F>(
+        &mut self,
+        name: &str,
+        method: Method,
+        path: &str,
+        route_fn: F,
+    ) -> &mut Self
+    where
+        F: Fn(RequestContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.router
+            .add_named_route(name, &method, path, Box::new(route_fn));
+        self
+    }
+
+    /// A route behind its own middleware `stack`, see [`Middleware`].
+    pub fn route_with<F>(
+        &mut self,
+        method: Method,
+        path: &str,
+        stack: &[Arc<dyn Middleware>],
+        route_fn: F,
+    ) -> &mut Self
+    where
+        F: Fn(RequestContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
+    {
+        let route = middleware::layer(stack.to_vec(), Box::new(route_fn));
+        self.router.add_route(&method, path, route);
+        self
+    }
+
+    pub fn fallback_route<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(RequestContext, &mut ResponseHandle) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.router.set_fallback_route(Box::new(f));
+        self
+    }
+
+    /// A nested scope, its prefix is relative to this one.
+    pub fn scope<F>(&mut self, prefix: &str, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut Scope),
+    {
+        let mut scope = Scope::new();
+        f(&mut scope);
+        self.router.nest(prefix, scope.into_router());
+        self
+    }
+}
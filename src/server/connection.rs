@@ -0,0 +1,57 @@
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The connection a request came in on, see [`RequestContext::connection`]. Addresses
+/// are looked up once per connection.
+///
+/// [`RequestContext::connection`]: crate::RequestContext::connection
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionInfo {
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
+    id: u64,
+    accepted_at: Instant,
+    request_index: u64,
+}
+
+impl ConnectionInfo {
+    pub(crate) fn new(stream: &TcpStream, accepted_at: Instant) -> io::Result<Self> {
+        Ok(ConnectionInfo {
+            peer_addr: stream.peer_addr()?,
+            local_addr: stream.local_addr()?,
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            accepted_at,
+            request_index: 0,
+        })
+    }
+
+    pub(crate) fn set_request_index(&mut self, index: u64) {
+        self.request_index = index;
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Unique among the connections of this process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn accepted_at(&self) -> Instant {
+        self.accepted_at
+    }
+
+    /// Index of the current request on the connection, 0 for the first one.
+    pub fn request_index(&self) -> u64 {
+        self.request_index
+    }
+}
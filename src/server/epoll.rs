@@ -5,26 +5,31 @@
 ))]
 compile_error!("feature `epoll` requires Linux on a 64-bit target.");
 
-use super::{ConnectionSetupAction, Server};
+use super::{ConnectionInfo, ConnectionSetupAction, Server};
 use crate::server::{handle_one_request, HandlerConfig};
 use crate::threadpool::{Task, ThreadPool};
-use crate::ResponseHandle;
+use crate::{Extensions, ResponseHandle};
 
 use libc::{
     epoll_create1, epoll_ctl, epoll_event, epoll_wait, EPOLLET, EPOLLIN, EPOLLRDHUP, EPOLL_CTL_ADD,
     EPOLL_CTL_DEL,
 };
+use std::cell::UnsafeCell;
 use std::net::{TcpListener, TcpStream};
 use std::os::unix::io::{AsRawFd, RawFd};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::{io, ptr};
+use std::time::Instant;
+use std::{io, mem, ptr};
 
 #[repr(align(64))]
 struct Handle {
     in_flight: AtomicBool, // ensure only one worker processes this connection at a time
     stream_ptr: *mut TcpStream,
     handler_config: Arc<HandlerConfig>,
+    connection: ConnectionInfo,
+    requests: AtomicU64, // handled so far, for `ConnectionInfo::request_index`
+    state: UnsafeCell<Extensions>, // only touched by the worker holding `in_flight`
     fd: RawFd,
     epfd: RawFd,
     closed: AtomicBool,
@@ -38,19 +43,36 @@ impl Task for EpollJob {
     #[inline(always)]
     fn run(self) {
         let handle = unsafe { &*(self.handle_ptr as *const Handle) };
-        let stream = unsafe { &*(handle.stream_ptr) };
-
-        let mut response = ResponseHandle::new(stream);
-        let keep_alive =
-            handle_one_request(stream, &mut response, &handle.handler_config).unwrap_or(false);
+        // SAFETY: `in_flight` gives this job exclusive access to the connection
+        let state = unsafe { &mut *handle.state.get() };
+        let result = {
+            let stream = unsafe { &*(handle.stream_ptr) };
+            let mut response = ResponseHandle::new(stream);
+            let mut connection = handle.connection;
+            connection.set_request_index(handle.requests.fetch_add(1, Ordering::Relaxed));
+            handle_one_request(
+                stream,
+                &mut response,
+                &handle.handler_config,
+                &connection,
+                state,
+            )
+        };
 
-        if keep_alive {
+        if let Ok(true) = result {
             handle.in_flight.store(false, Ordering::Release);
         } else {
-            unsafe {
+            // SAFETY: unregistered, so no other job gets to the stream or the state again
+            let stream = unsafe {
                 let _ = epoll_ctl(handle.epfd, EPOLL_CTL_DEL, handle.fd, ptr::null_mut());
-                drop(Box::from_raw(handle.stream_ptr)); // close connection
+                *Box::from_raw(handle.stream_ptr)
+            };
+            let state = mem::take(state);
+            match &handle.handler_config.connection_teardown_hook {
+                Some(hook) => (hook)(stream, state, result.map(|_| ())),
+                None => drop(stream), // close connection
             }
+            // the handle must stay alive until here, see the stale pointer cleanup
             handle.closed.store(true, Ordering::Release);
         }
     }
@@ -83,21 +105,37 @@ impl Server {
                 if token == LISTENER_TOKEN {
                     // Edge-triggered accept: drain until WouldBlock
                     while let Ok((mut stream, _peer)) = listener.accept() {
+                        let accepted_at = Instant::now();
+                        let mut state = Extensions::new();
                         if let Some(hook) = &self.connection_setup_hook {
                             stream = match (hook)(Ok((stream, _peer))) {
                                 ConnectionSetupAction::Proceed(s) => s,
+                                ConnectionSetupAction::ProceedWith(s, ext) => {
+                                    state = ext;
+                                    s
+                                }
                                 ConnectionSetupAction::Drop => continue,
                                 ConnectionSetupAction::StopAccepting => return Ok(()),
                             }
                         }
 
                         let _ = stream.set_nodelay(true);
+                        let connection = match ConnectionInfo::new(&stream, accepted_at) {
+                            Ok(connection) => connection,
+                            Err(e) => {
+                                self.teardown(stream, state, Err(e));
+                                continue;
+                            }
+                        };
                         let fd = stream.as_raw_fd();
                         let stream_ptr = Box::into_raw(Box::new(stream));
 
                         let handle = Box::new(Handle {
                             in_flight: AtomicBool::new(false),
                             handler_config: Arc::clone(&self.handler_config),
+                            connection,
+                            requests: AtomicU64::new(0),
+                            state: UnsafeCell::new(state),
                             stream_ptr,
                             epfd,
                             fd,
@@ -110,9 +148,13 @@ impl Server {
                             u64: handle_ptr,
                         };
                         if unsafe { epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &mut cev) } == -1 {
-                            unsafe {
-                                drop(Box::from_raw(handle_ptr as *mut Handle));
-                            }
+                            let e = io::Error::last_os_error();
+                            // SAFETY: never registered, so no job has seen the handle
+                            let (stream, state) = unsafe {
+                                let handle = *Box::from_raw(handle_ptr as *mut Handle);
+                                (*Box::from_raw(handle.stream_ptr), handle.state.into_inner())
+                            };
+                            self.teardown(stream, state, Err(e));
                         }
                     }
                 } else {
@@ -138,6 +180,14 @@ impl Server {
         }
     }
 
+    /// Hands a connection that never reached a worker back to the teardown hook.
+    fn teardown(&self, stream: TcpStream, state: Extensions, result: io::Result<()>) {
+        match &self.handler_config.connection_teardown_hook {
+            Some(hook) => (hook)(stream, state, result),
+            None => drop(stream),
+        }
+    }
+
     fn create_listener(&self, listener_token: u64) -> io::Result<(TcpListener, i32)> {
         let listener = TcpListener::bind(&*self.bind_addrs)?;
         listener.set_nonblocking(true)?;
@@ -1,18 +1,31 @@
-use crate::parser::Request;
-use crate::router::RouteParams;
+use crate::form::{self, FormLimits, Multipart, UrlEncodedForm};
+use crate::middleware::{Middleware, Next};
+use crate::parser::{ParseStatus, Request, RequestLimits, RequestParser};
+use crate::router::{ParamError, RouteInfo, RouteParams, UnknownHost, UrlError};
+use crate::sendfile;
 use crate::threadpool::{Task, ThreadPool};
 use crate::{
-    BodyReader, Headers, HttpParsingError, HttpPrinter, Method, RequestUri, Router, Status,
+    BodyReader, Extensions, Headers, HttpParsingError, HttpPrinter, Method, PathNormalization,
+    RequestUri, Router, Status,
 };
+use std::borrow::Cow;
 use std::cell::RefCell;
+use std::fs::File;
 use std::io::{self, Read};
 use std::mem::MaybeUninit;
 use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(feature = "compression")]
+use crate::compression::{self, BodyDecoder, CompressionConfig, ResponseCompression};
 
 mod builder;
+mod connection;
 mod epoll;
-pub use builder::ServerBuilder;
+pub use builder::{Scope, ServerBuilder};
+pub use connection::ConnectionInfo;
 
 pub type RouteFn = dyn for<'req, 's> Fn(RequestContext<'req>, &mut ResponseHandle<'s>) -> io::Result<()>
     + Send
@@ -21,17 +34,29 @@ pub type RouteFn = dyn for<'req, 's> Fn(RequestContext<'req>, &mut ResponseHandl
 pub type ConnectionSetupHookFn =
     dyn Fn(io::Result<(TcpStream, SocketAddr)>) -> ConnectionSetupAction + Send + Sync;
 
-pub type ConnectionTeardownHookFn = dyn Fn(TcpStream, io::Result<()>) + Send + Sync;
+pub type ConnectionTeardownHookFn = dyn Fn(TcpStream, Extensions, io::Result<()>) + Send + Sync;
 
-pub type PreRoutingHookFn = dyn for<'req, 's> Fn(&mut Request<'req>, &mut ResponseHandle<'s>) -> PreRoutingAction
+pub type PreRoutingHookFn = dyn for<'req, 's> Fn(
+        &mut Request<'req>,
+        &ConnectionInfo,
+        &mut Extensions,
+        &mut ResponseHandle<'s>,
+    ) -> PreRoutingAction
     + Send
     + Sync;
 
 struct HandlerConfig {
     router: Router<Box<RouteFn>>,
-    pre_routing_hook: Option<Box<PreRoutingHookFn>>,
+    state: Extensions,
+    middleware: Vec<Arc<dyn Middleware>>,
+    pre_routing_hooks: Vec<Box<PreRoutingHookFn>>,
     connection_teardown_hook: Option<Box<ConnectionTeardownHookFn>>,
     max_request_head: usize,
+    strict_parsing: bool,
+    request_limits: RequestLimits,
+    path_normalization: Option<PathNormalization>,
+    #[cfg(feature = "compression")]
+    compression: Option<CompressionConfig>,
 }
 
 pub struct Server {
@@ -45,6 +70,9 @@ pub struct Server {
 
 pub enum ConnectionSetupAction {
     Proceed(TcpStream),
+    /// Proceed with per-connection state, available to every request on the connection
+    /// ([`RequestContext::connection_state`]) and handed to the connection teardown hook.
+    ProceedWith(TcpStream, Extensions),
     Drop,
     StopAccepting,
 }
@@ -69,15 +97,20 @@ impl Server {
         self.thread_count
     }
 
+    /// The registered routes, e.g. for logging at startup, see [`Router::routes`].
+    pub fn routes(&self) -> impl Iterator<Item = &RouteInfo> {
+        self.handler_config.router.routes()
+    }
+
     pub fn serve(self) -> io::Result<()> {
-        struct PoolJob(TcpStream, Arc<HandlerConfig>);
+        struct PoolJob(TcpStream, Extensions, Instant, Arc<HandlerConfig>);
 
         impl Task for PoolJob {
             #[inline]
-            fn run(self) {
-                let result = handle_connection(&self.0, &self.1);
-                if let Some(hook) = &self.1.connection_teardown_hook {
-                    (hook)(self.0, result);
+            fn run(mut self) {
+                let result = handle_connection(&self.0, &mut self.1, self.2, &self.3);
+                if let Some(hook) = &self.3.connection_teardown_hook {
+                    (hook)(self.0, self.1, result);
                 }
             }
         }
@@ -87,20 +120,23 @@ impl Server {
 
         loop {
             let conn = listener.accept();
+            let accepted_at = Instant::now();
 
-            let stream = match &self.connection_setup_hook {
+            let (stream, state) = match &self.connection_setup_hook {
                 Some(hook) => match (hook)(conn) {
-                    ConnectionSetupAction::Proceed(stream) => stream,
+                    ConnectionSetupAction::Proceed(stream) => (stream, Extensions::new()),
+                    ConnectionSetupAction::ProceedWith(stream, state) => (stream, state),
                     ConnectionSetupAction::Drop => continue,
                     ConnectionSetupAction::StopAccepting => break,
                 },
                 None => match conn {
-                    Ok((stream, _)) => stream,
+                    Ok((stream, _)) => (stream, Extensions::new()),
                     Err(_) => continue,
                 },
             };
 
-            pool.execute(PoolJob(stream, Arc::clone(&self.handler_config)));
+            let config = Arc::clone(&self.handler_config);
+            pool.execute(PoolJob(stream, state, accepted_at, config));
         }
         Ok(())
     }
@@ -110,38 +146,50 @@ impl Server {
 
         loop {
             let conn = listener.accept();
+            let accepted_at = Instant::now();
 
-            let stream = match &self.connection_setup_hook {
+            let (stream, state) = match &self.connection_setup_hook {
                 Some(hook) => match (hook)(conn) {
-                    ConnectionSetupAction::Proceed(stream) => stream,
+                    ConnectionSetupAction::Proceed(stream) => (stream, Extensions::new()),
+                    ConnectionSetupAction::ProceedWith(stream, state) => (stream, state),
                     ConnectionSetupAction::Drop => continue,
                     ConnectionSetupAction::StopAccepting => break,
                 },
                 None => match conn {
-                    Ok((stream, _)) => stream,
+                    Ok((stream, _)) => (stream, Extensions::new()),
                     Err(_) => continue,
                 },
             };
             let config = Arc::clone(&self.handler_config);
 
             std::thread::spawn(move || {
-                let result = handle_connection(&stream, &config);
+                let mut state = state;
+                let result = handle_connection(&stream, &mut state, accepted_at, &config);
                 if let Some(hook) = &config.connection_teardown_hook {
-                    (hook)(stream, result);
+                    (hook)(stream, state, result);
                 }
             });
         }
         Ok(())
     }
 
+    /// Serves the requests of `stream` until it's closed, the lifecycle hooks aside.
     pub fn handle(&self, stream: &TcpStream) -> io::Result<()> {
-        handle_connection(stream, &self.handler_config)
+        self.handle_with(stream, &mut Extensions::new())
+    }
+
+    /// Like [`handle`](Server::handle), with `state` as the per-connection state (see
+    /// [`ConnectionSetupAction::ProceedWith`]).
+    pub fn handle_with(&self, stream: &TcpStream, state: &mut Extensions) -> io::Result<()> {
+        handle_connection(stream, state, Instant::now(), &self.handler_config)
     }
 }
 
 pub struct ResponseHandle<'s> {
     stream: &'s TcpStream,
     keep_alive: bool,
+    #[cfg(feature = "compression")]
+    compression: ResponseCompression,
 }
 
 impl<'s> ResponseHandle<'s> {
@@ -149,6 +197,8 @@ impl<'s> ResponseHandle<'s> {
         ResponseHandle {
             stream,
             keep_alive: true,
+            #[cfg(feature = "compression")]
+            compression: ResponseCompression::default(),
         }
     }
 
@@ -165,6 +215,17 @@ impl<'s> ResponseHandle<'s> {
         if headers.is_connection_close() {
             self.keep_alive = false;
         }
+        #[cfg(feature = "compression")]
+        if self.compression.is_active() {
+            let body = body.as_ref();
+            return compression::write_response_bytes(
+                self.stream,
+                &self.compression,
+                status,
+                headers,
+                body,
+            );
+        }
         HttpPrinter::write_response_bytes(self.stream, status, headers, body.as_ref())
     }
 
@@ -192,9 +253,77 @@ impl<'s> ResponseHandle<'s> {
         if headers.is_connection_close() {
             self.keep_alive = false;
         }
+        #[cfg(feature = "compression")]
+        if self.compression.is_active() {
+            return compression::write_response(
+                self.stream,
+                &self.compression,
+                status,
+                headers,
+                body,
+            );
+        }
         HttpPrinter::write_response(self.stream, status, headers, body)
     }
 
+    /// Sends only the head of a response announcing `content_length` bytes, e.g. for HEAD requests.
+    pub fn send_head(
+        &mut self,
+        status: &Status,
+        headers: &Headers,
+        content_length: u64,
+    ) -> io::Result<()> {
+        if headers.is_connection_close() {
+            self.keep_alive = false;
+        }
+        HttpPrinter::write_response_head(self.stream, status, headers, content_length)
+    }
+
+    /// Sends `file` (or `range` of it) as the body, with `content-length` taken from the file
+    /// metadata and `content-range` added for ranges. With `--features sendfile` the body is
+    /// copied by the kernel on Linux. These responses are never compressed. The file cursor
+    /// is left untouched on unix, other platforms seek `file`.
+    pub fn send_file(
+        &mut self,
+        status: &Status,
+        headers: &Headers,
+        file: &File,
+        range: Option<Range<u64>>,
+    ) -> io::Result<()> {
+        if headers.is_transfer_encoding_chunked() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "send_file bodies can't be chunked",
+            ));
+        }
+        let file_len = file.metadata()?.len();
+        if let Some(r) = &range {
+            if r.start >= r.end || r.end > file_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "range not satisfiable",
+                ));
+            }
+        }
+        if headers.is_connection_close() {
+            self.keep_alive = false;
+        }
+
+        match range {
+            Some(r) => {
+                let mut headers = headers.clone();
+                let content_range = format!("bytes {}-{}/{}", r.start, r.end - 1, file_len);
+                headers.replace("content-range", content_range.into_bytes());
+                HttpPrinter::write_response_head(self.stream, status, &headers, r.end - r.start)?;
+                sendfile::copy_file_to_stream(self.stream, file, r.start, r.end - r.start)
+            }
+            None => {
+                HttpPrinter::write_response_head(self.stream, status, headers, file_len)?;
+                sendfile::copy_file_to_stream(self.stream, file, 0, file_len)
+            }
+        }
+    }
+
     pub fn send_100_continue(&mut self) -> io::Result<()> {
         HttpPrinter::write_100_continue(self.stream)
     }
@@ -203,6 +332,12 @@ impl<'s> ResponseHandle<'s> {
         HttpPrinter::write_417_expectation_failed(self.stream)
     }
 
+    /// Sends the rest of this request's responses uncompressed.
+    #[cfg(feature = "compression")]
+    pub fn disable_compression(&mut self) {
+        self.compression.disable();
+    }
+
     pub fn get_stream(&self) -> &TcpStream {
         self.stream
     }
@@ -214,14 +349,111 @@ pub struct RequestContext<'r> {
     pub headers: Headers<'r>,
     pub params: &'r RouteParams<'r, 'r>,
     pub http_version: u8,
+    path: &'r str,
+    scope_prefix: &'r str,
+    route_pattern: Option<&'r str>,
+    route_name: Option<&'r str>,
+    connection: &'r ConnectionInfo,
+    connection_state: &'r mut Extensions,
+    router: &'r Router<Box<RouteFn>>,
+    state: &'r Extensions,
+    extensions: Extensions,
     body: BodyReader<'r, &'r TcpStream>,
 }
 
 impl<'r> RequestContext<'r> {
+    /// The request path after normalization, as used for routing (see [`PathNormalization`]).
+    pub fn path(&self) -> &'r str {
+        self.path
+    }
+
+    /// The part of the path matched by the prefix of the route's scope (e.g. `/api/v1`),
+    /// empty outside of scopes (see [`ServerBuilder::scope`]).
+    pub fn scope_prefix(&self) -> &'r str {
+        self.scope_prefix
+    }
+
+    /// The path without the scope prefix, e.g. `/users/1` for `/api/v1/users/1`.
+    pub fn scoped_path(&self) -> &'r str {
+        match &self.path[self.scope_prefix.len()..] {
+            "" => "/",
+            rest => rest,
+        }
+    }
+
+    /// The path pattern of the matched route, group and scope prefixes included (e.g.
+    /// `/api/users/:id`), `None` for fallback routes. Unlike the path, it makes a
+    /// low-cardinality metrics label.
+    pub fn route_pattern(&self) -> Option<&'r str> {
+        self.route_pattern
+    }
+
+    /// The name of the matched route, see [`ServerBuilder::named_route`].
+    pub fn route_name(&self) -> Option<&'r str> {
+        self.route_name
+    }
+
+    pub fn connection(&self) -> &'r ConnectionInfo {
+        self.connection
+    }
+
+    /// Application state of type `T`, see [`ServerBuilder::state`].
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&'r T> {
+        self.state.get()
+    }
+
+    /// Data attached to this request by pre-routing hooks and middleware.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// State kept across the requests of a connection, set up by the connection setup
+    /// hook (see [`ConnectionSetupAction::ProceedWith`]), empty otherwise.
+    pub fn connection_state(&mut self) -> &mut Extensions {
+        self.connection_state
+    }
+
+    /// Path of the route registered as `name`, see [`Router::url_for`].
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        self.router.url_for(name, params)
+    }
+
     pub fn body(&mut self) -> &mut BodyReader<'r, &'r TcpStream> {
         &mut self.body
     }
 
+    /// Request body with `content-encoding` (gzip / deflate) undone, see [`BodyDecoder`].
+    #[cfg(feature = "compression")]
+    pub fn decoded_body(
+        &mut self,
+        max_size: u64,
+    ) -> io::Result<BodyDecoder<&mut BodyReader<'r, &'r TcpStream>>> {
+        BodyDecoder::new(&mut self.body, &mut self.headers, max_size)
+    }
+
+    /// Streaming `multipart/form-data` body, `InvalidInput` error for other content types.
+    pub fn multipart(
+        &mut self,
+        limits: FormLimits,
+    ) -> io::Result<Multipart<&mut BodyReader<'r, &'r TcpStream>>> {
+        Multipart::from_headers(&mut self.body, &self.headers, limits)
+    }
+
+    /// Reads an `application/x-www-form-urlencoded` body, `InvalidInput` error for other content types.
+    pub fn urlencoded_form(&mut self, limits: FormLimits) -> io::Result<UrlEncodedForm> {
+        if !form::is_urlencoded(&self.headers) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not an application/x-www-form-urlencoded body",
+            ));
+        }
+        UrlEncodedForm::read(&mut self.body, limits)
+    }
+
     pub fn get_stream(&self) -> &TcpStream {
         self.body.inner()
     }
@@ -247,15 +479,23 @@ impl<'r> RequestContext<'r> {
     }
 }
 
-fn handle_connection(stream: &TcpStream, config: &Arc<HandlerConfig>) -> io::Result<()> {
+fn handle_connection(
+    stream: &TcpStream,
+    state: &mut Extensions,
+    accepted_at: Instant,
+    config: &Arc<HandlerConfig>,
+) -> io::Result<()> {
     let mut response = ResponseHandle::new(stream);
+    let mut connection = ConnectionInfo::new(stream, accepted_at)?;
 
-    loop {
-        let keep_alive = handle_one_request(stream, &mut response, config)?;
+    for index in 0.. {
+        connection.set_request_index(index);
+        let keep_alive = handle_one_request(stream, &mut response, config, &connection, state)?;
         if !keep_alive {
-            return Ok(());
+            break;
         }
     }
+    Ok(())
 }
 
 const DEFAULT_REQUEST_BUFFER_SIZE: usize = 4096;
@@ -264,15 +504,16 @@ thread_local! {
         RefCell::new(Vec::with_capacity(DEFAULT_REQUEST_BUFFER_SIZE));
 }
 
-/// Read request head into a thread-local uninitialized buffer and parse it.
+/// Read request head into a thread-local uninitialized buffer and parse it incrementally.
 /// Thread-local storage is used since each thread handles exactly one request at once.
 fn read_request<'a>(
     mut stream: &TcpStream,
-    max_size: usize,
+    config: &HandlerConfig,
 ) -> Result<(&'a [u8], Request<'a>), ReadRequestError> {
     use std::slice::{from_raw_parts, from_raw_parts_mut};
     use ReadRequestError::*;
 
+    let max_size = config.max_request_head;
     REQUEST_BUFFER.with(|cell| {
         let mut vec = cell.borrow_mut();
 
@@ -282,9 +523,16 @@ fn read_request<'a>(
 
         let ptr = vec.as_mut_ptr() as *mut u8;
         let mut filled = 0;
+        let mut parser = RequestParser::new()
+            .strict(config.strict_parsing)
+            .limits(config.request_limits);
 
         loop {
             if filled == max_size {
+                // the request line alone doesn't fit
+                if !parser.in_headers() {
+                    return Err(InvalidRequestHead(HttpParsingError::UriTooLong));
+                }
                 return Err(RequestHeadTooLarge);
             }
 
@@ -301,10 +549,10 @@ fn read_request<'a>(
             // SAFETY: only the prefix [..filled] has been written (initialized) by read()
             let buf = unsafe { from_raw_parts(ptr as *const u8, filled) };
 
-            match Request::parse(buf) {
-                Ok(req) => return Ok((buf, req)),
-                Err(HttpParsingError::UnexpectedEof) => continue, // need more bytes, keep reading
-                Err(_) => return Err(InvalidRequestHead),         // malformed request head
+            match parser.parse(buf) {
+                Ok(ParseStatus::Complete(req)) => return Ok((buf, req)),
+                Ok(ParseStatus::Partial(_)) => continue, // need more bytes, keep reading
+                Err(e) => return Err(InvalidRequestHead(e)), // malformed request head
             }
         }
     })
@@ -312,7 +560,7 @@ fn read_request<'a>(
 
 enum ReadRequestError {
     RequestHeadTooLarge,
-    InvalidRequestHead,
+    InvalidRequestHead(HttpParsingError),
     ReadEof,
     IOError,
 }
@@ -322,11 +570,20 @@ fn handle_one_request(
     stream: &TcpStream,
     response: &mut ResponseHandle<'_>,
     config: &HandlerConfig,
+    connection: &ConnectionInfo,
+    connection_state: &mut Extensions,
 ) -> io::Result<bool> {
-    let (buf, mut request) = match read_request(stream, config.max_request_head) {
+    let (buf, mut request) = match read_request(stream, config) {
         Ok((buf, req)) => (buf, req),
-        Err(ReadRequestError::InvalidRequestHead) => {
-            response.send0(&Status::BAD_REQUEST, Headers::close())?;
+        Err(ReadRequestError::InvalidRequestHead(e)) => {
+            let status = match e {
+                HttpParsingError::TooManyHeaders | HttpParsingError::HeaderTooLarge => {
+                    Status::of(431)
+                }
+                HttpParsingError::UriTooLong => Status::of(414),
+                _ => Status::BAD_REQUEST,
+            };
+            response.send0(&status, Headers::close())?;
             return Ok(false);
         }
         Err(ReadRequestError::RequestHeadTooLarge) => {
@@ -336,16 +593,52 @@ fn handle_one_request(
         Err(_) => return Ok(false), // silently drop connection on eof / io-error
     };
 
-    if let Some(hook) = &config.pre_routing_hook {
-        match (hook)(&mut request, response) {
+    // several host headers could each select a different virtual host (RFC 9112 3.2)
+    if request.headers.get_all("host").nth(1).is_some() {
+        response.send0(&Status::BAD_REQUEST, Headers::close())?;
+        return Ok(false);
+    }
+
+    #[cfg(feature = "compression")]
+    {
+        response.compression = ResponseCompression::negotiate(config.compression, &request.headers);
+    }
+
+    let mut extensions = Extensions::new();
+    for hook in &config.pre_routing_hooks {
+        match (hook)(&mut request, connection, &mut extensions, response) {
             PreRoutingAction::Proceed => {}
             PreRoutingAction::Drop => return Ok(response.keep_alive),
         }
     }
 
-    let matched_route = config
-        .router
-        .match_route(&request.method, request.uri.path());
+    let path = match &config.path_normalization {
+        Some(normalization) => match normalization.normalize(request.uri.path()) {
+            Some(path) => path,
+            None => {
+                response.send0(&Status::BAD_REQUEST, Headers::close())?;
+                return Ok(false);
+            }
+        },
+        None => Cow::Borrowed(request.uri.path()),
+    };
+    // absolute-form request-target authority wins over the host header (RFC 9112 3.2.2)
+    let host = match request.uri.authority() {
+        Some(authority) => Some(Cow::Borrowed(authority)),
+        None => host_header(&request.headers),
+    };
+    let Some(matched_route) =
+        config
+            .router
+            .match_host_route(host.as_deref(), &request.method, &path)
+    else {
+        let status = match config.router.unknown_host() {
+            UnknownHost::Misdirected => Status::of(421),
+            _ => Status::NOT_FOUND,
+        };
+        response.send0(&status, Headers::close())?;
+        return Ok(false);
+    };
 
     let body = BodyReader::from_request(&buf[request.buf_offset..], stream, &request.headers);
     let ctx = RequestContext {
@@ -354,13 +647,39 @@ fn handle_one_request(
         uri: &request.uri,
         http_version: request.http_version,
         params: &matched_route.params,
+        path: &path,
+        scope_prefix: matched_route.prefix,
+        route_pattern: matched_route.pattern,
+        route_name: matched_route.name,
+        connection,
+        connection_state,
+        router: &config.router,
+        state: &config.state,
+        extensions,
         body,
     };
 
     let client_requested_close = ctx.headers.is_connection_close();
-    (matched_route.route)(ctx, response)?;
+    let next = Next::new(&config.middleware, &**matched_route.route);
+    if let Err(e) = next.run(ctx, response) {
+        // a route param that didn't parse, see `RouteParams::parse`
+        if ParamError::is_param_error(&e) {
+            response.send0(&Status::BAD_REQUEST, Headers::close())?;
+            return Ok(false);
+        }
+        return Err(e);
+    }
     if client_requested_close {
         return Ok(false);
     }
     Ok(response.keep_alive)
 }
+
+/// The `host` header value, borrowed from the request unless a hook replaced it.
+fn host_header<'r>(headers: &Headers<'r>) -> Option<Cow<'r, str>> {
+    let (_, value) = headers.get_all("host").next()?;
+    match value {
+        Cow::Borrowed(bytes) => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+        Cow::Owned(bytes) => String::from_utf8(bytes.clone()).ok().map(Cow::Owned),
+    }
+}
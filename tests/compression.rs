@@ -0,0 +1,334 @@
+#![cfg(feature = "compression")]
+use flate2::read::{DeflateEncoder, GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder};
+use flate2::Compression;
+use khttp::{BodyDecoder, BodyReader, CompressionConfig, Headers, Method::*, Server, Status};
+use std::io::{Cursor, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+const TEXT: &str = "The quick brown fox jumps over the lazy dog. ";
+
+#[test]
+fn test_gzip_send() {
+    let res = exchange("/text", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), Some("gzip"));
+    assert_eq!(res.header("vary"), Some("accept-encoding"));
+    assert!(res.header("content-length").is_some());
+    assert_eq!(gunzip(&res.body), TEXT.repeat(100));
+}
+
+#[test]
+fn test_deflate_preferred_by_qvalue() {
+    let res = exchange("/text", "accept-encoding: gzip;q=0.5, deflate\r\n");
+
+    assert_eq!(res.header("content-encoding"), Some("deflate"));
+    assert_eq!(inflate(&res.body), TEXT.repeat(100));
+}
+
+#[test]
+fn test_wildcard_and_q0() {
+    let res = exchange("/text", "accept-encoding: gzip;q=0, *\r\n");
+    assert_eq!(res.header("content-encoding"), Some("deflate"));
+
+    let res = exchange("/text", "accept-encoding: gzip;q=0, deflate;q=0\r\n");
+    assert_eq!(res.header("content-encoding"), None);
+    assert_eq!(res.header("vary"), Some("accept-encoding"));
+    assert_eq!(res.body, TEXT.repeat(100).as_bytes());
+}
+
+#[test]
+fn test_no_accept_encoding() {
+    let res = exchange("/text", "");
+
+    assert_eq!(res.header("content-encoding"), None);
+    assert_eq!(res.header("vary"), Some("accept-encoding"));
+    assert_eq!(res.body, TEXT.repeat(100).as_bytes());
+}
+
+#[test]
+fn test_small_body_not_compressed() {
+    let res = exchange("/small", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), None);
+    assert_eq!(res.header("vary"), None);
+    assert_eq!(res.body, b"tiny");
+}
+
+#[test]
+fn test_incompressible_content_type() {
+    let res = exchange("/image", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), None);
+    assert_eq!(res.body.len(), 4096);
+}
+
+#[test]
+fn test_streamed_content_length_switches_framing() {
+    let res = exchange("/stream-cl", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), Some("gzip"));
+    assert_ne!(
+        res.header("content-length"),
+        Some(&*(TEXT.len() * 1000).to_string())
+    );
+    assert_eq!(gunzip(&res.body), TEXT.repeat(1000));
+}
+
+#[test]
+fn test_streamed_unknown_length() {
+    let res = exchange("/stream", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), Some("gzip"));
+    assert_eq!(gunzip(&res.body), TEXT.repeat(10_000));
+}
+
+#[test]
+fn test_route_opt_out() {
+    let res = exchange("/opt-out", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), None);
+    assert_eq!(res.body, TEXT.repeat(100).as_bytes());
+}
+
+#[test]
+fn test_compression_is_opt_in() {
+    let request = "GET / HTTP/1.1\r\nconnection: close\r\naccept-encoding: gzip\r\n\r\n";
+    let res = exchange_on(
+        || {
+            let mut app = Server::builder("127.0.0.1:0").unwrap();
+            app.route(Get, "/", |_, res| res.ok(&text_headers(), TEXT.repeat(100)));
+            app.build()
+        },
+        request.as_bytes().to_vec(),
+    );
+
+    assert_eq!(res.header("content-encoding"), None);
+    assert_eq!(res.header("vary"), None);
+    assert_eq!(res.body, TEXT.repeat(100).as_bytes());
+}
+
+#[test]
+fn test_existing_content_encoding_untouched() {
+    let res = exchange("/encoded", "accept-encoding: gzip\r\n");
+
+    assert_eq!(res.header("content-encoding"), Some("br"));
+    assert_eq!(res.body, TEXT.repeat(100).as_bytes());
+}
+
+// ---------------------------------------------------------------------
+// REQUEST BODY DECODING
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_decode_gzip_body() {
+    let encoded = gzip(TEXT.as_bytes());
+    let mut headers = encoded_headers("gzip", encoded.len());
+    let body = BodyReader::new_fixed(&encoded, &[][..], encoded.len());
+
+    let mut decoder = BodyDecoder::new(body, &mut headers, 1024).unwrap();
+    assert_eq!(decoder.string().unwrap(), TEXT);
+    assert_eq!(headers.get("content-encoding"), None);
+    assert_eq!(headers.get_content_length(), None);
+    assert_eq!(headers.get("content-type"), Some(&b"application/json"[..]));
+}
+
+#[test]
+fn test_decode_deflate_zlib_and_raw() {
+    let mut zlib = Vec::new();
+    ZlibEncoder::new(TEXT.as_bytes(), Compression::default())
+        .read_to_end(&mut zlib)
+        .unwrap();
+    let mut raw = Vec::new();
+    DeflateEncoder::new(TEXT.as_bytes(), Compression::default())
+        .read_to_end(&mut raw)
+        .unwrap();
+
+    for encoded in [zlib, raw] {
+        let mut headers = encoded_headers("deflate", encoded.len());
+        let mut decoder = BodyDecoder::new(&encoded[..], &mut headers, 1024).unwrap();
+        assert_eq!(decoder.string().unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn test_decode_identity_keeps_headers() {
+    let mut headers = Headers::new();
+    headers.add("content-length", &b"4"[..]);
+    let mut decoder = BodyDecoder::new(&b"body"[..], &mut headers, 1024).unwrap();
+
+    assert_eq!(decoder.string().unwrap(), "body");
+    assert_eq!(headers.get_content_length(), Some(4));
+}
+
+#[test]
+fn test_decode_size_limit_applies_to_decoded_size() {
+    let bomb = gzip(&vec![0u8; 1024 * 1024]);
+    assert!(bomb.len() < 2048);
+    let mut headers = encoded_headers("gzip", bomb.len());
+
+    let mut decoder = BodyDecoder::new(&bomb[..], &mut headers, 64 * 1024).unwrap();
+    let err = decoder.vec().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(decoder.limit_exceeded());
+}
+
+#[test]
+fn test_decode_unsupported_coding() {
+    for coding in ["br", "gzip, gzip"] {
+        let mut headers = encoded_headers(coding, 0);
+        let err = BodyDecoder::new(&b""[..], &mut headers, 1024)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+}
+
+#[test]
+fn test_decoded_request_body() {
+    let encoded = gzip(TEXT.repeat(10).as_bytes());
+    let res = exchange_raw(
+        [
+            format!(
+                "POST /decode HTTP/1.1\r\nconnection: close\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\n\r\n",
+                encoded.len()
+            )
+            .into_bytes(),
+            encoded,
+        ]
+        .concat(),
+    );
+
+    assert_eq!(res.body, format!("{} none", TEXT.len() * 10).as_bytes());
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_server() -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.compression(Some(CompressionConfig::new().min_size(256)));
+
+    app.route(Get, "/text", |_, res| {
+        res.ok(&text_headers(), TEXT.repeat(100))
+    });
+    app.route(Get, "/small", |_, res| res.ok(&text_headers(), "tiny"));
+    app.route(Get, "/image", |_, res| {
+        let mut headers = Headers::new();
+        headers.add(Headers::CONTENT_TYPE, &b"image/png"[..]);
+        res.ok(&headers, vec![7u8; 4096])
+    });
+    app.route(Get, "/stream-cl", |_, res| {
+        let body = TEXT.repeat(1000);
+        let mut headers = text_headers();
+        headers.set_content_length(Some(body.len() as u64));
+        res.okr(&headers, Cursor::new(body))
+    });
+    app.route(Get, "/stream", |_, res| {
+        res.okr(&text_headers(), Cursor::new(TEXT.repeat(10_000)))
+    });
+    app.route(Get, "/opt-out", |_, res| {
+        res.disable_compression();
+        res.ok(&text_headers(), TEXT.repeat(100))
+    });
+    app.route(Get, "/encoded", |_, res| {
+        let mut headers = text_headers();
+        headers.add("content-encoding", &b"br"[..]);
+        res.send(&Status::OK, &headers, TEXT.repeat(100))
+    });
+    app.route(Post, "/decode", |mut ctx, res| {
+        let body = ctx.decoded_body(64 * 1024)?.vec()?;
+        let encoding = ctx.headers.get("content-encoding").unwrap_or(b"none");
+        let reply = format!("{} {}", body.len(), String::from_utf8_lossy(encoding));
+        res.ok(Headers::empty(), reply)
+    });
+    app.build()
+}
+
+fn text_headers() -> Headers<'static> {
+    let mut headers = Headers::new_nodate();
+    headers.add(Headers::CONTENT_TYPE, &b"text/plain; charset=utf-8"[..]);
+    headers
+}
+
+struct RawResponse {
+    head: String,
+    body: Vec<u8>,
+}
+
+impl RawResponse {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            k.eq_ignore_ascii_case(name).then(|| v.trim())
+        })
+    }
+}
+
+fn exchange(path: &str, extra_headers: &str) -> RawResponse {
+    let request = format!("GET {path} HTTP/1.1\r\nconnection: close\r\n{extra_headers}\r\n");
+    exchange_raw(request.into_bytes())
+}
+
+fn exchange_raw(request: Vec<u8>) -> RawResponse {
+    exchange_on(build_server, request)
+}
+
+fn exchange_on(build: fn() -> Server, request: Vec<u8>) -> RawResponse {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build().handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(&request).unwrap();
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).unwrap();
+    server.join().unwrap();
+
+    let head_end = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8(raw[..head_end].to_vec()).unwrap();
+    let rest = &raw[head_end + 4..];
+
+    let mut response = RawResponse {
+        head,
+        body: Vec::new(),
+    };
+    response.body = match response.header("transfer-encoding") {
+        Some("chunked") => khttp::BodyReader::new_chunked(&[], rest).vec().unwrap(),
+        _ => rest.to_vec(),
+    };
+    response
+}
+
+fn encoded_headers(coding: &str, len: usize) -> Headers<'static> {
+    let mut headers = Headers::new();
+    headers.add("content-type", &b"application/json"[..]);
+    headers.add("content-encoding", coding.as_bytes().to_vec());
+    headers.add("content-length", len.to_string().into_bytes());
+    headers
+}
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    GzEncoder::new(bytes, Compression::default())
+        .read_to_end(&mut out)
+        .unwrap();
+    out
+}
+
+fn gunzip(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    GzDecoder::new(bytes).read_to_string(&mut out).unwrap();
+    out
+}
+
+fn inflate(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    ZlibDecoder::new(bytes).read_to_string(&mut out).unwrap();
+    out
+}
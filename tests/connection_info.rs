@@ -0,0 +1,163 @@
+use khttp::{Extensions, Headers, Method::*, PreRoutingAction, Server};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// TESTS
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_route_pattern_and_name() {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.named_route("user", Get, "/users/:id", |ctx, res| {
+        let body = format!("{:?} {:?}", ctx.route_pattern(), ctx.route_name());
+        res.ok(Headers::empty(), body)
+    });
+    app.scope("/api", |api| {
+        api.route(Get, "/items/*", |ctx, res| {
+            let body = format!("{:?} {:?}", ctx.route_pattern(), ctx.route_name());
+            res.ok(Headers::empty(), body)
+        });
+    });
+    let server = app.build();
+
+    let responses = exchange(&server, &["GET /users/1", "GET /api/items/x"]);
+    assert!(responses[0].ends_with(r#"Some("/users/:id") Some("user")"#));
+    assert!(responses[1].ends_with(r#"Some("/api/items/*") None"#));
+}
+
+#[test]
+fn test_connection_info() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    let hook_seen = seen.clone();
+    app.pre_routing_hook(move |_, conn, _, _| {
+        hook_seen.lock().unwrap().push(("hook", *conn));
+        PreRoutingAction::Proceed
+    });
+    let route_seen = seen.clone();
+    app.route(Get, "/", move |ctx, res| {
+        route_seen
+            .lock()
+            .unwrap()
+            .push(("route", *ctx.connection()));
+        res.ok(Headers::empty(), "ok")
+    });
+    let server = app.build();
+
+    exchange(&server, &["GET /", "GET /", "GET /"]);
+    exchange(&server, &["GET /"]);
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 8);
+    let (first, last) = (seen[0].1, seen[7].1);
+    assert_eq!(first.local_addr().ip(), first.peer_addr().ip());
+    assert_ne!(first.local_addr().port(), first.peer_addr().port());
+    assert!(last.id() > first.id());
+    assert!(last.accepted_at() > first.accepted_at());
+
+    let indexes: Vec<_> = seen
+        .iter()
+        .map(|(at, conn)| (*at, conn.id() == first.id(), conn.request_index()))
+        .collect();
+    assert_eq!(
+        indexes,
+        [
+            ("hook", true, 0),
+            ("route", true, 0),
+            ("hook", true, 1),
+            ("route", true, 1),
+            ("hook", true, 2),
+            ("route", true, 2),
+            ("hook", false, 0),
+            ("route", false, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_connection_state() {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.route(Get, "/", |mut ctx, res| {
+        let visits = ctx.connection_state().get_mut::<Visits>().unwrap();
+        visits.0 += 1;
+        let body = format!("visit {}", visits.0);
+        res.ok(Headers::empty(), body)
+    });
+    app.route(Get, "/fresh", |mut ctx, res| {
+        let empty = ctx.connection_state().is_empty();
+        res.ok(Headers::empty(), format!("empty {empty}"))
+    });
+    let server = app.build();
+
+    let mut state = Extensions::new();
+    state.insert(Visits(0));
+    let responses = exchange_with(&server, &mut state, &["GET /", "GET /", "GET /"]);
+    assert_eq!(responses, ["visit 1", "visit 2", "visit 3"]);
+    assert_eq!(state.get::<Visits>().unwrap().0, 3);
+
+    assert_eq!(exchange(&server, &["GET /fresh"]), ["empty true"]);
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+struct Visits(u64);
+
+fn exchange(server: &Server, request_lines: &[&str]) -> Vec<String> {
+    exchange_with(server, &mut Extensions::new(), request_lines)
+}
+
+/// Sends `request_lines` on one connection, the last one with `connection: close`, and
+/// returns the response bodies.
+fn exchange_with(server: &Server, state: &mut Extensions, request_lines: &[&str]) -> Vec<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests: Vec<String> = request_lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match i + 1 == request_lines.len() {
+            true => format!("{line} HTTP/1.1\r\nconnection: close\r\n\r\n"),
+            false => format!("{line} HTTP/1.1\r\n\r\n"),
+        })
+        .collect();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        requests
+            .iter()
+            .map(|request| {
+                stream.write_all(request.as_bytes()).unwrap();
+                read_body(&mut reader)
+            })
+            .collect()
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let _ = server.handle_with(&stream, state);
+    drop(stream);
+    client.join().unwrap()
+}
+
+fn read_body(reader: &mut BufReader<TcpStream>) -> String {
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    String::from_utf8(body).unwrap()
+}
@@ -0,0 +1,115 @@
+use khttp::cookie::{Cookies, SameSite, SetCookie};
+use khttp::Headers;
+use std::io::ErrorKind;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[test]
+fn test_cookies_parse() {
+    let mut headers = Headers::new();
+    headers.add("cookie", &b"a=1; b=\"quoted\";c=x=y; ;novalue; d="[..]);
+    headers.add("Cookie", &b"a=2; e=3"[..]);
+    let cookies = Cookies::new(&headers);
+
+    assert_eq!(cookies.get("a"), Some("1"));
+    assert_eq!(cookies.get("b"), Some("quoted"));
+    assert_eq!(cookies.get("c"), Some("x=y"));
+    assert_eq!(cookies.get("d"), Some(""));
+    assert_eq!(cookies.get("e"), Some("3"));
+    assert_eq!(cookies.get("novalue"), None);
+    assert_eq!(
+        cookies.iter().collect::<Vec<_>>(),
+        [
+            ("a", "1"),
+            ("b", "quoted"),
+            ("c", "x=y"),
+            ("d", ""),
+            ("a", "2"),
+            ("e", "3")
+        ]
+    );
+}
+
+#[test]
+fn test_cookies_empty() {
+    let headers = Headers::new();
+    let cookies = Cookies::new(&headers);
+    assert!(cookies.is_empty());
+    assert_eq!(cookies.get("a"), None);
+}
+
+#[test]
+fn test_set_cookie_all_attributes() {
+    let cookie = SetCookie::new("id", "a3fWa")
+        .unwrap()
+        .path("/app")
+        .domain("example.com")
+        .max_age(Duration::from_secs(3600))
+        .expires(UNIX_EPOCH + Duration::from_secs(1445412480))
+        .secure(true)
+        .http_only(true)
+        .same_site(SameSite::Strict);
+
+    assert_eq!(
+        cookie.to_string(),
+        "id=a3fWa; Path=/app; Domain=example.com; Max-Age=3600; \
+         Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure; HttpOnly; SameSite=Strict"
+    );
+}
+
+#[test]
+fn test_set_cookie_same_site_none_is_secure() {
+    let cookie = SetCookie::new("a", "b").unwrap().same_site(SameSite::None);
+    assert_eq!(cookie.to_string(), "a=b; Secure; SameSite=None");
+}
+
+#[test]
+fn test_set_cookie_removal() {
+    let cookie = SetCookie::removal("session").unwrap().path("/");
+    assert_eq!(
+        cookie.to_string(),
+        "session=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT"
+    );
+}
+
+#[test]
+fn test_set_cookie_appends_separate_headers() {
+    let mut headers = Headers::new_nodate();
+    SetCookie::new("a", "1").unwrap().append_to(&mut headers);
+    SetCookie::new("b", "2")
+        .unwrap()
+        .expires(SystemTime::now())
+        .append_to(&mut headers);
+
+    let values: Vec<_> = headers.get_all("set-cookie").map(|(_, v)| v).collect();
+    assert_eq!(values.len(), 2);
+    assert_eq!(&values[0][..], b"a=1");
+    assert!(values[1].starts_with(b"b=2; Expires="));
+}
+
+#[test]
+fn test_set_cookie_attribute_injection() {
+    let cookie = SetCookie::new("a", "b")
+        .unwrap()
+        .path("/x; HttpOnly=false\r\nevil: 1")
+        .domain("example.com;Secure");
+    assert_eq!(
+        cookie.to_string(),
+        "a=b; Path=/x HttpOnly=falseevil: 1; Domain=example.comSecure"
+    );
+}
+
+#[test]
+fn test_set_cookie_invalid() {
+    for (name, value) in [
+        ("", "x"),
+        ("a b", "x"),
+        ("a;", "x"),
+        ("a", "x y"),
+        ("a", "x;y"),
+        ("a", "\"x\""),
+        ("a", "x\r\nset-cookie: evil=1"),
+    ] {
+        let err = SetCookie::new(name, value).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{name:?}={value:?}");
+    }
+}
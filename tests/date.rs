@@ -1,4 +1,8 @@
-use khttp::date::get_date_from_secs;
+use khttp::date::{
+    get_date_from_secs, get_date_value_from_secs, parse_http_date, parse_http_date_to_system_time,
+    secs_to_system_time, system_time_to_http_date, system_time_to_secs,
+};
+use std::time::{Duration, UNIX_EPOCH};
 
 const DATE_LEN: usize = 37;
 fn to_string(bytes: [u8; DATE_LEN]) -> String {
@@ -58,3 +62,107 @@ fn http_date_known_vectors() {
         assert!(got.ends_with(" GMT\r\n"), "missing GMT suffix for {secs}");
     }
 }
+
+#[test]
+fn http_date_value_roundtrip() {
+    for secs in [
+        -2208988800,
+        -1,
+        0,
+        784111777,
+        951827696,
+        1754956800,
+        4102444799,
+    ] {
+        let value = get_date_value_from_secs(secs);
+        assert_eq!(&value[..], &get_date_from_secs(secs)[6..35]);
+        assert_eq!(parse_http_date(&value), Some(secs), "roundtrip {secs}");
+    }
+    // every day (at a shifting time of day) from 1900 to 2400
+    for secs in (-2208988800..13574649600).step_by(86_399) {
+        let value = get_date_value_from_secs(secs);
+        assert_eq!(parse_http_date(&value), Some(secs), "roundtrip {secs}");
+    }
+}
+
+#[test]
+fn parse_http_date_formats() {
+    const NOV_6_1994: i64 = 784111777;
+    let cases: &[&str] = &[
+        "Sun, 06 Nov 1994 08:49:37 GMT",
+        "  Sun, 06 Nov 1994 08:49:37 GMT ",
+        "Sunday, 06-Nov-94 08:49:37 GMT",
+        "Sun Nov  6 08:49:37 1994",
+        "Sun Nov 06 08:49:37 1994",
+    ];
+    for value in cases {
+        assert_eq!(
+            parse_http_date(value.as_bytes()),
+            Some(NOV_6_1994),
+            "{value}"
+        );
+    }
+}
+
+#[test]
+fn parse_http_date_rfc850_two_digit_year() {
+    assert_eq!(
+        parse_http_date(b"Friday, 31-Dec-99 23:59:59 GMT"),
+        Some(946684799)
+    );
+    assert_eq!(
+        parse_http_date(b"Saturday, 01-Jan-00 00:00:00 GMT"),
+        Some(946684800)
+    );
+}
+
+#[test]
+fn parse_http_date_invalid() {
+    let cases: &[&str] = &[
+        "",
+        "garbage",
+        "Sun, 06 Nov 1994 08:49:37 UTC",
+        "Sun, 06 Nov 1994 08:49:37",
+        "Sun, 6 Nov 1994 08:49:37 GMT",
+        "Sun, 06 Foo 1994 08:49:37 GMT",
+        "Xyz, 06 Nov 1994 08:49:37 GMT",
+        "Sun, 31 Nov 1994 08:49:37 GMT",
+        "Sun, 29 Feb 1900 08:49:37 GMT",
+        "Sun, 06 Nov 1994 24:00:00 GMT",
+        "Sun, 06 Nov 1994 08:60:00 GMT",
+        "Sun, 06 Nov 199a 08:49:37 GMT",
+        "Sun, 06 Nov 1994 08-49-37 GMT",
+        "Sunday, 06-Nov-1994 08:49:37 GMT",
+        "Sundae, 06-Nov-94 08:49:37 GMT",
+        "Sun Nov  6 08:49:37 94",
+    ];
+    for value in cases {
+        assert_eq!(parse_http_date(value.as_bytes()), None, "{value:?}");
+    }
+}
+
+#[test]
+fn http_date_system_time() {
+    let time = UNIX_EPOCH + Duration::from_millis(784_111_777_900);
+    assert_eq!(
+        &system_time_to_http_date(time),
+        b"Sun, 06 Nov 1994 08:49:37 GMT"
+    );
+    assert_eq!(
+        parse_http_date_to_system_time(b"Sun, 06 Nov 1994 08:49:37 GMT"),
+        Some(UNIX_EPOCH + Duration::from_secs(784111777))
+    );
+
+    assert_eq!(
+        system_time_to_secs(UNIX_EPOCH - Duration::from_millis(500)),
+        -1
+    );
+    assert_eq!(
+        secs_to_system_time(-60),
+        UNIX_EPOCH - Duration::from_secs(60)
+    );
+    assert_eq!(
+        system_time_to_secs(secs_to_system_time(1754956800)),
+        1754956800
+    );
+}
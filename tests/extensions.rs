@@ -0,0 +1,117 @@
+use khttp::middleware::from_fn;
+use khttp::{Extensions, Headers, Method::*, PreRoutingAction, Server, ServerBuilder};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// EXTENSIONS
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_extensions_type_map() {
+    let mut ext = Extensions::new();
+    assert!(ext.is_empty());
+    assert_eq!(ext.insert(1u32), None);
+    assert_eq!(ext.insert("a"), None);
+    assert_eq!(ext.insert(2u32), Some(1));
+    assert_eq!(ext.len(), 2);
+
+    assert_eq!(ext.get::<u32>(), Some(&2));
+    assert_eq!(ext.get::<&str>(), Some(&"a"));
+    assert_eq!(ext.get::<u64>(), None);
+
+    *ext.get_mut::<u32>().unwrap() += 1;
+    assert_eq!(ext.remove::<u32>(), Some(3));
+    assert!(!ext.contains::<u32>());
+    assert!(ext.contains::<&str>());
+    ext.clear();
+    assert!(ext.is_empty());
+}
+
+// ---------------------------------------------------------------------
+// SERVER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_app_state() {
+    let response = exchange(
+        |app| {
+            app.state(Config { greeting: "hello" });
+            app.state(AtomicUsize::new(0));
+            app.route(Get, "/", |ctx, res| {
+                let config = ctx.state::<Config>().unwrap();
+                let hits = ctx.state::<AtomicUsize>().unwrap();
+                let n = hits.fetch_add(1, Ordering::Relaxed) + 1;
+                let missing = ctx.state::<String>().is_none();
+                res.ok(
+                    Headers::empty(),
+                    format!("{} {n} {missing}", config.greeting),
+                )
+            });
+        },
+        "GET /",
+    );
+    assert!(response.ends_with("hello 1 true"), "{response}");
+}
+
+#[test]
+fn test_request_extensions_from_hooks_and_middleware() {
+    let response = exchange(
+        |app| {
+            app.pre_routing_hook(|_, _, extensions, _| {
+                extensions.insert(RequestId(7));
+                PreRoutingAction::Proceed
+            });
+            app.middleware(from_fn(|mut ctx, res, next| {
+                let user = ctx.headers.get("x-user").map(|u| User(u.to_vec()));
+                if let Some(user) = user {
+                    ctx.extensions_mut().insert(user);
+                }
+                next.run(ctx, res)
+            }));
+            app.route(Get, "/", |ctx, res| {
+                let id = ctx.extensions().get::<RequestId>().unwrap().0;
+                let user = match ctx.extensions().get::<User>() {
+                    Some(User(name)) => String::from_utf8_lossy(name).into_owned(),
+                    None => "anonymous".into(),
+                };
+                res.ok(Headers::empty(), format!("request {id} by {user}"))
+            });
+        },
+        "GET /",
+    );
+    assert!(response.ends_with("request 7 by anonymous"), "{response}");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+struct Config {
+    greeting: &'static str,
+}
+
+struct RequestId(u64);
+
+struct User(Vec<u8>);
+
+fn exchange(configure: fn(&mut ServerBuilder), request_line: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut app = Server::builder("127.0.0.1:0").unwrap();
+        configure(&mut app);
+        let _ = app.build().handle(&stream);
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("{request_line} HTTP/1.1\r\nconnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
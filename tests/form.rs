@@ -0,0 +1,318 @@
+use khttp::form::{multipart_boundary, FormLimits, Multipart, UrlEncodedForm};
+use khttp::{Headers, Method::*, Server};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+const BOUNDARY: &str = "----khttpFormBoundary7MA4YWxk";
+
+#[test]
+fn test_multipart_parts() {
+    let body = sample_body();
+    let mut form = Multipart::new(&body[..], BOUNDARY, FormLimits::default());
+
+    let parts = collect_parts(&mut form).unwrap();
+    assert_eq!(
+        parts,
+        vec![
+            part("title", None, None, "hello world"),
+            part("empty", None, None, ""),
+            part(
+                "upload",
+                Some("a \"b\".txt"),
+                Some("text/plain"),
+                "line 1\r\n--not-a-boundary\r\nline 3"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_multipart_byte_by_byte() {
+    let body = sample_body();
+    let mut form = Multipart::new(OneByteReader(&body), BOUNDARY, FormLimits::default());
+
+    let parts = collect_parts(&mut form).unwrap();
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[2].3, "line 1\r\n--not-a-boundary\r\nline 3");
+}
+
+#[test]
+fn test_multipart_large_file_streamed() {
+    let file: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let body = [
+        format!(
+            "--{BOUNDARY}\r\ncontent-disposition: form-data; name=\"f\"; filename=\"x.bin\"\r\n\r\n"
+        )
+        .as_bytes(),
+        &file,
+        format!("\r\n--{BOUNDARY}--\r\n").as_bytes(),
+    ]
+    .concat();
+    let mut form = Multipart::new(&body[..], BOUNDARY, FormLimits::default());
+
+    let mut part = form.next_part().unwrap().unwrap();
+    assert_eq!(part.filename(), Some("x.bin"));
+    assert_eq!(part.vec().unwrap(), file);
+    assert!(form.next_part().unwrap().is_none());
+}
+
+#[test]
+fn test_multipart_skip_unread_parts() {
+    let body = sample_body();
+    let mut form = Multipart::new(&body[..], BOUNDARY, FormLimits::default());
+
+    let mut names = Vec::new();
+    while let Some(part) = form.next_part().unwrap() {
+        names.push(part.name().unwrap().to_string());
+    }
+    assert_eq!(names, ["title", "empty", "upload"]);
+    assert!(form.next_part().unwrap().is_none());
+}
+
+#[test]
+fn test_multipart_preamble_and_epilogue() {
+    let body = format!(
+        "preamble, ignored\r\n--{BOUNDARY}  \r\ncontent-disposition: form-data; name=a\r\n\r\n1\r\n--{BOUNDARY}--\r\nepilogue"
+    );
+    let mut form = Multipart::new(body.as_bytes(), BOUNDARY, FormLimits::default());
+
+    let parts = collect_parts(&mut form).unwrap();
+    assert_eq!(parts, vec![part("a", None, None, "1")]);
+}
+
+#[test]
+fn test_multipart_filename_ext() {
+    let body = format!(
+        "--{BOUNDARY}\r\ncontent-disposition: form-data; name=\"f\"; filename=\"fallback.txt\"; filename*=UTF-8''na%C3%AFve.txt\r\n\r\nx\r\n--{BOUNDARY}--"
+    );
+    let mut form = Multipart::new(body.as_bytes(), BOUNDARY, FormLimits::default());
+
+    let part = form.next_part().unwrap().unwrap();
+    assert_eq!(part.filename(), Some("naïve.txt"));
+}
+
+#[test]
+fn test_multipart_limits() {
+    let body = sample_body();
+
+    let limits = FormLimits::new().max_parts(2);
+    let err = collect_parts(&mut Multipart::new(&body[..], BOUNDARY, limits)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let limits = FormLimits::new().max_field_size(5);
+    let err = collect_parts(&mut Multipart::new(&body[..], BOUNDARY, limits)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let limits = FormLimits::new().max_file_size(5);
+    let err = collect_parts(&mut Multipart::new(&body[..], BOUNDARY, limits)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let limits = FormLimits::new().max_part_header_size(16);
+    let err = collect_parts(&mut Multipart::new(&body[..], BOUNDARY, limits)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_multipart_malformed() {
+    let truncated = format!("--{BOUNDARY}\r\ncontent-disposition: form-data; name=a\r\n\r\nabc");
+    let mut form = Multipart::new(truncated.as_bytes(), BOUNDARY, FormLimits::default());
+    let err = collect_parts(&mut form).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let bad_header = format!("--{BOUNDARY}\r\nno colon here\r\n\r\nabc\r\n--{BOUNDARY}--");
+    let mut form = Multipart::new(bad_header.as_bytes(), BOUNDARY, FormLimits::default());
+    let err = collect_parts(&mut form).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_multipart_boundary() {
+    let headers = content_type(&format!("multipart/form-data; boundary={BOUNDARY}"));
+    assert_eq!(multipart_boundary(&headers).as_deref(), Some(BOUNDARY));
+
+    let headers = content_type("Multipart/Form-Data; charset=utf-8; boundary=\"a b;c\"");
+    assert_eq!(multipart_boundary(&headers).as_deref(), Some("a b;c"));
+
+    for value in [
+        "multipart/mixed; boundary=x",
+        "multipart/form-data",
+        "multipart/form-data; boundary=\"\"",
+        "text/plain",
+    ] {
+        assert_eq!(multipart_boundary(&content_type(value)), None, "{value}");
+    }
+}
+
+// ---------------------------------------------------------------------
+// URLENCODED
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_urlencoded() {
+    let form = UrlEncodedForm::read(
+        &b"name=J%C3%BCrgen+K&tags=a&tags=b"[..],
+        FormLimits::default(),
+    )
+    .unwrap();
+
+    assert_eq!(form.get("name").as_deref(), Some("Jürgen K"));
+    assert_eq!(form.pairs().get_all("tags").collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(form.get("missing"), None);
+}
+
+#[test]
+fn test_urlencoded_limits() {
+    let body = "a=1&b=2&c=3";
+
+    let limits = FormLimits::new().max_urlencoded_size(5);
+    let err = UrlEncodedForm::read(body.as_bytes(), limits).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let limits = FormLimits::new().max_parts(2);
+    let err = UrlEncodedForm::read(body.as_bytes(), limits).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let err = UrlEncodedForm::read(&b"a=\xff"[..], FormLimits::default())
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_request_context_forms() {
+    let body = sample_body();
+    let request = [
+        format!(
+            "POST /upload HTTP/1.1\r\nconnection: close\r\ncontent-type: multipart/form-data; boundary={BOUNDARY}\r\ncontent-length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes(),
+        body,
+    ]
+    .concat();
+    assert_eq!(
+        exchange(&request),
+        "title=11 empty=0 upload=32(a \"b\".txt)"
+    );
+
+    let request = b"POST /login HTTP/1.1\r\nconnection: close\r\ncontent-type: application/x-www-form-urlencoded\r\ncontent-length: 18\r\n\r\nuser=me&pass=a%26b";
+    assert_eq!(exchange(request), "me a&b");
+
+    let request = b"POST /login HTTP/1.1\r\nconnection: close\r\ncontent-type: text/plain\r\ncontent-length: 3\r\n\r\na=b";
+    assert_eq!(exchange(request), "not a form");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+type PartSummary = (String, Option<String>, Option<String>, String);
+
+fn part(name: &str, filename: Option<&str>, ct: Option<&str>, body: &str) -> PartSummary {
+    (
+        name.to_string(),
+        filename.map(str::to_string),
+        ct.map(str::to_string),
+        body.to_string(),
+    )
+}
+
+fn collect_parts<R: Read>(form: &mut Multipart<R>) -> io::Result<Vec<PartSummary>> {
+    let mut parts = Vec::new();
+    while let Some(mut part) = form.next_part()? {
+        let body = part.string()?;
+        parts.push((
+            part.name().unwrap_or_default().to_string(),
+            part.filename().map(str::to_string),
+            part.content_type().map(str::to_string),
+            body,
+        ));
+    }
+    Ok(parts)
+}
+
+fn sample_body() -> Vec<u8> {
+    format!(
+        "--{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+         hello world\r\n\
+         --{BOUNDARY}\r\n\
+         content-disposition: form-data; name=empty\r\n\r\n\
+         \r\n\
+         --{BOUNDARY}\r\n\
+         content-disposition: form-data; name=\"upload\"; filename=\"a \\\"b\\\".txt\"\r\n\
+         content-type: text/plain\r\n\r\n\
+         line 1\r\n--not-a-boundary\r\nline 3\r\n\
+         --{BOUNDARY}--\r\n"
+    )
+    .into_bytes()
+}
+
+fn content_type(value: &str) -> Headers<'static> {
+    let mut headers = Headers::new();
+    headers.add(Headers::CONTENT_TYPE, value.as_bytes().to_vec());
+    headers
+}
+
+struct OneByteReader<'a>(&'a [u8]);
+
+impl Read for OneByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((&b, rest)), Some(out)) => {
+                *out = b;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+fn build_server() -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.route(Post, "/upload", |mut ctx, res| {
+        let mut form = ctx.multipart(FormLimits::default())?;
+        let mut summary = Vec::new();
+        while let Some(mut part) = form.next_part()? {
+            let len = part.vec()?.len();
+            let name = part.name().unwrap_or_default().to_string();
+            match part.filename() {
+                Some(f) => summary.push(format!("{name}={len}({f})")),
+                None => summary.push(format!("{name}={len}")),
+            }
+        }
+        res.ok(Headers::empty(), summary.join(" "))
+    });
+    app.route(Post, "/login", |mut ctx, res| {
+        let form = match ctx.urlencoded_form(FormLimits::default()) {
+            Ok(form) => form,
+            Err(e) if e.kind() == ErrorKind::InvalidInput => {
+                return res.ok(Headers::empty(), "not a form")
+            }
+            Err(e) => return Err(e),
+        };
+        let user = form.get("user").unwrap_or_default();
+        let pass = form.get("pass").unwrap_or_default();
+        res.ok(Headers::empty(), format!("{user} {pass}"))
+    });
+    app.build()
+}
+
+fn exchange(request: &[u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build_server().handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+
+    raw.split_once("\r\n\r\n").unwrap().1.to_string()
+}
@@ -0,0 +1,389 @@
+use khttp::fs::{mime_type, StaticFiles};
+use khttp::{Method::*, Server};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{fs, thread};
+
+#[test]
+fn test_serve_file() {
+    let root = fixture_dir();
+    let res = get(&root, "/static/hello.txt", "");
+
+    assert_eq!(res.status, 200);
+    assert_eq!(
+        res.header("content-type"),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(res.header("content-length"), Some("11"));
+    assert_eq!(res.header("accept-ranges"), Some("bytes"));
+    assert!(res.header("etag").is_some());
+    assert!(res
+        .header("last-modified")
+        .is_some_and(|v| v.ends_with(" GMT")));
+    assert_eq!(res.body, b"hello world");
+}
+
+#[test]
+fn test_head_request() {
+    let root = fixture_dir();
+    let res = exchange(
+        &root,
+        b"HEAD /static/hello.txt HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+
+    assert_eq!(res.status, 200);
+    assert_eq!(res.header("content-length"), Some("11"));
+    assert!(res.body.is_empty());
+}
+
+#[test]
+fn test_method_not_allowed() {
+    let root = fixture_dir();
+    let res = exchange(
+        &root,
+        b"POST /static/hello.txt HTTP/1.1\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+    );
+
+    assert_eq!(res.status, 405);
+    assert_eq!(res.header("allow"), Some("GET, HEAD"));
+}
+
+#[test]
+fn test_traversal_rejected() {
+    let root = fixture_dir();
+    fs::write(root.parent().unwrap().join("secret.txt"), "secret").unwrap();
+
+    for uri in [
+        "/static/../secret.txt",
+        "/static/%2e%2e/secret.txt",
+        "/static/sub/%2E%2E/%2e%2e/secret.txt",
+        "/static/..%2fsecret.txt",
+        "/static/..%5csecret.txt",
+        "/static/hello.txt%00",
+    ] {
+        // rejected by path normalization (400) or by StaticFiles itself (404)
+        let res = get(&root, uri, "");
+        assert!(matches!(res.status, 400 | 404), "{uri}: {}", res.status);
+    }
+}
+
+#[test]
+fn test_non_normalized_path() {
+    let root = fixture_dir();
+
+    for uri in [
+        "//static/hello.txt",
+        "/x/../static/hello.txt",
+        "/static/./sub/../hello.txt",
+        "/%73tatic/hello.txt",
+    ] {
+        let res = get(&root, uri, "");
+        assert_eq!(res.status, 200, "{uri}");
+        assert_eq!(res.body, b"hello world", "{uri}");
+    }
+
+    let res = get(&root, "//static//sub", "");
+    assert_eq!(res.status, 301);
+    assert_eq!(res.header("location"), Some("/static/sub/"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_outside_root_rejected() {
+    let root = fixture_dir();
+    let outside = root.parent().unwrap().join("outside.txt");
+    fs::write(&outside, "outside").unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("link.txt")).unwrap();
+
+    assert_eq!(get(&root, "/static/link.txt", "").status, 404);
+}
+
+#[test]
+fn test_directory_redirect_and_index() {
+    let root = fixture_dir();
+
+    let res = get(&root, "/static/sub?x=1", "");
+    assert_eq!(res.status, 301);
+    assert_eq!(res.header("location"), Some("/static/sub/?x=1"));
+
+    let res = get(&root, "/static/sub/", "");
+    assert_eq!(res.status, 200);
+    assert_eq!(res.header("content-type"), Some("text/html; charset=utf-8"));
+    assert_eq!(res.body, b"<h1>index</h1>");
+}
+
+#[test]
+fn test_directory_listing() {
+    let root = fixture_dir();
+    fs::create_dir(root.join("list")).unwrap();
+    fs::write(root.join("list").join("<b>&.txt"), "x").unwrap();
+    fs::create_dir(root.join("list").join("nested")).unwrap();
+
+    let res = get(&root, "/static/list/", "");
+    assert_eq!(res.status, 404);
+
+    let res = get(&root, "/files/list/", "");
+    let body = String::from_utf8(res.body).unwrap();
+    assert_eq!(res.status, 200);
+    assert!(body.contains("&lt;b&gt;&amp;.txt"));
+    assert!(!body.contains("<b>"));
+    assert!(body.contains("href=\"nested/\""));
+}
+
+#[test]
+fn test_conditional_requests() {
+    let root = fixture_dir();
+    let res = get(&root, "/static/hello.txt", "");
+    let etag = res.header("etag").unwrap().to_string();
+    let last_modified = res.header("last-modified").unwrap().to_string();
+
+    let res = get(
+        &root,
+        "/static/hello.txt",
+        &format!("if-none-match: {etag}\r\n"),
+    );
+    assert_eq!(res.status, 304);
+    assert!(res.body.is_empty());
+
+    let res = get(
+        &root,
+        "/static/hello.txt",
+        &format!("if-none-match: \"other\", W/{etag}\r\n"),
+    );
+    assert_eq!(res.status, 304);
+
+    let res = get(&root, "/static/hello.txt", "if-none-match: *\r\n");
+    assert_eq!(res.status, 304);
+
+    // a present but malformed if-none-match still takes precedence over if-modified-since
+    for condition in ["\"other\"", "not-quoted"] {
+        let res = get(
+            &root,
+            "/static/hello.txt",
+            &format!("if-none-match: {condition}\r\nif-modified-since: {last_modified}\r\n"),
+        );
+        assert_eq!(res.status, 200, "{condition}");
+    }
+
+    let res = get(
+        &root,
+        "/static/hello.txt",
+        &format!("if-modified-since: {last_modified}\r\n"),
+    );
+    assert_eq!(res.status, 304);
+
+    let res = get(
+        &root,
+        "/static/hello.txt",
+        "if-modified-since: Fri, 31 Dec 9999 23:59:59 GMT\r\n",
+    );
+    assert_eq!(res.status, 304);
+
+    for since in ["Sunday, 06-Nov-94 08:49:37 GMT", "not a date"] {
+        let res = get(
+            &root,
+            "/static/hello.txt",
+            &format!("if-modified-since: {since}\r\n"),
+        );
+        assert_eq!(res.status, 200, "{since}");
+    }
+}
+
+#[test]
+fn test_single_range() {
+    let root = fixture_dir();
+
+    let res = get(&root, "/static/hello.txt", "range: bytes=6-\r\n");
+    assert_eq!(res.status, 206);
+    assert_eq!(res.header("content-range"), Some("bytes 6-10/11"));
+    assert_eq!(res.body, b"world");
+
+    let res = get(&root, "/static/hello.txt", "range: bytes=-3\r\n");
+    assert_eq!(res.status, 206);
+    assert_eq!(res.body, b"rld");
+
+    let res = get(&root, "/static/hello.txt", "range: bytes=0-100\r\n");
+    assert_eq!(res.status, 206);
+    assert_eq!(res.header("content-range"), Some("bytes 0-10/11"));
+    assert_eq!(res.body, b"hello world");
+}
+
+#[test]
+fn test_multiple_ranges() {
+    let root = fixture_dir();
+    let res = get(&root, "/static/hello.txt", "range: bytes=0-1, 6-7\r\n");
+
+    assert_eq!(res.status, 206);
+    assert_eq!(
+        res.header("content-type"),
+        Some("multipart/byteranges; boundary=khttp-byteranges-boundary")
+    );
+    assert_eq!(
+        res.header("content-length"),
+        Some(&*res.body.len().to_string())
+    );
+    let body = String::from_utf8(res.body).unwrap();
+    assert_eq!(
+        body,
+        "--khttp-byteranges-boundary\r\n\
+         content-type: text/plain; charset=utf-8\r\n\
+         content-range: bytes 0-1/11\r\n\r\n\
+         he\r\n\
+         --khttp-byteranges-boundary\r\n\
+         content-type: text/plain; charset=utf-8\r\n\
+         content-range: bytes 6-7/11\r\n\r\n\
+         wo\r\n\
+         --khttp-byteranges-boundary--\r\n"
+    );
+}
+
+#[test]
+fn test_unsatisfiable_and_malformed_ranges() {
+    let root = fixture_dir();
+
+    let res = get(&root, "/static/hello.txt", "range: bytes=100-200\r\n");
+    assert_eq!(res.status, 416);
+    assert_eq!(res.header("content-range"), Some("bytes */11"));
+
+    for range in ["bytes=5-1", "bytes=abc", "items=0-1"] {
+        let res = get(&root, "/static/hello.txt", &format!("range: {range}\r\n"));
+        assert_eq!(res.status, 200, "{range}");
+        assert_eq!(res.body, b"hello world");
+    }
+}
+
+#[test]
+fn test_precompressed_gzip() {
+    let root = fixture_dir();
+    fs::write(root.join("app.js"), "console.log(1)").unwrap();
+    fs::write(root.join("app.js.gz"), b"\x1f\x8bfake-gzip").unwrap();
+
+    let res = get(&root, "/static/app.js", "accept-encoding: br, gzip\r\n");
+    assert_eq!(res.header("content-encoding"), Some("gzip"));
+    assert_eq!(
+        res.header("content-type"),
+        Some("text/javascript; charset=utf-8")
+    );
+    assert_eq!(res.header("vary"), Some("accept-encoding"));
+    assert_eq!(res.body, b"\x1f\x8bfake-gzip");
+
+    let res = get(&root, "/static/app.js", "accept-encoding: GZIP;q=0.5\r\n");
+    assert_eq!(res.header("content-encoding"), Some("gzip"));
+
+    for accept in [
+        "gzip;q=0",
+        "gzip;q=0.000",
+        "*;q=0",
+        "gzip;q=2",
+        "gzip;q=abc",
+    ] {
+        let res = get(
+            &root,
+            "/static/app.js",
+            &format!("accept-encoding: {accept}\r\n"),
+        );
+        assert_eq!(res.header("content-encoding"), None, "{accept}");
+        assert_eq!(res.header("vary"), Some("accept-encoding"));
+        assert_eq!(res.body, b"console.log(1)");
+    }
+}
+
+#[test]
+fn test_mime_type() {
+    assert_eq!(mime_type(Path::new("a/b.HTML")), "text/html; charset=utf-8");
+    assert_eq!(mime_type(Path::new("font.woff2")), "font/woff2");
+    assert_eq!(mime_type(Path::new("noext")), "application/octet-stream");
+    assert_eq!(
+        mime_type(Path::new("x.unknown")),
+        "application/octet-stream"
+    );
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+/// Creates `<tmp>/khttp-fs-test-<pid>-<n>/root` with a few files.
+fn fixture_dir() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let base = std::env::temp_dir().join(format!("khttp-fs-test-{}-{n}", std::process::id()));
+    let _ = fs::remove_dir_all(&base);
+    let root = base.join("root");
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(root.join("hello.txt"), "hello world").unwrap();
+    fs::write(root.join("sub").join("index.html"), "<h1>index</h1>").unwrap();
+    root
+}
+
+fn build_server(root: &Path) -> Server {
+    let files = Arc::new(StaticFiles::new(root).unwrap().prefix("/static"));
+    let listing = StaticFiles::new(root)
+        .unwrap()
+        .prefix("/files")
+        .directory_listing(true);
+
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    for method in [Get, Head, Post] {
+        let files = files.clone();
+        app.route(method, "/static/**", move |ctx, res| files.serve(ctx, res));
+    }
+    app.route(Get, "/files/**", move |ctx, res| listing.serve(ctx, res));
+    app.build()
+}
+
+struct RawResponse {
+    status: u16,
+    head: String,
+    body: Vec<u8>,
+}
+
+impl RawResponse {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            k.eq_ignore_ascii_case(name).then(|| v.trim())
+        })
+    }
+}
+
+fn get(root: &Path, uri: &str, extra_headers: &str) -> RawResponse {
+    let request = format!("GET {uri} HTTP/1.1\r\nconnection: close\r\n{extra_headers}\r\n");
+    exchange(root, request.as_bytes())
+}
+
+fn exchange(root: &Path, request: &[u8]) -> RawResponse {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let root = root.to_path_buf();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build_server(&root).handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request).unwrap();
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).unwrap();
+    server.join().unwrap();
+
+    let head_end = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8(raw[..head_end].to_vec()).unwrap();
+    let status = head[9..12].parse().unwrap();
+    let rest = &raw[head_end + 4..];
+
+    let mut response = RawResponse {
+        status,
+        head,
+        body: Vec::new(),
+    };
+    response.body = match response.header("transfer-encoding") {
+        Some("chunked") => khttp::BodyReader::new_chunked(&[], rest).vec().unwrap(),
+        _ => rest.to_vec(),
+    };
+    response
+}
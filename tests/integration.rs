@@ -1,11 +1,17 @@
 #![cfg(feature = "client")]
-use khttp::{Client, ClientResponseHandle, ConnectionSetupAction, Headers, Method, Server, Status};
+use khttp::{
+    Client, ClientResponseHandle, ConnectionSetupAction, Extensions, Headers, Method, Server,
+    Status,
+};
 use std::io;
 use std::net::SocketAddr;
 use std::{
     io::Cursor,
     net::TcpStream,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     thread::{self},
     time::Duration,
 };
@@ -44,6 +50,79 @@ fn test_serve_epoll() {
     handle.join().unwrap();
 }
 
+#[cfg(feature = "epoll")]
+#[test]
+fn test_serve_epoll_connection_teardown() {
+    const TEST_PORT: u16 = 32737;
+    let torn_down = Arc::new(AtomicU64::new(0));
+    let seen = torn_down.clone();
+    let handle = thread::spawn(move || {
+        let mut app = Server::builder(format!("127.0.0.1:{TEST_PORT}")).unwrap();
+        app.route(Method::Get, "/hello", |_, res| {
+            res.ok(Headers::empty(), &b"Hello, World!"[..])
+        });
+        app.connection_setup_hook(request_limiter(Arc::new(AtomicU64::new(0)), 3));
+        app.connection_teardown_hook(move |_conn, state, _io_result| {
+            // one bit per connection, so each state must arrive exactly once
+            let ConnectionNumber(n) = state.get().unwrap();
+            seen.fetch_add(1 << n, Ordering::SeqCst);
+        });
+        app.build().serve_epoll().unwrap()
+    });
+    thread::sleep(Duration::from_millis(10));
+
+    let mut client = Client::new(format!("localhost:{TEST_PORT}"));
+    for _ in 0..3 {
+        let response = client.get("/hello", Headers::empty()).unwrap();
+        assert_status_and_body(response, 200, "Hello, World!");
+    }
+    TcpStream::connect(("127.0.0.1", TEST_PORT)).expect("should close server");
+    handle.join().unwrap();
+
+    // teardown runs on the workers, after the response went out
+    for _ in 0..100 {
+        if torn_down.load(Ordering::SeqCst) == 0b111 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(torn_down.load(Ordering::SeqCst), 0b111);
+}
+
+#[cfg(feature = "epoll")]
+#[test]
+fn test_serve_epoll_teardown_on_setup_failure() {
+    use std::os::fd::OwnedFd;
+    use std::sync::atomic::AtomicBool;
+    const TEST_PORT: u16 = 32738;
+    let torn_down = Arc::new(AtomicBool::new(false));
+    let seen = torn_down.clone();
+    let handle = thread::spawn(move || {
+        let mut app = Server::builder(format!("127.0.0.1:{TEST_PORT}")).unwrap();
+        let limiter = request_limiter(Arc::new(AtomicU64::new(0)), 1);
+        app.connection_setup_hook(move |conn| match limiter(conn) {
+            // a listening socket has no peer, so the connection fails before it's served
+            ConnectionSetupAction::ProceedWith(_, state) => {
+                let unconnected = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+                ConnectionSetupAction::ProceedWith(OwnedFd::from(unconnected).into(), state)
+            }
+            action => action,
+        });
+        app.connection_teardown_hook(move |_conn, state, io_result| {
+            assert!(io_result.is_err());
+            assert!(state.contains::<ConnectionNumber>());
+            seen.store(true, Ordering::SeqCst);
+        });
+        app.build().serve_epoll().unwrap()
+    });
+    thread::sleep(Duration::from_millis(10));
+
+    TcpStream::connect(("127.0.0.1", TEST_PORT)).unwrap();
+    TcpStream::connect(("127.0.0.1", TEST_PORT)).expect("should close server");
+    handle.join().unwrap();
+    assert!(torn_down.load(Ordering::SeqCst));
+}
+
 // ---------------------------------------------------------------------
 // server & client
 // ---------------------------------------------------------------------
@@ -79,12 +158,18 @@ fn build_server(port: u16) -> khttp::Server {
         res.send(&Status::of(200), Headers::empty(), body.as_bytes())
     });
 
+    app.route(Method::Get, "/connection", |mut ctx, res| {
+        let ConnectionNumber(n) = ctx.connection_state().get().unwrap();
+        res.ok(Headers::empty(), format!("connection {n}"))
+    });
+
     let counter = Arc::new(AtomicU64::new(0));
-    app.connection_setup_hook(request_limiter(counter, 6));
-    app.connection_teardown_hook(|_conn, io_result| {
+    app.connection_setup_hook(request_limiter(counter, 7));
+    app.connection_teardown_hook(|_conn, state, io_result| {
         if let Some(e) = io_result.err() {
             panic!("socket error: {e}");
         }
+        assert!(state.contains::<ConnectionNumber>());
     });
     app.build()
 }
@@ -117,12 +202,17 @@ fn run_test_requests(port: u16) {
         .post("/upload/chunked", Headers::empty(), "hello123".as_bytes())
         .unwrap();
     assert_status_and_body(response, 200, "got: hello123");
+
+    let response = client.get("/connection", Headers::empty()).unwrap();
+    assert_status_and_body(response, 200, "connection 6");
 }
 
 // ---------------------------------------------------------------------
 // UTILS
 // ---------------------------------------------------------------------
 
+struct ConnectionNumber(u64);
+
 fn request_limiter(
     counter: Arc<AtomicU64>,
     n: u64,
@@ -130,11 +220,13 @@ fn request_limiter(
     let counter = counter.clone();
     move |stream| match stream {
         Ok((stream, _peer_addr)) => {
-            let seen = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let seen = counter.fetch_add(1, Ordering::SeqCst);
             if seen < n {
                 let _ = stream.set_read_timeout(Some(Duration::from_millis(500)));
                 let _ = stream.set_write_timeout(Some(Duration::from_millis(500)));
-                ConnectionSetupAction::Proceed(stream)
+                let mut state = Extensions::new();
+                state.insert(ConnectionNumber(seen));
+                ConnectionSetupAction::ProceedWith(stream, state)
             } else {
                 ConnectionSetupAction::StopAccepting
             }
@@ -0,0 +1,157 @@
+use khttp::{
+    Headers, HttpParsingError::*, ParseStatus, Request, RequestLimits, RequestParser, Server,
+    ServerBuilder,
+};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// PARSER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_invalid_header_value_bytes_rejected() {
+    for value in ["a\0b", "a\rb", "\x01", "a\x7fb", "a\x1bb"] {
+        let input = format!("GET / HTTP/1.1\r\nx: {value}\r\n\r\n");
+        for result in [
+            Request::parse(input.as_bytes()),
+            Request::parse_lenient(input.as_bytes()),
+        ] {
+            assert_eq!(result.unwrap_err(), InvalidHeaderValue, "{value:?}");
+        }
+    }
+
+    // HTAB, SP, VCHAR and obs-text are fine
+    let input = b"GET / HTTP/1.1\r\nx: a\tb c~\xe9\xff\r\n\r\n";
+    let req = Request::parse(input).unwrap();
+    assert_eq!(req.headers.get("x"), Some(&b"a\tb c~\xe9\xff"[..]));
+}
+
+#[test]
+fn test_max_headers() {
+    let limits = RequestLimits::new().max_headers(3);
+    let head = |n: usize| {
+        let headers: String = (0..n).map(|i| format!("x-{i}: {i}\r\n")).collect();
+        format!("GET / HTTP/1.1\r\n{headers}\r\n")
+    };
+    assert!(parse(limits, &head(3)).is_ok());
+    assert_eq!(parse(limits, &head(4)).unwrap_err(), TooManyHeaders);
+}
+
+#[test]
+fn test_max_header_size() {
+    let limits = RequestLimits::new().max_header_size(16);
+    assert!(parse(limits, "GET / HTTP/1.1\r\nx: 0123456789abc\r\n\r\n").is_ok());
+    assert_eq!(
+        parse(limits, "GET / HTTP/1.1\r\nx: 0123456789abcd\r\n\r\n").unwrap_err(),
+        HeaderTooLarge
+    );
+
+    // reported before the line is complete
+    let mut parser = RequestParser::new().limits(limits);
+    let buf = b"GET / HTTP/1.1\r\nx: 0123456789abc\r";
+    assert!(matches!(parser.parse(buf), Ok(ParseStatus::Partial(_))));
+    let buf = b"GET / HTTP/1.1\r\nx: 0123456789abcdef";
+    assert_eq!(parser.parse(buf).unwrap_err(), HeaderTooLarge);
+}
+
+#[test]
+fn test_max_uri_length() {
+    let limits = RequestLimits::new().max_uri_length(8);
+    assert!(parse(limits, "GET /1234567 HTTP/1.1\r\n\r\n").is_ok());
+    assert_eq!(
+        parse(limits, "GET /12345678 HTTP/1.1\r\n\r\n").unwrap_err(),
+        UriTooLong
+    );
+
+    // reported before the line is complete
+    let mut parser = RequestParser::new().limits(limits);
+    assert_eq!(parser.parse(b"GET /12345678").unwrap_err(), UriTooLong);
+}
+
+// ---------------------------------------------------------------------
+// SERVER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_server_limit_responses() {
+    let long = "a".repeat(100);
+    let many: String = (0..8).map(|i| format!("x-{i}: {i}\r\n")).collect();
+    let cases = [
+        (format!("GET /{long} HTTP/1.1\r\n\r\n"), "414"),
+        (format!("GET / HTTP/1.1\r\nx: {long}\r\n\r\n"), "431"),
+        (format!("GET / HTTP/1.1\r\n{many}\r\n"), "431"),
+        ("GET / HTTP/1.1\r\nx: \0\r\n\r\n".to_string(), "400"),
+        (
+            "GET / HTTP/1.1\r\nconnection: close\r\n\r\n".to_string(),
+            "200",
+        ),
+    ];
+    for (request, status) in cases {
+        let response = exchange(
+            |app| {
+                let limits = RequestLimits::new()
+                    .max_headers(4)
+                    .max_header_size(64)
+                    .max_uri_length(32);
+                app.request_limits(limits);
+            },
+            &request,
+        );
+        assert!(
+            response.starts_with(&format!("HTTP/1.1 {status}")),
+            "{request:?}: {response}"
+        );
+    }
+}
+
+#[test]
+fn test_server_request_line_over_head_size_is_414() {
+    // fills the head buffer exactly, so nothing is left unread when the server closes
+    let request = format!("GET /{}", "a".repeat(59));
+    let response = exchange(
+        |app| {
+            app.max_request_head_size(64);
+        },
+        &request,
+    );
+    assert!(response.starts_with("HTTP/1.1 414"), "{response}");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn parse(limits: RequestLimits, input: &str) -> Result<(), khttp::HttpParsingError> {
+    match RequestParser::new()
+        .limits(limits)
+        .parse(input.as_bytes())?
+    {
+        ParseStatus::Complete(_) => Ok(()),
+        ParseStatus::Partial(_) => Err(UnexpectedEof),
+    }
+}
+
+fn build_server(configure: fn(&mut ServerBuilder)) -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    configure(&mut app);
+    app.fallback_route(|_, res| res.ok(Headers::empty(), "ok"));
+    app.build()
+}
+
+fn exchange(configure: fn(&mut ServerBuilder), request: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let _ = build_server(configure).handle(&stream);
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
@@ -0,0 +1,179 @@
+use khttp::middleware::from_fn;
+use khttp::{
+    Headers, Method::*, Middleware, Next, PreRoutingAction, RequestContext, ResponseHandle, Server,
+    ServerBuilder, Status,
+};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// TESTS
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_middleware_order() {
+    let log = Log::default();
+    let mut app = builder();
+    app.middleware(log.tag("global"));
+    let route_stack: Vec<Arc<dyn Middleware>> = vec![Arc::new(log.tag("route"))];
+    app.scope("/api", |api| {
+        api.route_with(Get, "/a", &route_stack, |_, res| {
+            res.ok(Headers::empty(), "a")
+        });
+        // registered before the scope's middleware, still behind it
+        api.scope("/v1", |v1| {
+            v1.route(Get, "/b", |_, res| res.ok(Headers::empty(), "b"));
+            v1.middleware(log.tag("v1"));
+        });
+        api.middleware(log.tag("api"));
+    });
+    app.route(Get, "/c", |_, res| res.ok(Headers::empty(), "c"));
+    let server = app.build();
+
+    assert!(exchange(&server, "GET /api/a").ends_with("a"));
+    assert_eq!(
+        log.take(),
+        ["global", "api", "route", "/route", "/api", "/global"]
+    );
+
+    assert!(exchange(&server, "GET /api/v1/b").ends_with("b"));
+    assert_eq!(
+        log.take(),
+        ["global", "api", "v1", "/v1", "/api", "/global"]
+    );
+
+    assert!(exchange(&server, "GET /c").ends_with("c"));
+    assert_eq!(log.take(), ["global", "/global"]);
+
+    // fallback routes are covered too
+    assert!(exchange(&server, "GET /nope").starts_with("HTTP/1.1 404"));
+    assert_eq!(log.take(), ["global", "/global"]);
+}
+
+#[test]
+fn test_middleware_short_circuit() {
+    let mut app = builder();
+    app.middleware(Auth("secret"));
+    app.route(Get, "/", |ctx, res| {
+        res.ok(Headers::empty(), format!("hello {}", ctx.path()))
+    });
+    let server = app.build();
+
+    let response = exchange(&server, "GET /");
+    assert!(response.starts_with("HTTP/1.1 401"), "{response}");
+    let response = exchange_with(&server, "GET /", "authorization: secret\r\n");
+    assert!(response.ends_with("hello /"), "{response}");
+}
+
+#[test]
+fn test_middleware_sees_route_result() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let mut app = builder();
+    let seen = errors.clone();
+    app.middleware(from_fn(move |ctx, res, next| {
+        let path = ctx.path().to_string();
+        let result = next.run(ctx, res);
+        if let Err(e) = &result {
+            seen.lock().unwrap().push(format!("{path}: {e}"));
+            return res.send(&Status::of(500), Headers::close(), "internal error");
+        }
+        result
+    }));
+    app.route(Get, "/fail", |_, _| Err(io::Error::other("boom")));
+    let server = app.build();
+
+    let response = exchange(&server, "GET /fail");
+    assert!(response.starts_with("HTTP/1.1 500"), "{response}");
+    assert_eq!(*errors.lock().unwrap(), ["/fail: boom"]);
+}
+
+#[test]
+fn test_pre_routing_hooks_stack() {
+    let log = Log::default();
+    let mut app = builder();
+    for name in ["first", "second"] {
+        let log = log.clone();
+        app.pre_routing_hook(move |req, _, _, res| {
+            log.push(name);
+            if req.uri.path() == "/blocked" {
+                let _ = res.send0(&Status::of(403), Headers::close());
+                return PreRoutingAction::Drop;
+            }
+            PreRoutingAction::Proceed
+        });
+    }
+    app.route(Get, "/", |_, res| res.ok(Headers::empty(), "ok"));
+    let server = app.build();
+
+    assert!(exchange(&server, "GET /").ends_with("ok"));
+    assert_eq!(log.take(), ["first", "second"]);
+    assert!(exchange(&server, "GET /blocked").starts_with("HTTP/1.1 403"));
+    assert_eq!(log.take(), ["first"]);
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+struct Auth(&'static str);
+
+impl Middleware for Auth {
+    fn handle(&self, ctx: RequestContext, res: &mut ResponseHandle, next: Next) -> io::Result<()> {
+        match ctx.headers.get("authorization") {
+            Some(token) if token == self.0.as_bytes() => next.run(ctx, res),
+            _ => res.send0(&Status::of(401), Headers::empty()),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Log(Arc<Mutex<Vec<String>>>);
+
+impl Log {
+    fn push(&self, entry: &str) {
+        self.0.lock().unwrap().push(entry.to_string());
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+
+    /// Logs `name` before and `/name` after the rest of the chain.
+    fn tag(&self, name: &'static str) -> impl Middleware + 'static {
+        let log = self.clone();
+        from_fn(move |ctx, res, next| {
+            log.push(name);
+            let result = next.run(ctx, res);
+            log.push(&format!("/{name}"));
+            result
+        })
+    }
+}
+
+fn builder() -> ServerBuilder {
+    Server::builder("127.0.0.1:0").unwrap()
+}
+
+fn exchange(server: &Server, request_line: &str) -> String {
+    exchange_with(server, request_line, "")
+}
+
+fn exchange_with(server: &Server, request_line: &str, headers: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let request = format!("{request_line} HTTP/1.1\r\n{headers}connection: close\r\n\r\n");
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        raw
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let _ = server.handle(&stream);
+    drop(stream);
+    client.join().unwrap()
+}
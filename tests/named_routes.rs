@@ -0,0 +1,244 @@
+use khttp::{Headers, Method::*, Router, RouterBuilder, Server, Status, UrlError};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// ROUTER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_url_for() {
+    let router = build_router();
+    assert_eq!(router.url_for("home", &[]).unwrap(), "/");
+    assert_eq!(router.url_for("about", &[]).unwrap(), "/about");
+    assert_eq!(
+        router.url_for("user_profile", &[("id", "42")]).unwrap(),
+        "/users/42/profile"
+    );
+    assert_eq!(
+        router.url_for("report", &[("name", "q1")]).unwrap(),
+        "/reports/q1.json"
+    );
+    assert_eq!(
+        router
+            .url_for("comment", &[("post", "7"), ("comment", "3")])
+            .unwrap(),
+        "/api/posts/7/comments/3"
+    );
+
+    // the url routes back to the named route with the same params
+    let url = router
+        .url_for("comment", &[("comment", "c d"), ("post", "a/b")])
+        .unwrap();
+    assert_eq!(url, "/api/posts/a%2Fb/comments/c%20d");
+    let m = router.match_route(&Get, &url);
+    assert_eq!(*m.route, 5);
+    assert_eq!(m.params.get("post"), Some("a/b"));
+    assert_eq!(m.params.get("comment"), Some("c d"));
+}
+
+#[test]
+fn test_url_for_wildcards() {
+    let router = build_router();
+    assert_eq!(
+        router
+            .url_for("thumb", &[("*", "a b"), ("*", "small")])
+            .unwrap(),
+        "/thumbs/a%20b/small"
+    );
+    assert_eq!(
+        router
+            .url_for("static", &[("**", "css/site v2.css")])
+            .unwrap(),
+        "/static/css/site%20v2.css"
+    );
+    assert_eq!(router.url_for("static", &[("**", "")]).unwrap(), "/static");
+    assert_eq!(*router.match_route(&Get, "/static").route, 7);
+}
+
+#[test]
+fn test_url_for_errors() {
+    let router = build_router();
+    assert_eq!(
+        router.url_for("nope", &[]),
+        Err(UrlError::UnknownRoute("nope".into()))
+    );
+    assert_eq!(
+        router.url_for("user_profile", &[]),
+        Err(UrlError::MissingParam("id".into()))
+    );
+    assert_eq!(
+        router.url_for("user_profile", &[("id", "1"), ("id", "2")]),
+        Err(UrlError::UnexpectedParam("id".into()))
+    );
+    assert_eq!(
+        router.url_for("about", &[("x", "1")]),
+        Err(UrlError::UnexpectedParam("x".into()))
+    );
+    assert_eq!(
+        router.url_for("thumb", &[("*", "a")]),
+        Err(UrlError::MissingParam("*".into()))
+    );
+    assert_eq!(
+        router.url_for("user_profile", &[("id", "-1")]),
+        Err(UrlError::InvalidParam {
+            name: "id".into(),
+            value: "-1".into()
+        })
+    );
+}
+
+#[test]
+fn test_url_for_empty_values() {
+    let router = build_router();
+    let empty = |name: &str| {
+        Err(UrlError::InvalidParam {
+            name: name.into(),
+            value: "".into(),
+        })
+    };
+    assert_eq!(
+        router.url_for("comment", &[("post", ""), ("comment", "3")]),
+        empty("post")
+    );
+    assert_eq!(
+        router.url_for("comment", &[("post", "7"), ("comment", "")]),
+        empty("comment")
+    );
+    assert_eq!(router.url_for("report", &[("name", "")]), empty("name"));
+    assert_eq!(
+        router.url_for("thumb", &[("*", "a"), ("*", "")]),
+        empty("*")
+    );
+
+    // every url that is generated routes back to its route
+    for (name, params, route) in [
+        ("comment", &[("post", "a"), ("comment", "b")][..], 5),
+        ("report", &[("name", "q")], 4),
+        ("thumb", &[("*", "a"), ("*", "b")], 6),
+        ("static", &[("**", "")], 7),
+    ] {
+        let url = router.url_for(name, params).unwrap();
+        assert_eq!(*router.match_route(&Get, &url).route, route, "{url}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "duplicate route name: a")]
+fn test_duplicate_route_name() {
+    let mut builder = RouterBuilder::new(0);
+    builder.add_named_route("a", &Get, "/a", 1);
+    builder.add_named_route("a", &Post, "/b", 2);
+    builder.build();
+}
+
+#[test]
+fn test_replaced_route_drops_name() {
+    let mut builder = RouterBuilder::new(0);
+    builder.add_named_route("old", &Get, "/users/:id", 1);
+    builder.add_route(&Get, "/users/:user", 2);
+    // the replacing route may take over a name only the replaced route used
+    builder.add_named_route("a", &Get, "/a", 3);
+    builder.add_named_route("a", &Get, "/a", 4);
+    let router = builder.build();
+
+    assert_eq!(
+        router.url_for("old", &[("id", "1")]),
+        Err(UrlError::UnknownRoute("old".into()))
+    );
+    assert_eq!(*router.match_route(&Get, "/users/1").route, 2);
+    assert_eq!(router.url_for("a", &[]).unwrap(), "/a");
+    assert_eq!(*router.match_route(&Get, "/a").route, 4);
+}
+
+#[test]
+fn test_name_freed_by_replaced_route() {
+    let mut builder = RouterBuilder::new(0);
+    builder.add_named_route("a", &Get, "/a", 1);
+    builder.add_named_route("a", &Get, "/b", 2);
+    builder.add_route(&Get, "/a", 3);
+    let router = builder.build();
+    assert_eq!(router.url_for("a", &[]).unwrap(), "/b");
+}
+
+// ---------------------------------------------------------------------
+// SERVER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_url_for_in_handler() {
+    let response = exchange("GET /go/42");
+    assert!(response.starts_with("HTTP/1.1 303"), "{response}");
+    assert!(
+        response.contains("location: /api/users/42\r\n"),
+        "{response}"
+    );
+
+    let response = exchange("GET /go/x");
+    assert!(response.starts_with("HTTP/1.1 500"), "{response}");
+    assert!(
+        response.ends_with("invalid route param id: \"x\""),
+        "{response}"
+    );
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_router() -> Router<usize> {
+    let mut builder = RouterBuilder::new(0);
+    builder.add_named_route("home", &Get, "/", 1);
+    builder.add_named_route("about", &Get, "/about", 2);
+    builder.add_named_route("user_profile", &Get, "/users/:id<u64>/profile", 3);
+    builder.add_named_route("report", &Get, "/reports/:name.json", 4);
+    builder.add_named_route("thumb", &Get, "/thumbs/*/*", 6);
+    builder.add_named_route("static", &Get, "/static/**", 7);
+    builder.add_route(&Get, "/unnamed", 8);
+
+    let mut api = RouterBuilder::group();
+    let mut posts = RouterBuilder::group();
+    posts.add_named_route("comment", &Get, "/comments/:comment", 5);
+    api.nest("/posts/:post", posts);
+    builder.nest("/api", api);
+    builder.build()
+}
+
+fn build_server() -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.scope("/api", |api| {
+        api.named_route("user", Get, "/users/:id<u32>", |_, res| {
+            res.ok(Headers::empty(), "user")
+        });
+    });
+    app.route(Get, "/go/:id", |ctx, res| {
+        let id = ctx.params.get("id").unwrap();
+        match ctx.url_for("user", &[("id", id)]) {
+            Ok(url) => {
+                let mut headers = Headers::new();
+                headers.add("location", url.into_bytes());
+                res.send0(&Status::of(303), &headers)
+            }
+            Err(e) => res.send(&Status::of(500), Headers::empty(), e.to_string()),
+        }
+    });
+    app.build()
+}
+
+fn exchange(request_line: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let _ = build_server().handle(&stream);
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("{request_line} HTTP/1.1\r\nconnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
@@ -0,0 +1,177 @@
+use khttp::negotiate::Negotiator;
+use khttp::{Headers, Method::*, Server, Status};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+const OFFERED: [&str; 3] = ["application/json", "text/html", "text/csv"];
+
+#[test]
+fn test_media_type() {
+    let cases = [
+        ("text/html", Some("text/html")),
+        ("text/*", Some("text/html")),
+        ("text/*;q=0.5, text/csv", Some("text/csv")),
+        ("*/*", Some("application/json")),
+        ("*/*;q=0.1, text/html;q=0.2", Some("text/html")),
+        // the most specific range decides an offer's weight
+        ("text/*, text/html;q=0", Some("text/csv")),
+        ("*/*, application/json;q=0", Some("text/html")),
+        ("text/html;level=1", None),
+        ("image/png", None),
+        ("text/html;q=0", None),
+        // ties go to the earlier offer
+        ("text/csv, text/html", Some("text/html")),
+    ];
+    for (accept, expected) in cases {
+        let headers = request(&[("accept", accept)]);
+        let mut negotiator = Negotiator::new(&headers);
+        assert_eq!(negotiator.media_type(&OFFERED), expected, "{accept}");
+    }
+}
+
+#[test]
+fn test_missing_or_invalid_header_accepts_anything() {
+    let headers = request(&[]);
+    assert_eq!(
+        Negotiator::new(&headers).media_type(&OFFERED),
+        Some("application/json")
+    );
+
+    let headers = request(&[("accept", "not a media type")]);
+    assert_eq!(
+        Negotiator::new(&headers).media_type(&OFFERED),
+        Some("application/json")
+    );
+
+    let headers = request(&[("accept", "*/*")]);
+    assert_eq!(Negotiator::new(&headers).media_type(&[]), None);
+}
+
+#[test]
+fn test_offer_params() {
+    let headers = request(&[("accept", "text/html;level=1, text/html;q=0.5")]);
+    let offered = ["text/html", "text/html;level=1"];
+    assert_eq!(
+        Negotiator::new(&headers).media_type(&offered),
+        Some("text/html;level=1")
+    );
+}
+
+#[test]
+fn test_language() {
+    let offered = ["en-US", "de", "fr-CA"];
+    let cases = [
+        ("de", Some("de")),
+        ("fr", Some("fr-CA")),
+        ("EN-us;q=0.5, de;q=0.4", Some("en-US")),
+        ("en;q=0.8, *;q=0.9", Some("de")),
+        ("*, en;q=0", Some("de")),
+        ("ja", None),
+    ];
+    for (accept, expected) in cases {
+        let headers = request(&[("accept-language", accept)]);
+        let mut negotiator = Negotiator::new(&headers);
+        assert_eq!(negotiator.language(&offered), expected, "{accept}");
+    }
+}
+
+#[test]
+fn test_charset() {
+    let offered = ["utf-8", "iso-8859-1"];
+    let cases = [
+        ("ISO-8859-1", Some("iso-8859-1")),
+        ("utf-8;q=0.5, *", Some("iso-8859-1")),
+        ("ascii", None),
+    ];
+    for (accept, expected) in cases {
+        let headers = request(&[("accept-charset", accept)]);
+        let mut negotiator = Negotiator::new(&headers);
+        assert_eq!(negotiator.charset(&offered), expected, "{accept}");
+    }
+}
+
+#[test]
+fn test_vary() {
+    let headers = request(&[]);
+    let mut negotiator = Negotiator::new(&headers);
+    negotiator.media_type(&OFFERED);
+    negotiator.language(&["en"]);
+    negotiator.media_type(&OFFERED);
+    assert_eq!(negotiator.vary(), ["accept", "accept-language"]);
+
+    let mut response = Headers::new_nodate();
+    response.add(Headers::VARY, b"Accept-Encoding, Accept".to_vec());
+    negotiator.append_vary(&mut response);
+    let vary: Vec<&[u8]> = response.get_all("vary").map(|(_, v)| v.as_ref()).collect();
+    assert_eq!(vary, [&b"Accept-Encoding, Accept"[..], b"accept-language"]);
+
+    let mut response = Headers::new_nodate();
+    response.add(Headers::VARY, b"*".to_vec());
+    negotiator.append_vary(&mut response);
+    assert_eq!(response.get_all("vary").count(), 1);
+}
+
+#[test]
+fn test_negotiated_route() {
+    let response = exchange("accept: text/csv;q=0.9, application/json;q=0.8\r\n");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(
+        response.contains("content-type: text/csv\r\n"),
+        "{response}"
+    );
+    assert!(response.contains("vary: accept\r\n"), "{response}");
+    assert!(response.ends_with("a,b"), "{response}");
+
+    let response = exchange("accept: image/png\r\n");
+    assert!(response.starts_with("HTTP/1.1 406"), "{response}");
+    assert!(response.contains("vary: accept\r\n"), "{response}");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn request(pairs: &[(&str, &str)]) -> Headers<'static> {
+    let mut headers = Headers::new_nodate();
+    for (name, value) in pairs {
+        headers.add(name.to_string(), value.as_bytes().to_vec());
+    }
+    headers
+}
+
+fn build_server() -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.route(Get, "/data", |ctx, res| {
+        let mut negotiator = Negotiator::new(&ctx.headers);
+        let mut headers = Headers::new();
+        let choice = negotiator.media_type(&OFFERED);
+        negotiator.append_vary(&mut headers);
+        let body = match choice {
+            Some("application/json") => "[\"a\",\"b\"]",
+            Some("text/html") => "<p>a b</p>",
+            Some(_) => "a,b",
+            None => return res.send0(&Status::NOT_ACCEPTABLE, &headers),
+        };
+        headers.add(Headers::CONTENT_TYPE, choice.unwrap().as_bytes().to_vec());
+        res.ok(&headers, body)
+    });
+    app.build()
+}
+
+fn exchange(extra_headers: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build_server().handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("GET /data HTTP/1.1\r\nconnection: close\r\n{extra_headers}\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
@@ -1,4 +1,4 @@
-use khttp::{Headers, HttpParsingError, Method, Request};
+use khttp::{Headers, HttpParsingError, Method, ParseStatus, Request, RequestParser};
 use std::io::Read;
 
 #[cfg(feature = "client")]
@@ -125,6 +125,21 @@ fn test_request_missing_http_version() {
     );
 }
 
+#[test]
+fn test_request_invalid_path_bytes() {
+    for path in [
+        "/a\"b",
+        "/a<b>",
+        "/caf\u{e9}",
+        "/a{b}/long/enough/for/a/full/vector",
+    ] {
+        assert_parse_request_err(
+            &format!("GET {path} HTTP/1.1\r\n\r\n"),
+            HttpParsingError::MalformedStatusLine,
+        );
+    }
+}
+
 #[test]
 fn test_request_header_without_colon() {
     assert_parse_request_err(
@@ -157,6 +172,98 @@ fn test_request_unsupported_http_version() {
     );
 }
 
+// ---------------------------------------------------------------------
+// INCREMENTAL
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_incremental_byte_by_byte() {
+    let input = b"POST /a?b=c HTTP/1.1\r\nhost: x\r\ncontent-length: 5\r\nX-Empty:\r\n\r\nhello";
+    let head_len = input.len() - 5;
+    let mut parser = RequestParser::new();
+
+    for end in 0..head_len {
+        match parser.parse(&input[..end]) {
+            Ok(ParseStatus::Partial(needed)) => assert!(needed >= 1),
+            other => panic!("{end}: {other:?}"),
+        }
+    }
+    let Ok(ParseStatus::Complete(req)) = parser.parse(&input[..]) else {
+        panic!("should be complete");
+    };
+    let expected = Request::parse(input).unwrap();
+    assert_eq!(req.method, expected.method);
+    assert_eq!(req.uri.as_str(), "/a?b=c");
+    assert_eq!(req.headers, expected.headers);
+    assert_eq!(req.headers.get_content_length(), Some(5));
+    assert_eq!(req.buf_offset, head_len);
+}
+
+#[test]
+fn test_incremental_needed_bytes() {
+    let mut parser = RequestParser::new();
+    let needed = |parser: &mut RequestParser, input: &[u8]| match parser.parse(input) {
+        Ok(ParseStatus::Partial(n)) => n,
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(needed(&mut parser, b"GET / HTTP/1.1\r\nhost: x"), 2);
+    assert_eq!(needed(&mut parser, b"GET / HTTP/1.1\r\nhost: x\r\n\r"), 1);
+
+    let mut parser = RequestParser::new().strict(false);
+    assert_eq!(needed(&mut parser, b"GET / HTTP/1.1\r\nhost: x"), 1);
+}
+
+#[test]
+fn test_incremental_early_error() {
+    // a malformed header is reported before the head is complete
+    let mut parser = RequestParser::new();
+    assert_eq!(
+        parser
+            .parse(b"GET / HTTP/1.1\r\nbadheader\r\nhost:")
+            .unwrap_err(),
+        HttpParsingError::MalformedHeader
+    );
+    assert_eq!(
+        parser.parse(b"GET / HTTP/1.1\r\nx: 1\nhost:").unwrap_err(),
+        HttpParsingError::BareLineFeed
+    );
+    assert_eq!(
+        parser.parse(b"GET / HTTP/9.9\r\n").unwrap_err(),
+        HttpParsingError::UnsupportedHttpVersion
+    );
+}
+
+#[test]
+fn test_incremental_reuse() {
+    let mut parser = RequestParser::new();
+    assert!(matches!(
+        parser.parse(b"GET /first HTTP/1.1\r\na: 1\r\n"),
+        Ok(ParseStatus::Partial(_))
+    ));
+    assert!(matches!(
+        parser.parse(b"GET /first HTTP/1.1\r\na: 1\r\n\r\n"),
+        Ok(ParseStatus::Complete(_))
+    ));
+
+    // reset after completion: the next request starts from scratch
+    let Ok(ParseStatus::Complete(req)) = parser.parse(b"GET /second HTTP/1.1\r\n\r\n") else {
+        panic!("should be complete");
+    };
+    assert_eq!(req.uri.path(), "/second");
+    assert_eq!(req.headers.get("a"), None);
+
+    // a shorter buffer is not a continuation, the parser starts over
+    assert!(matches!(
+        parser.parse(b"GET /third HTTP/1.1\r\nb: 2\r\nc: 3"),
+        Ok(ParseStatus::Partial(_))
+    ));
+    let Ok(ParseStatus::Complete(req)) = parser.parse(b"GET /x HTTP/1.1\r\n\r\n") else {
+        panic!("should be complete");
+    };
+    assert_eq!(req.uri.path(), "/x");
+    assert_eq!(req.headers.get("b"), None);
+}
+
 // ---------------------------------------------------------------------
 // RESPONSE OK
 // ---------------------------------------------------------------------
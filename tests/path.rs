@@ -0,0 +1,139 @@
+use khttp::{EncodedSlash, Headers, Method::*, PathNormalization, Server};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+#[test]
+fn test_normalize_default() {
+    let cases: &[(&str, &str)] = &[
+        ("/", "/"),
+        ("/user/1", "/user/1"),
+        ("/user/%31", "/user/1"),
+        ("/%7Euser/%41%2d%5f", "/~user/A-_"),
+        ("/a%20b/%e2%82%ac", "/a%20b/%E2%82%AC"),
+        ("//user///1", "/user/1"),
+        ("/a/../admin", "/admin"),
+        ("/a/./b/.", "/a/b/"),
+        ("/a/b/..", "/a/"),
+        ("/../../etc/passwd", "/etc/passwd"),
+        ("/a/%2e%2E/admin", "/admin"),
+        ("/a/.%2e", "/"),
+        ("/a//..//admin", "/admin"),
+        ("/.well-known/x", "/.well-known/x"),
+        ("*", "*"),
+    ];
+    let normalization = PathNormalization::default();
+    for (path, expected) in cases {
+        assert_eq!(
+            normalization.normalize(path).as_deref(),
+            Some(*expected),
+            "{path}"
+        );
+    }
+}
+
+#[test]
+fn test_normalize_rejects() {
+    let normalization = PathNormalization::default();
+    for path in ["/a%", "/a%2", "/a%zz", "/a%+1"] {
+        assert_eq!(normalization.normalize(path), None, "{path}");
+    }
+}
+
+#[test]
+fn test_normalize_encoded_slash_policies() {
+    let preserve = PathNormalization::default();
+    assert_eq!(
+        preserve.normalize("/a%2f..%2Fb").as_deref(),
+        Some("/a%2F..%2Fb")
+    );
+
+    let reject = PathNormalization::new().encoded_slash(EncodedSlash::Reject);
+    for path in ["/a%2Fb", "/a%2f..%2fadmin"] {
+        assert_eq!(reject.normalize(path), None, "{path}");
+    }
+
+    let decode = PathNormalization::new().encoded_slash(EncodedSlash::Decode);
+    assert_eq!(decode.normalize("/a%2f..%2Fb").as_deref(), Some("/b"));
+}
+
+#[test]
+fn test_normalize_steps_can_be_disabled() {
+    let none = PathNormalization::new()
+        .decode_unreserved(false)
+        .merge_slashes(false)
+        .remove_dot_segments(false);
+    assert_eq!(none.normalize("//a/../%31").as_deref(), Some("//a/../%31"));
+
+    let keep_slashes = PathNormalization::new().merge_slashes(false);
+    assert_eq!(keep_slashes.normalize("/a//b/..").as_deref(), Some("/a//"));
+}
+
+#[test]
+fn test_routing_uses_normalized_path() {
+    let app = build_server(Some(PathNormalization::default()));
+    assert_eq!(exchange(&app, "/public/../admin"), "200 admin");
+    assert_eq!(exchange(&app, "//admin"), "200 admin");
+    assert_eq!(exchange(&app, "/%61dmin"), "200 admin");
+    assert_eq!(exchange(&app, "/user/%31"), "200 user 1 /user/1");
+    assert_eq!(exchange(&app, "/user/a%20b"), "200 user a b /user/a%20b");
+    assert_eq!(exchange(&app, "/user/a%2Fb"), "200 user a/b /user/a%2Fb");
+    assert_eq!(
+        exchange(&app, "/user/..%2Fadmin"),
+        "200 user ../admin /user/..%2Fadmin"
+    );
+    assert_eq!(exchange(&app, "/user/%zz"), "400 ");
+}
+
+#[test]
+fn test_routing_encoded_slash_rejected() {
+    let app = build_server(Some(
+        PathNormalization::new().encoded_slash(EncodedSlash::Reject),
+    ));
+    assert_eq!(exchange(&app, "/user/a%2Fb"), "400 ");
+    assert_eq!(exchange(&app, "/user/1"), "200 user 1 /user/1");
+}
+
+#[test]
+fn test_routing_without_normalization() {
+    let app = build_server(None);
+    assert_eq!(exchange(&app, "/public/../admin"), "404 ");
+    assert_eq!(exchange(&app, "/user/%31"), "200 user 1 /user/%31");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_server(normalization: Option<PathNormalization>) -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.path_normalization(normalization);
+    app.route(Get, "/admin", |_, res| res.ok(Headers::empty(), "admin"));
+    app.route(Get, "/user/:id", |ctx, res| {
+        let body = format!("user {} {}", ctx.params.get("id").unwrap(), ctx.path());
+        res.ok(Headers::empty(), body)
+    });
+    app.build()
+}
+
+/// Returns "<status code> <body>".
+fn exchange(app: &Server, path: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let request = format!("GET {path} HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        raw
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    app.handle(&stream).unwrap();
+    drop(stream);
+
+    let raw = client.join().unwrap();
+    let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+    format!("{} {body}", &head[9..12])
+}
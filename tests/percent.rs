@@ -0,0 +1,47 @@
+use khttp::percent::{
+    decode, decode_bytes, decode_form, encode_component, encode_form, encode_path_segment,
+};
+use std::borrow::Cow;
+
+#[test]
+fn test_decode() {
+    assert_eq!(decode("plain").as_deref(), Some("plain"));
+    assert!(matches!(decode("plain"), Some(Cow::Borrowed(_))));
+    assert_eq!(decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+    assert_eq!(decode("%e2%82%ac").as_deref(), Some("€"));
+    assert_eq!(decode("a+b").as_deref(), Some("a+b"));
+}
+
+#[test]
+fn test_decode_invalid() {
+    for s in ["%", "%2", "%zz", "abc%g1", "%ff"] {
+        assert_eq!(decode(s), None, "{s}");
+    }
+    assert_eq!(decode_bytes(b"%ff%00").as_deref(), Some(&b"\xff\x00"[..]));
+    assert_eq!(decode_bytes(b"%f"), None);
+}
+
+#[test]
+fn test_decode_form() {
+    assert_eq!(decode_form("a+b%2Bc"), "a b+c");
+    assert_eq!(decode_form("100%"), "100%");
+    assert_eq!(decode_form("%zz%41"), "%zzA");
+    assert_eq!(decode_form("%ff"), "\u{fffd}");
+    assert!(matches!(decode_form("plain"), Cow::Borrowed(_)));
+}
+
+#[test]
+fn test_encode() {
+    assert_eq!(encode_component("a b/c?d=é"), "a%20b%2Fc%3Fd%3D%C3%A9");
+    assert_eq!(encode_path_segment("a b/c:d@e&f"), "a%20b%2Fc:d@e&f");
+    assert_eq!(encode_form("a b&c=d+e"), "a+b%26c%3Dd%2Be");
+    assert!(matches!(encode_component("safe-._~"), Cow::Borrowed(_)));
+}
+
+#[test]
+fn test_roundtrip() {
+    for s in ["", "hello world", "ü/€?&=+%", "\u{1f980} x"] {
+        assert_eq!(decode(&encode_component(s)).as_deref(), Some(s));
+        assert_eq!(decode_form(&encode_form(s)), s);
+    }
+}
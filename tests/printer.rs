@@ -112,6 +112,19 @@ fn test_write_response_bytes_chunked() {
     );
 }
 
+#[test]
+fn test_write_response_head() {
+    let mut headers = Headers::new_nodate();
+    headers.add("foo", b"bar");
+    let mut w = MockWriter::new();
+    HttpPrinter::write_response_head(&mut w, &Status::OK, &headers, 1234).unwrap();
+
+    assert_eq!(
+        w.as_str(),
+        "HTTP/1.1 200 OK\r\nfoo: bar\r\ncontent-length: 1234\r\n\r\n"
+    );
+}
+
 #[test]
 fn test_100_continue() {
     let mut w = MockWriter::new();
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 fn make(uri_str: &str) -> khttp::RequestUri<'static> {
     let request = format!("GET {uri_str} HTTP/1.1\r\nHost: foo\r\n\r\n");
     let request = Box::leak(request.into_boxed_str());
@@ -184,3 +186,70 @@ fn test_display_trait() {
 
     assert_eq!(formatted, uri_str);
 }
+
+// ---------------------------------------------------------------------
+// query pairs
+// ---------------------------------------------------------------------
+
+#[test]
+fn query_pairs_decoded() {
+    let uri = make("/search?q=hello+world&lang=%F0%9F%A6%80&empty=&flag&&a%3Db=c%26d");
+    let pairs: Vec<(String, String)> = uri
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+
+    assert_eq!(
+        pairs,
+        [
+            ("q", "hello world"),
+            ("lang", "🦀"),
+            ("empty", ""),
+            ("flag", ""),
+            ("a=b", "c&d"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+    );
+}
+
+#[test]
+fn query_pairs_borrow_when_possible() {
+    let uri = make("/x?plain=value&encoded=a+b");
+    let mut pairs = uri.query_pairs();
+
+    let (k, v) = pairs.next().unwrap();
+    assert!(matches!(k, Cow::Borrowed("plain")));
+    assert!(matches!(v, Cow::Borrowed("value")));
+    let (_, v) = pairs.next().unwrap();
+    assert!(matches!(v, Cow::Owned(_)));
+    assert!(pairs.next().is_none());
+}
+
+#[test]
+fn query_pairs_lookup() {
+    let uri = make("/x?tag=a&page=2&tag=b&bad=%zz&n=x1");
+    let query = uri.query_pairs();
+
+    assert_eq!(query.get("tag").as_deref(), Some("a"));
+    assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(query.get("bad").as_deref(), Some("%zz"));
+    assert_eq!(query.get("missing"), None);
+    assert!(query.contains_key("page"));
+
+    assert_eq!(query.parse::<u32>("page"), Some(Ok(2)));
+    assert!(matches!(query.parse::<u32>("n"), Some(Err(_))));
+    assert_eq!(query.parse::<u32>("missing"), None);
+}
+
+#[test]
+fn query_pairs_without_query() {
+    assert_eq!(make("/x").query_pairs().count(), 0);
+    assert_eq!(make("/x?").query_pairs().count(), 0);
+    assert_eq!(
+        make("http://example.com?a=1")
+            .query_pairs()
+            .get("a")
+            .as_deref(),
+        Some("1")
+    );
+}
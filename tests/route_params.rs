@@ -0,0 +1,74 @@
+use khttp::{Headers, Method::*, Server};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+#[test]
+fn test_typed_param_extraction() {
+    let response = exchange("GET /orders/42/items/3");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("order 42, item 3"), "{response}");
+
+    // matches the route, but the param doesn't fit the extracted type
+    let response = exchange("GET /orders/42/items/-3");
+    assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+}
+
+#[test]
+fn test_constrained_routes() {
+    let response = exchange("GET /users/7");
+    assert!(response.ends_with("user id 7"), "{response}");
+
+    let response = exchange("GET /users/ferris");
+    assert!(response.ends_with("user name ferris"), "{response}");
+
+    let response = exchange("GET /reports/q3.json");
+    assert!(response.ends_with("report q3 as json"), "{response}");
+
+    // constraint failures fall through to the fallback route
+    let response = exchange("GET /reports/q3.xml");
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_server() -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.route(Get, "/orders/:order/items/:item", |ctx, res| {
+        let order: u64 = ctx.params.parse("order")?;
+        let item: u32 = ctx.params.parse("item")?;
+        res.ok(Headers::empty(), format!("order {order}, item {item}"))
+    });
+    app.route(Get, "/users/:id<u64>", |ctx, res| {
+        let id: u64 = ctx.params.parse("id")?;
+        res.ok(Headers::empty(), format!("user id {id}"))
+    });
+    app.route(Get, "/users/:name", |ctx, res| {
+        let name = ctx.params.get("name").unwrap();
+        res.ok(Headers::empty(), format!("user name {name}"))
+    });
+    app.route(Get, "/reports/:report<[a-z0-9]+>.json", |ctx, res| {
+        let report = ctx.params.get("report").unwrap();
+        res.ok(Headers::empty(), format!("report {report} as json"))
+    });
+    app.build()
+}
+
+fn exchange(request_line: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build_server().handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("{request_line} HTTP/1.1\r\nconnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
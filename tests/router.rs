@@ -1,4 +1,4 @@
-use khttp::{Method, Method::*, Router, RouterBuilder};
+use khttp::{Match, Method, Method::*, ParamError, RouteConflict, Router, RouterBuilder};
 
 // ---------------------------------------------------------------------
 // TESTS
@@ -100,6 +100,32 @@ fn params_extraction() {
     );
 }
 
+#[test]
+fn params_are_percent_decoded() {
+    let r = new_router(&[(Get, "/files/:name", 0)]);
+
+    assert_match_params(&r, Get, "/files/a%20b", 0, &[("name", "a b")]);
+    assert_match_params(&r, Get, "/files/dir%2Fx.txt", 0, &[("name", "dir/x.txt")]);
+    assert_match_params(&r, Get, "/files/caf%C3%A9", 0, &[("name", "café")]);
+    // not valid utf-8 / malformed: raw value is kept
+    assert_match_params(&r, Get, "/files/%ff", 0, &[("name", "%ff")]);
+    assert_match_params(&r, Get, "/files/100%", 0, &[("name", "100%")]);
+}
+
+#[test]
+fn params_get_cow_outlives_params() {
+    use std::borrow::Cow;
+
+    let r = new_router(&[(Get, "/files/:name", 0)]);
+    let (plain, decoded) = {
+        let plain = r.match_route(&Get, "/files/ab").params.get_cow("name");
+        let decoded = r.match_route(&Get, "/files/a%20b").params.get_cow("name");
+        (plain.unwrap(), decoded.unwrap())
+    };
+    assert!(matches!(plain, Cow::Borrowed("ab")));
+    assert!(matches!(decoded, Cow::Owned(ref v) if v == "a b"));
+}
+
 #[test]
 fn literal_beats_param() {
     let r = new_router(&[(Get, "/users/me", 0), (Get, "/users/:id", 1)]);
@@ -196,6 +222,362 @@ fn test_multiple_slashes() {
     assert_404(&r, Get, "//////foo////bar");
 }
 
+#[test]
+fn nest_prefixes_group_routes() {
+    let mut users = RouterBuilder::group();
+    users.add_route(&Get, "/", (0, "/"));
+    users.add_route(&Get, "/:id", (1, "/:id"));
+    let mut api = RouterBuilder::group();
+    api.nest("/users/", users);
+    api.add_route(&Get, "/health", (2, "/health"));
+    let mut b = RouterBuilder::new((404, "/404"));
+    b.nest("/api/v1", api);
+    b.add_route(&Get, "/users", (3, "/users"));
+    let r = b.build();
+
+    assert_match(&r, Get, "/api/v1/users", 0);
+    assert_match_params(&r, Get, "/api/v1/users/7", 1, &[("id", "7")]);
+    assert_match(&r, Get, "/api/v1/health", 2);
+    assert_match(&r, Get, "/users", 3);
+    assert_404(&r, Get, "/health");
+    assert_404(&r, Get, "/api/v1");
+
+    assert_eq!(
+        match_checked(&r, &Get, "/api/v1/users/7").prefix,
+        "/api/v1/users"
+    );
+    assert_eq!(match_checked(&r, &Get, "/api/v1/health").prefix, "/api/v1");
+    assert_eq!(match_checked(&r, &Get, "/users").prefix, "");
+}
+
+#[test]
+fn nest_group_fallbacks() {
+    let mut admin = RouterBuilder::group();
+    admin.set_fallback_route((11, "admin fallback"));
+    let mut api = RouterBuilder::group();
+    api.add_route(&Get, "/items", (0, "/items"));
+    api.set_fallback_route((10, "api fallback"));
+    api.nest("/admin", admin);
+    let mut b = RouterBuilder::new((404, "/404"));
+    b.nest("/api", api);
+    let r = b.build();
+
+    assert_match(&r, Get, "/api/items", 0);
+    assert_match(&r, Post, "/api/items", 10);
+    assert_match(&r, Get, "/api/nope", 10);
+    assert_match(&r, Get, "/api", 10);
+    assert_match(&r, Get, "/api/admin/x/y", 11);
+    assert_match(&r, Method::Custom("PURGE".into()), "/api/admin", 11);
+    assert_404(&r, Get, "/apix");
+    assert_404(&r, Get, "/");
+
+    let m = match_checked(&r, &Get, "/api/admin/x");
+    assert_eq!(m.prefix, "/api/admin");
+}
+
+#[test]
+fn nest_param_prefix() {
+    let mut repo = RouterBuilder::group();
+    repo.add_route(&Get, "/issues", (0, "/issues"));
+    repo.set_fallback_route((10, "repo fallback"));
+    let mut b = RouterBuilder::new((404, "/404"));
+    b.nest("/:owner/:repo", repo);
+    let r = b.build();
+
+    assert_match_params(
+        &r,
+        Get,
+        "/rust-lang/rust/issues",
+        0,
+        &[("owner", "rust-lang"), ("repo", "rust")],
+    );
+    assert_match_params(&r, Get, "/a/b/pulls", 10, &[("owner", "a"), ("repo", "b")]);
+    assert_eq!(match_checked(&r, &Get, "/a/b/issues").prefix, "/a/b");
+    assert_404(&r, Get, "/a");
+}
+
+#[test]
+fn constrained_params_coexist() {
+    let r = new_router(&[
+        (Get, "/user/:id<u64>", 0),
+        (Get, "/user/:name", 1),
+        (Get, "/user/:slug<[a-z-]+>", 2),
+    ]);
+
+    assert_match_params(&r, Get, "/user/42", 0, &[("id", "42")]);
+    assert_match_params(&r, Get, "/user/john-doe", 2, &[("slug", "john-doe")]);
+    assert_match_params(&r, Get, "/user/John", 1, &[("name", "John")]);
+    assert_match(&r, Get, "/user/-1", 1);
+    assert_match(&r, Get, "/user/+1", 1);
+    assert_match(&r, Get, "/user/18446744073709551616", 1);
+}
+
+#[test]
+fn integer_constraints() {
+    let r = new_router(&[
+        (Get, "/u8/:n<u8>", 0),
+        (Get, "/i8/:n<i8>", 1),
+        (Get, "/i64/:n<i64>", 2),
+    ]);
+
+    assert_match(&r, Get, "/u8/255", 0);
+    assert_404(&r, Get, "/u8/256");
+    assert_match(&r, Get, "/i8/-128", 1);
+    assert_404(&r, Get, "/i8/128");
+    assert_404(&r, Get, "/i8/-");
+    assert_match(&r, Get, "/i64/-9223372036854775808", 2);
+    assert_404(&r, Get, "/i64/1.5");
+}
+
+#[test]
+fn class_constraints() {
+    let r = new_router(&[
+        (Get, "/year/:y<[0-9]{4}>", 0),
+        (Get, "/code/:c<[A-Z_]{2,3}>", 1),
+        (Get, "/opt/:o<[a-c]?>", 2),
+    ]);
+
+    assert_match(&r, Get, "/year/2024", 0);
+    assert_404(&r, Get, "/year/202");
+    assert_404(&r, Get, "/year/20245");
+    assert_match(&r, Get, "/code/A_", 1);
+    assert_match(&r, Get, "/code/ABC", 1);
+    assert_404(&r, Get, "/code/ABCD");
+    assert_404(&r, Get, "/code/abc");
+    assert_match(&r, Get, "/opt/", 2);
+    assert_match(&r, Get, "/opt/b", 2);
+    assert_404(&r, Get, "/opt/d");
+}
+
+#[test]
+fn constraints_apply_to_decoded_values() {
+    let r = new_router(&[(Get, "/tag/:t<[a-z ]+>", 0)]);
+    assert_match_params(&r, Get, "/tag/a%20b", 0, &[("t", "a b")]);
+    assert_404(&r, Get, "/tag/a%2Fb");
+}
+
+#[test]
+fn suffix_params() {
+    let r = new_router(&[
+        (Get, "/files/:file.json", 0),
+        (Get, "/files/:id<u32>.json", 1),
+        (Get, "/files/:file", 2),
+    ]);
+
+    assert_match_params(&r, Get, "/files/report.json", 0, &[("file", "report")]);
+    assert_match_params(&r, Get, "/files/7.json", 1, &[("id", "7")]);
+    assert_match_params(&r, Get, "/files/report.csv", 2, &[("file", "report.csv")]);
+    assert_match_params(&r, Get, "/files/.json", 2, &[("file", ".json")]);
+}
+
+#[test]
+fn constrained_param_precedence() {
+    let r = new_router(&[
+        (Get, "/a/:x/:y", 0),
+        (Get, "/a/:x<u8>/:y", 1),
+        (Get, "/a/*/*", 2),
+        (Get, "/a/b/**", 3),
+    ]);
+
+    assert_match(&r, Get, "/a/1/z", 1);
+    assert_match(&r, Get, "/a/x/z", 0);
+    assert_match(&r, Get, "/a/b/z", 3);
+}
+
+#[test]
+fn typed_param_extraction() {
+    let r = new_router(&[(Get, "/item/:id/:name", 0)]);
+    let m = match_checked(&r, &Get, "/item/12/abc");
+    assert_eq!(m.params.parse::<u64>("id"), Ok(12));
+    assert_eq!(m.params.parse::<String>("name").as_deref(), Ok("abc"));
+
+    let err = m.params.parse::<u64>("name").unwrap_err();
+    assert_eq!(err.name(), "name");
+    assert!(ParamError::is_param_error(&err.into()));
+    assert!(m.params.parse::<u64>("missing").is_err());
+}
+
+#[test]
+fn tree_agrees_with_linear_matcher() {
+    const SEGMENTS: [&str; 10] = ["a", "b", "", ":x", ":n<u8>", ":f.json", "*", "**", "a", "7"];
+    const PARTS: [&str; 8] = ["a", "b", "", "7", "300", "f.json", "x", ".json"];
+    let mut rng = Lcg(7);
+    for _ in 0..200 {
+        let mut b = RouterBuilder::new((404, "/404"));
+        let mut patterns = Vec::new();
+        for id in 0..rng.below(30) {
+            let len = 1 + rng.below(4);
+            let path: Vec<&str> = (0..len).map(|_| SEGMENTS[rng.below(10)]).collect();
+            patterns.push(format!("/{}", path.join("/")));
+            b.add_route(&Get, patterns.last().unwrap(), (id, ""));
+        }
+        let router = b.build();
+        for _ in 0..50 {
+            let len = 1 + rng.below(5);
+            let uri: Vec<&str> = (0..len).map(|_| PARTS[rng.below(8)]).collect();
+            let uri = format!("/{}", uri.join("/"));
+            let m = router.match_route(&Get, &uri);
+            let linear = router.match_route_linear(&Get, &uri);
+            assert_eq!(m.route, linear.route, "{uri} with {patterns:?}");
+            assert!(m.params.iter().eq(linear.params.iter()), "{uri}");
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "invalid route param constraint")]
+fn invalid_constraint_panics() {
+    new_router(&[(Get, "/a/:x<float>", 0)]);
+}
+
+#[test]
+fn try_build_rejects_duplicates() {
+    let conflict = try_new_router(&[(Get, "/a/:x", 0), (Post, "/a/:y", 1), (Get, "/a/:y", 2)]);
+    assert_eq!(
+        conflict.err(),
+        Some(RouteConflict::Duplicate {
+            method: Get,
+            route: "/a/:y".into(),
+            existing: "/a/:x".into(),
+        })
+    );
+    assert!(try_new_router(&[(Get, "/a", 0), (Get, "a", 1)]).is_err());
+    assert!(try_new_router(&[(Get, "/a/:x<u8>", 0), (Get, "/a/:x", 1)]).is_ok());
+
+    // `build` keeps the last one
+    let r = new_router(&[(Get, "/a/:x", 0), (Get, "/a/:y", 1)]);
+    assert_match_params(&r, Get, "/a/1", 1, &[("y", "1")]);
+}
+
+#[test]
+fn try_build_rejects_shadowed_routes() {
+    let shadowed = |routes: &[RouteSpec]| match try_new_router(routes) {
+        Err(RouteConflict::Shadowed { route, by, .. }) => Some((route, by)),
+        Err(e) => panic!("{e}"),
+        Ok(_) => None,
+    };
+    let pair = |a: &str, b: &str| Some((a.to_string(), b.to_string()));
+
+    assert_eq!(
+        shadowed(&[(Get, "/a/*", 0), (Get, "/a/:id", 1)]),
+        pair("/a/*", "/a/:id")
+    );
+    assert_eq!(
+        shadowed(&[(Get, "/a/:id", 0), (Get, "/a/*", 1)]),
+        pair("/a/*", "/a/:id")
+    );
+    assert_eq!(
+        shadowed(&[(Get, "/*/*", 0), (Get, "/:x/:y", 1)]),
+        pair("/*/*", "/:x/:y")
+    );
+    assert_eq!(
+        shadowed(&[(Get, "/:x/b", 0), (Get, "/a/:y", 1), (Get, "/*/*", 2)]),
+        None
+    );
+    assert_eq!(
+        shadowed(&[
+            (Get, "/:n<u8>/x", 0),
+            (Get, "/:m<u8>/*", 1),
+            (Get, "/**", 2)
+        ]),
+        None
+    );
+    assert_eq!(shadowed(&[(Get, "/f/:a.json", 0), (Get, "/f/**", 1)]), None);
+    assert_eq!(shadowed(&[(Get, "/f/*", 0), (Get, "/f/:a.json", 1)]), None);
+    assert_eq!(shadowed(&[(Get, "/a/*", 0), (Post, "/a/:id", 1)]), None);
+}
+
+#[test]
+fn shadowed_routes_never_match() {
+    const SEGMENTS: [&str; 8] = ["a", "", ":x", ":n<u8>", ":f.json", "*", "**", "7"];
+    const PARTS: [&str; 7] = ["a", "", "7", "300", "f.json", "x", ".json"];
+    let mut rng = Lcg(11);
+    let mut found = 0;
+    for _ in 0..3000 {
+        let routes: Vec<RouteSpec> = (0..2 + rng.below(3))
+            .map(|id| {
+                let len = 1 + rng.below(3);
+                let path: Vec<&str> = (0..len).map(|_| SEGMENTS[rng.below(8)]).collect();
+                let path: &'static str = format!("/{}", path.join("/")).leak();
+                (Get, path, id)
+            })
+            .collect();
+        let Err(RouteConflict::Shadowed { route, .. }) = try_new_router(&routes) else {
+            continue;
+        };
+        found += 1;
+        let router = new_router(&routes);
+        for _ in 0..30 {
+            let len = 1 + rng.below(4);
+            let uri: Vec<&str> = (0..len).map(|_| PARTS[rng.below(7)]).collect();
+            let uri = format!("/{}", uri.join("/"));
+            let m = router.match_route(&Get, &uri);
+            assert_ne!(m.route.1, route, "{uri} with {routes:?}");
+        }
+    }
+    assert!(found > 20, "{found}");
+}
+
+#[test]
+fn routes_lists_registered_routes() {
+    let mut b = RouterBuilder::new(0);
+    b.add_route(&Get, "users", 1);
+    b.add_named_route("user", &Get, "/users/:id", 2);
+    let mut api = RouterBuilder::group();
+    api.add_route(&Post, "/items/:a", 3);
+    api.add_route(&Post, "/items/:b", 4);
+    b.nest("/api", api);
+    let mut blog = RouterBuilder::group();
+    blog.add_route(&Get, "/", 5);
+    b.host("blog.test", blog);
+    let router = b.build();
+
+    let routes: Vec<_> = router
+        .routes()
+        .map(|r| {
+            (
+                &r.method,
+                r.path.as_str(),
+                r.name.as_deref(),
+                r.host.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        routes,
+        [
+            (&Get, "/users", None, None),
+            (&Get, "/users/:id", Some("user"), None),
+            (&Post, "/api/items/:b", None, None),
+            (&Get, "/", None, Some("blog.test")),
+        ]
+    );
+}
+
+#[test]
+fn match_reports_pattern_and_name() {
+    let mut b = RouterBuilder::new(0);
+    b.add_route(&Get, "/health", 1);
+    b.add_named_route("user", &Get, "/users/:id", 2);
+    let mut api = RouterBuilder::group();
+    api.add_route(&Get, "/items/*", 3);
+    api.set_fallback_route(4);
+    b.nest("/api/:version", api);
+    let router = b.build();
+
+    let cases = [
+        ("/health", Some("/health"), None),
+        ("/users/7", Some("/users/:id"), Some("user")),
+        ("/api/v1/items/x", Some("/api/:version/items/*"), None),
+        ("/api/v1/nope", None, None),
+        ("/nope", None, None),
+    ];
+    for (uri, pattern, name) in cases {
+        let m = router.match_route(&Get, uri);
+        assert_eq!((m.pattern, m.name), (pattern, name), "{uri}");
+    }
+}
+
 // ---------------------------------------------------------------------
 // UTILS
 // ---------------------------------------------------------------------
@@ -211,8 +593,16 @@ fn new_router(routes: &[RouteSpec]) -> MockRouter {
     b.build()
 }
 
+fn try_new_router(routes: &[RouteSpec]) -> Result<MockRouter, RouteConflict> {
+    let mut b = RouterBuilder::new((404, "/404"));
+    for (m, pat, id) in routes {
+        b.add_route(m, pat, (*id, *pat));
+    }
+    b.try_build()
+}
+
 fn assert_match(router: &MockRouter, method: Method, uri: &str, expected_idx: usize) {
-    let m = router.match_route(&method, uri);
+    let m = match_checked(router, &method, uri);
     assert_eq!(m.route.0, expected_idx, "URI: {uri}");
 }
 
@@ -223,7 +613,7 @@ fn assert_match_params(
     expected_idx: usize,
     expected_params: &[(&str, &str)],
 ) {
-    let m = router.match_route(&method, uri);
+    let m = match_checked(router, &method, uri);
     assert_eq!(m.route.0, expected_idx, "URI: {}", uri);
     for (k, v) in expected_params {
         assert_eq!(
@@ -234,9 +624,46 @@ fn assert_match_params(
     }
 }
 
+/// Matches with the radix tree, checking that the linear reference matcher agrees.
+fn match_checked<'a, 'r>(
+    router: &'a MockRouter,
+    method: &Method,
+    uri: &'r str,
+) -> Match<'a, 'r, (usize, &'static str)> {
+    let m = router.match_route(method, uri);
+    let linear = router.match_route_linear(method, uri);
+    assert_eq!(
+        m.route, linear.route,
+        "tree/linear route mismatch for {uri}"
+    );
+    assert!(
+        m.params.iter().eq(linear.params.iter()),
+        "tree/linear params mismatch for {uri}: {:?} vs {:?}",
+        m.params,
+        linear.params,
+    );
+    assert_eq!(
+        m.prefix, linear.prefix,
+        "tree/linear prefix mismatch for {uri}"
+    );
+    m
+}
+
 fn assert_404(router: &MockRouter, method: Method, uri: &str) {
     assert!(
-        router.match_route(&method, uri).route.0 == 404,
+        match_checked(router, &method, uri).route.0 == 404,
         "expected 404 for URI {uri}",
     );
 }
+
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+}
@@ -0,0 +1,94 @@
+use khttp::{Headers, Method::*, Server, Status};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+#[test]
+fn test_scoped_routes() {
+    let response = exchange("GET /api/v1/users/7");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("user 7 /api/v1 /users/7"), "{response}");
+
+    let response = exchange("GET /api/v1");
+    assert!(response.ends_with("index /api/v1 /"), "{response}");
+
+    let response = exchange("GET /api/v1/admin/stats");
+    assert!(
+        response.ends_with("stats /api/v1/admin /stats"),
+        "{response}"
+    );
+
+    let response = exchange("GET /users/7");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("top-level /users/7"), "{response}");
+}
+
+#[test]
+fn test_scope_fallbacks() {
+    let response = exchange("GET /api/v1/nope");
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+    assert!(response.ends_with("api: no route for /nope"), "{response}");
+
+    // nested scopes without a fallback use the enclosing one
+    let response = exchange("DELETE /api/v1/admin/stats");
+    assert!(
+        response.ends_with("api: no route for /admin/stats"),
+        "{response}"
+    );
+
+    let response = exchange("GET /nope");
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+    assert!(response.ends_with("server fallback"), "{response}");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_server() -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.scope("/api/v1", |api| {
+        api.route(Get, "/", |ctx, res| {
+            let body = format!("index {} {}", ctx.scope_prefix(), ctx.scoped_path());
+            res.ok(Headers::empty(), body)
+        });
+        api.route(Get, "/users/:id", |ctx, res| {
+            let id = ctx.params.get("id").unwrap();
+            let body = format!("user {id} {} {}", ctx.scope_prefix(), ctx.scoped_path());
+            res.ok(Headers::empty(), body)
+        });
+        api.scope("/admin", |admin| {
+            admin.route(Get, "/stats", |ctx, res| {
+                let body = format!("stats {} {}", ctx.scope_prefix(), ctx.scoped_path());
+                res.ok(Headers::empty(), body)
+            });
+        });
+        api.fallback_route(|ctx, res| {
+            let body = format!("api: no route for {}", ctx.scoped_path());
+            res.send(&Status::NOT_FOUND, Headers::empty(), body)
+        });
+    });
+    app.route(Get, "/users/:id", |ctx, res| {
+        let body = format!("top-level {}{}", ctx.scope_prefix(), ctx.scoped_path());
+        res.ok(Headers::empty(), body)
+    });
+    app.fallback_route(|_, res| res.send(&Status::NOT_FOUND, Headers::empty(), "server fallback"));
+    app.build()
+}
+
+fn exchange(request_line: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build_server().handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("{request_line} HTTP/1.1\r\nconnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
@@ -0,0 +1,133 @@
+use khttp::{Headers, Method::*, Server, Status};
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+#[test]
+fn test_send_file_full() {
+    let path = write_temp_file("full", 100_000);
+    let res = exchange(&path, "/full");
+
+    assert!(res.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(contains(&res, b"content-length: 100000\r\n"));
+    assert!(!contains(&res, b"content-range"));
+    assert_eq!(body(&res), &file_contents(100_000)[..]);
+}
+
+#[test]
+fn test_send_file_range() {
+    let path = write_temp_file("range", 1000);
+    let res = exchange(&path, "/range");
+
+    assert!(res.starts_with(b"HTTP/1.1 206 PARTIAL CONTENT\r\n"));
+    assert!(contains(&res, b"content-range: bytes 10-19/1000\r\n"));
+    assert!(contains(&res, b"content-length: 10\r\n"));
+    assert_eq!(body(&res), &file_contents(1000)[10..20]);
+}
+
+#[test]
+fn test_send_file_invalid_range() {
+    let path = write_temp_file("invalid", 10);
+    let res = exchange(&path, "/invalid");
+
+    assert!(res.starts_with(b"HTTP/1.1 416 RANGE NOT SATISFIABLE\r\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_send_file_keeps_file_cursor() {
+    let path = write_temp_file("cursor", 1000);
+    let res = exchange(&path, "/cursor");
+
+    assert!(res.starts_with(b"HTTP/1.1 206 PARTIAL CONTENT\r\n"));
+    assert_eq!(body(&res), &file_contents(1000)[10..20]);
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_server(path: PathBuf) -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+
+    let p = path.clone();
+    app.route(Get, "/full", move |_, res| {
+        let file = File::open(&p)?;
+        res.send_file(&Status::OK, Headers::empty_nodate(), &file, None)
+    });
+    let p = path.clone();
+    app.route(Get, "/range", move |_, res| {
+        let file = File::open(&p)?;
+        res.send_file(
+            &Status::of(206),
+            Headers::empty_nodate(),
+            &file,
+            Some(10..20),
+        )
+    });
+    let p = path.clone();
+    app.route(Get, "/cursor", move |_, res| {
+        let mut file = File::open(&p)?;
+        file.read_exact(&mut [0; 3])?;
+        res.send_file(
+            &Status::of(206),
+            Headers::empty_nodate(),
+            &file,
+            Some(10..20),
+        )?;
+        // the handler panics, and with it the server thread, if the cursor moved
+        assert_eq!(file.stream_position()?, 3);
+        Ok(())
+    });
+    app.route(Get, "/invalid", move |_, res| {
+        let file = File::open(&path)?;
+        match res.send_file(&Status::of(206), Headers::empty(), &file, Some(5..50)) {
+            Err(_) => res.send0(&Status::of(416), Headers::empty_nodate()),
+            Ok(()) => panic!("range should be rejected"),
+        }
+    });
+    app.build()
+}
+
+fn exchange(path: &Path, uri: &str) -> Vec<u8> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let path = path.to_path_buf();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        build_server(path).handle(&stream).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("GET {uri} HTTP/1.1\r\nconnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
+
+fn write_temp_file(name: &str, len: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("khttp-send-file-{}-{name}", std::process::id()));
+    File::create(&path)
+        .unwrap()
+        .write_all(&file_contents(len))
+        .unwrap();
+    path
+}
+
+fn file_contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn body(res: &[u8]) -> &[u8] {
+    let head_end = res.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    &res[head_end + 4..]
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
@@ -0,0 +1,107 @@
+use khttp::simd::{scan_with, Backend, Scan};
+
+const SCANS: [Scan; 4] = [Scan::Path, Scan::Uri, Scan::HeaderName, Scan::HeaderValue];
+
+#[test]
+fn test_scalar_always_supported() {
+    assert!(Backend::Scalar.is_supported());
+    assert!(Backend::Swar.is_supported());
+    assert!(Backend::detect().is_supported());
+}
+
+#[test]
+fn test_known_inputs() {
+    let cases: [(Scan, &[u8], usize); 8] = [
+        (Scan::Path, b"/foo/bar/baz/qux/quux?a=1 HTTP/1.1", 21),
+        (Scan::Path, b"/foo/bar/baz/qux/quux/\"quoted\" ", 22),
+        (Scan::Uri, b"/foo/bar/baz/qux/quux?a=1 HTTP/1.1", 25),
+        (Scan::Uri, b"/foo/bar/baz/qux/quux/caf\xc3\xa9 ", 25),
+        (Scan::HeaderName, b"x-some-long-header-name: value", 23),
+        (Scan::HeaderName, b"x-some-long-header name: value", 18),
+        (
+            Scan::HeaderValue,
+            b"text/html; q=0.9,\tcaf\xc3\xa9 and more\r\n",
+            32,
+        ),
+        (
+            Scan::HeaderValue,
+            b"some value that is long enough\x7fdel",
+            30,
+        ),
+    ];
+    for backend in supported() {
+        for (scan, input, expected) in cases {
+            let n = scan_with(backend, scan, input);
+            assert_eq!(n, expected, "{backend:?} {scan:?} {input:?}");
+        }
+    }
+}
+
+#[test]
+fn test_every_byte_at_every_position() {
+    for scan in SCANS {
+        let filler = match scan {
+            Scan::HeaderValue => b' ',
+            _ => b'a',
+        };
+        for len in [0, 1, 15, 16, 17, 31, 32, 33, 64, 80] {
+            for pos in 0..len {
+                for b in 0..=255u8 {
+                    let mut buf = vec![filler; len];
+                    buf[pos] = b;
+                    assert_matches_scalar(scan, &buf);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_random_inputs() {
+    let mut rng = Lcg(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        let len = (rng.next() % 200) as usize;
+        // mostly valid bytes, so the scans get past the first vector
+        let common = b"abcdefXYZ0129-._~/?:;=&%! \t\"";
+        let buf: Vec<u8> = (0..len)
+            .map(|_| match rng.next() % 64 {
+                0 => rng.next() as u8,
+                _ => common[rng.next() as usize % common.len()],
+            })
+            .collect();
+        for scan in SCANS {
+            assert_matches_scalar(scan, &buf);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn supported() -> Vec<Backend> {
+    Backend::ALL
+        .into_iter()
+        .filter(|b| b.is_supported())
+        .collect()
+}
+
+fn assert_matches_scalar(scan: Scan, buf: &[u8]) {
+    let expected = scan_with(Backend::Scalar, scan, buf);
+    for backend in supported() {
+        let n = scan_with(backend, scan, buf);
+        assert_eq!(n, expected, "{backend:?} {scan:?} {buf:?}");
+    }
+}
+
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
@@ -0,0 +1,229 @@
+use khttp::{BodyReader, Headers, HttpParsingError, HttpParsingError::*, Request, Server};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// PARSER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_bare_lf_rejected() {
+    let cases = [
+        "GET / HTTP/1.1\nhost: a\r\n\r\n",
+        "GET / HTTP/1.1\r\nhost: a\n\r\n",
+        "GET / HTTP/1.1\r\nhost: a\r\n\n",
+        "GET / HTTP/1.1\r\nx: 1\ncontent-length: 5\r\n\r\nhello",
+    ];
+    for input in cases {
+        assert_strict_err(input, BareLineFeed);
+    }
+
+    // lenient mode skips lines not ending in CRLF
+    let req = Request::parse_lenient(cases[3].as_bytes()).unwrap();
+    assert_eq!(req.headers.get("x"), None);
+    assert_eq!(req.headers.get_content_length(), Some(5));
+}
+
+#[test]
+fn test_request_line_must_end_in_crlf() {
+    assert_strict_err("GET / HTTP/1.1XX\r\n\r\n", MalformedStatusLine);
+    assert_strict_err("GET / HTTP/1.1\r", UnexpectedEof);
+}
+
+#[test]
+fn test_invalid_content_length_rejected() {
+    for value in [
+        "",
+        "abc",
+        "+5",
+        "-1",
+        "0x10",
+        "5 5",
+        "1,",
+        "99999999999999999999999",
+    ] {
+        let input = format!("POST / HTTP/1.1\r\ncontent-length: {value}\r\n\r\n");
+        assert_strict_err(&input, InvalidContentLength);
+    }
+}
+
+#[test]
+fn test_conflicting_content_length_rejected() {
+    assert_strict_err(
+        "POST / HTTP/1.1\r\ncontent-length: 5\r\ncontent-length: 6\r\n\r\nhello!",
+        InvalidContentLength,
+    );
+    assert_strict_err(
+        "POST / HTTP/1.1\r\ncontent-length: 5, 6\r\n\r\nhello!",
+        InvalidContentLength,
+    );
+
+    // identical values are unambiguous
+    for input in [
+        "POST / HTTP/1.1\r\ncontent-length: 5\r\nContent-Length: 5\r\n\r\nhello",
+        "POST / HTTP/1.1\r\ncontent-length: 5,5\r\n\r\nhello",
+    ] {
+        let req = Request::parse(input.as_bytes()).unwrap();
+        assert_eq!(req.headers.get_content_length(), Some(5), "{input}");
+    }
+}
+
+#[test]
+fn test_content_length_with_transfer_encoding_rejected() {
+    assert_strict_err(
+        "POST / HTTP/1.1\r\ncontent-length: 3\r\ntransfer-encoding: chunked\r\n\r\n0\r\n\r\n",
+        ContentLengthWithTransferEncoding,
+    );
+    assert_strict_err(
+        "POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\ncontent-length: 3\r\n\r\n0\r\n\r\n",
+        ContentLengthWithTransferEncoding,
+    );
+}
+
+#[test]
+fn test_transfer_encoding_must_end_in_chunked() {
+    for te in [
+        "gzip",
+        "chunked, gzip",
+        "chunked, chunked",
+        "",
+        "xchunked",
+        "chunked\r\ntransfer-encoding: identity",
+    ] {
+        let input = format!("POST / HTTP/1.1\r\ntransfer-encoding: {te}\r\n\r\n");
+        assert_strict_err(&input, InvalidTransferEncoding);
+    }
+    assert_strict_err(
+        "POST / HTTP/1.0\r\ntransfer-encoding: chunked\r\n\r\n0\r\n\r\n",
+        InvalidTransferEncoding,
+    );
+
+    for te in [
+        "chunked",
+        "gzip, chunked",
+        "gzip\r\ntransfer-encoding: Chunked",
+    ] {
+        let input = format!("POST / HTTP/1.1\r\ntransfer-encoding: {te}\r\n\r\n");
+        let req = Request::parse(input.as_bytes()).unwrap();
+        assert!(req.headers.is_transfer_encoding_chunked(), "{te}");
+    }
+}
+
+#[test]
+fn test_lenient_prefers_chunked_over_content_length() {
+    let input = b"POST / HTTP/1.1\r\ncontent-length: 4\r\ntransfer-encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+    let req = Request::parse_lenient(input).unwrap();
+    let mut body = BodyReader::from_request(&input[req.buf_offset..], &[][..], &req.headers);
+    assert_eq!(body.string().unwrap(), "hello");
+}
+
+#[test]
+fn test_strict_body_reader_rejects_ambiguous_framing() {
+    let input = b"POST / HTTP/1.1\r\ncontent-length: 4\r\ntransfer-encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+    let req = Request::parse_lenient(input).unwrap();
+    let err = BodyReader::from_request_strict(&input[req.buf_offset..], &[][..], &req.headers)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let input = b"POST / HTTP/1.1\r\ntransfer-encoding: chunked, gzip\r\n\r\n";
+    let req = Request::parse_lenient(input).unwrap();
+    let err = BodyReader::from_request_strict(&input[req.buf_offset..], &[][..], &req.headers)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let input =
+        b"POST / HTTP/1.1\r\ntransfer-encoding: gzip, chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+    let req = Request::parse_lenient(input).unwrap();
+    let mut body =
+        BodyReader::from_request_strict(&input[req.buf_offset..], &[][..], &req.headers).unwrap();
+    assert_eq!(body.string().unwrap(), "hello");
+}
+
+#[test]
+fn test_invalid_chunk_sizes_rejected() {
+    for body in [
+        "+5\r\nhello\r\n0\r\n\r\n",
+        "5\nhello\r\n0\r\n\r\n",
+        "\r\n",
+        "x\r\n",
+    ] {
+        let mut reader = BodyReader::new_chunked(body.as_bytes(), &[][..]);
+        let err = reader.string().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData, "{body:?}");
+    }
+
+    let mut reader = BodyReader::new_chunked(b"5 ;ext=1\r\nhello\r\n0\r\n\r\n", &[][..]);
+    assert_eq!(reader.string().unwrap(), "hello");
+}
+
+// ---------------------------------------------------------------------
+// SERVER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_server_rejects_smuggling_attempts() {
+    let attempts = [
+        // CL.TE
+        "POST / HTTP/1.1\r\ncontent-length: 6\r\ntransfer-encoding: chunked\r\n\r\n0\r\n\r\nGET /admin HTTP/1.1\r\n\r\n",
+        // TE.CL with an obfuscated coding
+        "POST / HTTP/1.1\r\ntransfer-encoding: chunked, identity\r\ncontent-length: 4\r\n\r\n5c\r\nGET /admin HTTP/1.1\r\n\r\n0\r\n\r\n",
+        // CL.CL
+        "POST / HTTP/1.1\r\ncontent-length: 0\r\ncontent-length: 44\r\n\r\nGET /admin HTTP/1.1\r\n\r\n",
+        // bare LF hiding a header from a CRLF-only proxy
+        "POST / HTTP/1.1\r\nx: y\ncontent-length: 24\r\n\r\nGET /admin HTTP/1.1\r\n\r\n",
+    ];
+    for request in attempts {
+        let response = exchange(true, request);
+        assert!(
+            response.starts_with("HTTP/1.1 400"),
+            "{request:?}: {response}"
+        );
+        assert!(!response.contains("admin"), "{request:?}: {response}");
+        assert_eq!(response.matches("HTTP/1.1").count(), 1, "{response}");
+    }
+}
+
+#[test]
+fn test_server_lenient_mode() {
+    let request = "GET /a HTTP/1.1\r\nx: y\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+    assert!(exchange(true, request).starts_with("HTTP/1.1 400"));
+    assert!(exchange(false, request).ends_with("/a"));
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn assert_strict_err(input: &str, expected: HttpParsingError) {
+    let result = Request::parse(input.as_bytes());
+    assert_eq!(result.unwrap_err(), expected, "{input:?}");
+}
+
+fn build_server(strict: bool) -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    app.strict_parsing(strict);
+    app.fallback_route(|mut ctx, res| {
+        ctx.body().vec()?;
+        res.ok(Headers::empty(), ctx.uri.as_str())
+    });
+    app.build()
+}
+
+fn exchange(strict: bool, request: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let _ = build_server(strict).handle(&stream);
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}
@@ -0,0 +1,215 @@
+use khttp::{
+    Headers, Method::*, RouteConflict, Router, RouterBuilder, Server, ServerBuilder, UnknownHost,
+};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// ---------------------------------------------------------------------
+// ROUTER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_host_selection() {
+    let router = build_router(UnknownHost::DefaultHost);
+    let route = |host: &str, path| {
+        *router
+            .match_host_route(Some(host), &Get, path)
+            .unwrap()
+            .route
+    };
+
+    assert_eq!(route("example.com", "/"), 1);
+    assert_eq!(route("EXAMPLE.com:8080", "/"), 1);
+    assert_eq!(route("example.com.", "/"), 1);
+    assert_eq!(route("user@example.com", "/"), 1);
+    assert_eq!(route("api.example.com", "/"), 2);
+    assert_eq!(route("acme.example.com", "/"), 3);
+    assert_eq!(route("a.b.example.com", "/"), 4);
+    assert_eq!(route("acme.shop.test", "/"), 5);
+    assert_eq!(route("other.test", "/"), 10);
+    assert_eq!(route("example.org", "/"), 10);
+    assert_eq!(route("[::1]:8080", "/"), 10);
+
+    let m = router.match_host_route(None, &Get, "/").unwrap();
+    assert_eq!(*m.route, 10);
+}
+
+#[test]
+fn test_host_params_and_fallbacks() {
+    let router = build_router(UnknownHost::DefaultHost);
+
+    let m = router
+        .match_host_route(Some("acme.shop.test"), &Get, "/items/9")
+        .unwrap();
+    assert_eq!(*m.route, 6);
+    let params: Vec<_> = m.params.iter().collect();
+    assert_eq!(params, [("tenant", "acme"), ("id", "9")]);
+
+    // a host's own fallback route, else the router's
+    let m = router.match_host_route(Some("acme.shop.test"), &Get, "/nope");
+    assert_eq!(*m.unwrap().route, 7);
+    let m = router.match_host_route(Some("example.com"), &Get, "/nope");
+    assert_eq!(*m.unwrap().route, 0);
+
+    // the default routes don't leak into hosts
+    let m = router.match_host_route(Some("example.com"), &Get, "/default");
+    assert_eq!(*m.unwrap().route, 0);
+    assert_eq!(*router.match_route(&Get, "/default").route, 11);
+}
+
+#[test]
+fn test_unknown_host_policy() {
+    for policy in [UnknownHost::Misdirected, UnknownHost::NotFound] {
+        let router = build_router(policy);
+        assert_eq!(router.unknown_host(), policy);
+        assert!(router
+            .match_host_route(Some("other.test"), &Get, "/")
+            .is_none());
+        assert!(router.match_host_route(None, &Get, "/").is_none());
+        assert!(router
+            .match_host_route(Some("example.com"), &Get, "/")
+            .is_some());
+    }
+}
+
+#[test]
+#[should_panic(expected = "invalid host pattern: a.*.com")]
+fn test_invalid_host_pattern() {
+    let mut builder = RouterBuilder::new(0);
+    builder.host("a.*.com", RouterBuilder::group());
+    builder.build();
+}
+
+#[test]
+fn test_duplicate_host_pattern() {
+    let duplicated = || {
+        let mut builder = RouterBuilder::new(0);
+        for (pattern, route) in [("example.com", 1), ("Example.com", 2)] {
+            let mut group = RouterBuilder::group();
+            group.add_route(&Get, "/", route);
+            builder.host(pattern, group);
+        }
+        builder
+    };
+
+    let router = duplicated().build();
+    let m = router.match_host_route(Some("example.com"), &Get, "/");
+    assert_eq!(m.map(|m| *m.route), Some(2));
+
+    assert_eq!(
+        duplicated().try_build().err(),
+        Some(RouteConflict::DuplicateHost("Example.com".into()))
+    );
+}
+
+// ---------------------------------------------------------------------
+// SERVER
+// ---------------------------------------------------------------------
+
+#[test]
+fn test_server_virtual_hosts() {
+    let response = exchange(|_| {}, "GET / HTTP/1.1\r\nhost: acme.example.com\r\n");
+    assert!(response.ends_with("tenant acme"), "{response}");
+
+    // absolute-form authority wins over the host header
+    let response = exchange(
+        |_| {},
+        "GET http://blog.test/ HTTP/1.1\r\nhost: acme.example.com\r\n",
+    );
+    assert!(response.ends_with("blog"), "{response}");
+
+    let response = exchange(|_| {}, "GET / HTTP/1.1\r\nhost: other.test\r\n");
+    assert!(response.ends_with("default"), "{response}");
+}
+
+#[test]
+fn test_server_rejects_several_host_headers() {
+    for head in [
+        "GET / HTTP/1.1\r\nhost: other.test\r\nhost: acme.example.com\r\n",
+        "GET / HTTP/1.1\r\nhost: blog.test\r\nHost: blog.test\r\n",
+        "GET http://blog.test/ HTTP/1.1\r\nhost: a.test\r\nhost: b.test\r\n",
+    ] {
+        let response = exchange(|_| {}, head);
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+    }
+}
+
+#[test]
+fn test_server_unknown_host_status() {
+    let response = exchange(
+        |app| {
+            app.unknown_host(UnknownHost::Misdirected);
+        },
+        "GET / HTTP/1.1\r\nhost: other.test\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 421"), "{response}");
+
+    let response = exchange(
+        |app| {
+            app.unknown_host(UnknownHost::NotFound);
+        },
+        "GET / HTTP/1.0\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+}
+
+// ---------------------------------------------------------------------
+// UTILS
+// ---------------------------------------------------------------------
+
+fn build_router(policy: UnknownHost) -> Router<usize> {
+    let mut builder = RouterBuilder::new(0);
+    builder.add_route(&Get, "/", 10);
+    builder.add_route(&Get, "/default", 11);
+    builder.set_unknown_host(policy);
+
+    let host = |route| {
+        let mut group = RouterBuilder::group();
+        group.add_route(&Get, "/", route);
+        group
+    };
+    builder.host("*.example.com", host(4));
+    builder.host(":tenant.example.com", host(3));
+    builder.host("example.com", host(1));
+    builder.host("api.example.com", host(2));
+
+    let mut shop = host(5);
+    shop.add_route(&Get, "/items/:id", 6);
+    shop.set_fallback_route(7);
+    builder.host(":tenant.shop.test", shop);
+    builder.build()
+}
+
+fn build_server(configure: fn(&mut ServerBuilder)) -> Server {
+    let mut app = Server::builder("127.0.0.1:0").unwrap();
+    configure(&mut app);
+    app.route(Get, "/", |_, res| res.ok(Headers::empty(), "default"));
+    app.host(":tenant.example.com", |site| {
+        site.route(Get, "/", |ctx, res| {
+            let body = format!("tenant {}", ctx.params.get("tenant").unwrap());
+            res.ok(Headers::empty(), body)
+        });
+    });
+    app.host("blog.test", |site| {
+        site.route(Get, "/", |_, res| res.ok(Headers::empty(), "blog"));
+    });
+    app.build()
+}
+
+fn exchange(configure: fn(&mut ServerBuilder), head: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let _ = build_server(configure).handle(&stream);
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!("{head}connection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    server.join().unwrap();
+    raw
+}